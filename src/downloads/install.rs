@@ -0,0 +1,109 @@
+use std::fs::File;
+use std::io;
+use std::path::{Path, PathBuf};
+use zip::ZipArchive;
+
+/// Characters refused by Windows in file names
+const FORBIDDEN_CHARS: &[char] = &['<', '>', ':', '"', '/', '\\', '|', '?', '*'];
+
+/// Name of the .osz file of a beatmapset, the one osu! gives to its folders
+///
+/// # Arguments
+/// * `set_id` - Id of the beatmapset, when known
+/// * `artist` - Artist of the song
+/// * `title` - Title of the song
+///
+/// # Returns
+/// `"{set_id} {artist} - {title}.osz"` without the characters refused in file names
+pub fn osz_file_name(set_id: Option<u32>, artist: &str, title: &str) -> String {
+    let name = match set_id {
+        Some(set_id) => format!("{} {} - {}", set_id, artist, title),
+        None => format!("{} - {}", artist, title),
+    };
+    let name: String = name
+        .chars()
+        .filter(|c| !c.is_control() && !FORBIDDEN_CHARS.contains(c))
+        .collect();
+    // Windows also refuses names ending with a dot or a space
+    format!("{}.osz", name.trim().trim_end_matches('.'))
+}
+
+/// Checks that a file is a complete .osz archive
+///
+/// Every entry is read so that its checksum is verified.
+///
+/// # Arguments
+/// * `path` - Path of the archive
+///
+/// # Returns
+/// Number of difficulties (.osu files) in the archive
+pub fn verify_osz(path: &Path) -> eyre::Result<usize> {
+    let mut archive = ZipArchive::new(File::open(path)?)?;
+    let mut difficulties = 0;
+    for index in 0..archive.len() {
+        let mut entry = archive.by_index(index)?;
+        let name = entry
+            .enclosed_name()
+            .ok_or_else(|| eyre::eyre!("chemin invalide dans l'archive : {}", entry.name()))?;
+        if name.extension().is_some_and(|extension| extension.eq_ignore_ascii_case("osu")) {
+            difficulties += 1;
+        }
+        io::copy(&mut entry, &mut io::sink())?;
+    }
+    if difficulties == 0 {
+        return Err(eyre::eyre!("l'archive ne contient aucune difficulté"));
+    }
+    Ok(difficulties)
+}
+
+/// Extracts a .osz archive in the Songs folder and removes the archive
+///
+/// # Arguments
+/// * `path` - Path of the archive
+/// * `songs_dir` - Songs folder of osu!
+///
+/// # Returns
+/// Folder of the beatmapset, named after the archive
+pub fn extract_osz(path: &Path, songs_dir: &Path) -> eyre::Result<PathBuf> {
+    let stem = path
+        .file_stem()
+        .ok_or_else(|| eyre::eyre!("nom de fichier invalide : {}", path.display()))?;
+    let destination = songs_dir.join(stem);
+    let mut archive = ZipArchive::new(File::open(path)?)?;
+    for index in 0..archive.len() {
+        let mut entry = archive.by_index(index)?;
+        // Entries going out of the folder (`../`) are refused
+        let name = entry
+            .enclosed_name()
+            .ok_or_else(|| eyre::eyre!("chemin invalide dans l'archive : {}", entry.name()))?;
+        let target = destination.join(name);
+        if entry.is_dir() {
+            std::fs::create_dir_all(&target)?;
+            continue;
+        }
+        if let Some(parent) = target.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        io::copy(&mut entry, &mut File::create(&target)?)?;
+    }
+    std::fs::remove_file(path)?;
+    Ok(destination)
+}
+
+/// Whether a beatmapset already has a folder or a pending .osz in the Songs folder
+///
+/// # Arguments
+/// * `songs_dir` - Songs folder of osu!
+/// * `set_id` - Id of the beatmapset
+pub fn is_installed(songs_dir: &Path, set_id: u32) -> bool {
+    let Ok(entries) = std::fs::read_dir(songs_dir) else {
+        return false;
+    };
+    let id = set_id.to_string();
+    let prefix = format!("{} ", set_id);
+    entries.flatten().any(|entry| {
+        let name = entry.file_name();
+        let name = name.to_string_lossy();
+        (name == id || name.starts_with(&prefix)) && !name.ends_with(".part")
+    })
+}
@@ -0,0 +1,509 @@
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use futures_util::StreamExt;
+use parking_lot::Mutex;
+use reqwest::{Client, Response, StatusCode};
+use tokio::io::AsyncWriteExt;
+use tokio::sync::{watch, Semaphore};
+use crate::downloads::install::{extract_osz, is_installed, verify_osz};
+use crate::utils::mirror::{check_response, with_range, MirrorList};
+
+pub mod install;
+pub mod view;
+#[cfg(test)]
+mod tests;
+
+/// Downloads waiting or running at the same time, further ones are refused
+pub const MAX_QUEUED: usize = 100;
+/// Downloads running in parallel by default
+pub const DEFAULT_PARALLELISM: usize = 3;
+/// Attempts after the first failure of a download
+pub const MAX_RETRIES: u32 = 4;
+/// Delay before the first retry, doubled after each failure
+#[cfg(not(test))]
+const RETRY_BASE_DELAY: Duration = Duration::from_secs(1);
+#[cfg(test)]
+const RETRY_BASE_DELAY: Duration = Duration::from_millis(10);
+/// Progress step between two notifications, in fraction of the file
+const PROGRESS_STEP: f32 = 0.01;
+
+/// Where a download comes from
+#[derive(Debug, Clone, PartialEq)]
+pub enum DownloadSource {
+    /// Beatmapset fetched from the mirrors, in the order of the settings
+    Set(u32),
+    /// Direct link, used when no beatmapset id could be found
+    Link(String),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum DownloadStatus {
+    Queued,
+    Downloading,
+    /// Waiting before the given retry
+    Retrying(u32),
+    Paused,
+    Completed,
+    /// Extracted in the Songs folder
+    Installed,
+    /// Not downloaded, the beatmapset was found in the Songs folder
+    AlreadyInstalled,
+    Failed(String),
+    Cancelled,
+}
+
+impl DownloadStatus {
+    /// Whether the download is over and won't change anymore
+    pub fn is_finished(&self) -> bool {
+        matches!(
+            self,
+            Self::Completed | Self::Installed | Self::AlreadyInstalled | Self::Failed(_) | Self::Cancelled
+        )
+    }
+
+    /// Whether the download is over and can't be resumed
+    fn is_over(&self) -> bool {
+        self.is_finished() && !matches!(self, Self::Failed(_))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DownloadItem {
+    pub id: u64,
+    pub source: DownloadSource,
+    pub title: String,
+    /// Final path of the file, written as `<path>.part` until complete
+    pub path: PathBuf,
+    /// Songs folder the archive is extracted into once downloaded
+    pub install_dir: Option<PathBuf>,
+    pub status: DownloadStatus,
+    /// Between 0.0 and 1.0, 0.0 while the size is unknown
+    pub progress: f32,
+    /// Mirror the file is coming from
+    pub mirror: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Control {
+    Run,
+    Pause,
+    Cancel,
+}
+
+struct Entry {
+    item: DownloadItem,
+    control: watch::Sender<Control>,
+}
+
+type ChangeCallback = Box<dyn Fn(Vec<DownloadItem>) + Send + Sync>;
+
+struct Inner {
+    client: Client,
+    mirrors: Mutex<Arc<MirrorList>>,
+    semaphore: Arc<Semaphore>,
+    parallelism: Mutex<usize>,
+    entries: Mutex<Vec<Entry>>,
+    next_id: AtomicU64,
+    on_change: ChangeCallback,
+}
+
+/// Queue of downloads running a bounded number at a time, with retries and resume
+#[derive(Clone)]
+pub struct DownloadManager {
+    inner: Arc<Inner>,
+}
+
+enum Outcome {
+    Completed(String),
+    Stopped,
+}
+
+/// Path of the file being written
+pub fn part_path(path: &Path) -> PathBuf {
+    let mut name = path.as_os_str().to_os_string();
+    name.push(".part");
+    PathBuf::from(name)
+}
+
+impl DownloadManager {
+    /// Creates the manager, downloads are run on the current tokio runtime
+    ///
+    /// # Arguments
+    /// * `client` - HTTP client for the direct links
+    /// * `mirrors` - Mirrors used for the beatmapsets
+    /// * `parallelism` - Downloads running at the same time
+    /// * `on_change` - Called with every download when one of them changes
+    pub fn new(
+        client: Client,
+        mirrors: Arc<MirrorList>,
+        parallelism: usize,
+        on_change: impl Fn(Vec<DownloadItem>) + Send + Sync + 'static,
+    ) -> Self {
+        let parallelism = parallelism.max(1);
+        Self {
+            inner: Arc::new(Inner {
+                client,
+                mirrors: Mutex::new(mirrors),
+                semaphore: Arc::new(Semaphore::new(parallelism)),
+                parallelism: Mutex::new(parallelism),
+                entries: Mutex::new(Vec::new()),
+                next_id: AtomicU64::new(1),
+                on_change: Box::new(on_change),
+            }),
+        }
+    }
+
+    /// Replaces the mirrors used by the next downloads
+    pub fn set_mirrors(&self, mirrors: Arc<MirrorList>) {
+        *self.inner.mirrors.lock() = mirrors;
+    }
+
+    /// Changes the number of downloads running at the same time
+    ///
+    /// Running downloads are not interrupted when the limit is lowered, the
+    /// next ones wait until enough of them are over.
+    pub fn set_parallelism(&self, parallelism: usize) {
+        let parallelism = parallelism.max(1);
+        let mut current = self.inner.parallelism.lock();
+        if parallelism > *current {
+            self.inner.semaphore.add_permits(parallelism - *current);
+        } else if parallelism < *current {
+            let missing = *current - parallelism;
+            let inner = self.inner.clone();
+            // Permits held by running downloads are taken back when they end
+            tokio::spawn(async move {
+                if let Ok(permits) = inner.semaphore.clone().acquire_many_owned(missing as u32).await {
+                    permits.forget();
+                }
+            });
+        }
+        *current = parallelism;
+    }
+
+    pub fn items(&self) -> Vec<DownloadItem> {
+        self.inner.items()
+    }
+
+    /// Adds a download to the queue
+    ///
+    /// # Arguments
+    /// * `source` - Beatmapset or link to download
+    /// * `title` - Name shown on the downloads page
+    /// * `path` - Where the file should be saved
+    /// * `install_dir` - Songs folder to extract the archive into, `None` to keep the .osz
+    ///
+    /// # Returns
+    /// Id of the download, or an error when the queue is full
+    ///
+    /// A beatmapset already present in the Songs folder (or next to `path`) is
+    /// not downloaded again, it is added as `AlreadyInstalled`.
+    pub fn enqueue(
+        &self,
+        source: DownloadSource,
+        title: &str,
+        path: PathBuf,
+        install_dir: Option<PathBuf>,
+    ) -> eyre::Result<u64> {
+        let installed = match &source {
+            DownloadSource::Set(set_id) => install_dir
+                .as_deref()
+                .or(path.parent())
+                .is_some_and(|songs_dir| is_installed(songs_dir, *set_id)),
+            DownloadSource::Link(_) => false,
+        };
+        let id = {
+            let mut entries = self.inner.entries.lock();
+            let pending = entries.iter().filter(|entry| !entry.item.status.is_finished()).count();
+            if pending >= MAX_QUEUED {
+                return Err(eyre::eyre!("File de téléchargement pleine ({} maps)", MAX_QUEUED));
+            }
+            let id = self.inner.next_id.fetch_add(1, Ordering::Relaxed);
+            let (control, _) = watch::channel(Control::Run);
+            entries.push(Entry {
+                item: DownloadItem {
+                    id,
+                    source,
+                    title: title.to_string(),
+                    path,
+                    install_dir,
+                    status: if installed { DownloadStatus::AlreadyInstalled } else { DownloadStatus::Queued },
+                    progress: if installed { 1.0 } else { 0.0 },
+                    mirror: None,
+                },
+                control,
+            });
+            id
+        };
+        if !installed {
+            self.start(id);
+        }
+        self.inner.notify();
+        Ok(id)
+    }
+
+    /// Stops a download and keeps its partial file for `resume`
+    pub fn pause(&self, id: u64) {
+        self.inner.stop(id, Control::Pause, DownloadStatus::Paused);
+    }
+
+    /// Stops a download and removes its partial file
+    pub fn cancel(&self, id: u64) {
+        let path = self.inner.stop(id, Control::Cancel, DownloadStatus::Cancelled);
+        // Nothing is running for paused or failed downloads, the file is removed here
+        if let Some(path) = path {
+            let _ = std::fs::remove_file(part_path(&path));
+        }
+    }
+
+    /// Queues a paused or failed download again, continuing its partial file
+    pub fn resume(&self, id: u64) {
+        let resumed = self.inner.update(id, true, |item| {
+            if matches!(item.status, DownloadStatus::Paused | DownloadStatus::Failed(_)) {
+                item.status = DownloadStatus::Queued;
+                true
+            } else {
+                false
+            }
+        });
+        if resumed == Some(true) {
+            self.start(id);
+        }
+    }
+
+    /// Removes the completed, failed and cancelled downloads from the list
+    pub fn clear_finished(&self) {
+        self.inner.entries.lock().retain(|entry| !entry.item.status.is_finished());
+        self.inner.notify();
+    }
+
+    fn start(&self, id: u64) {
+        let control = {
+            let mut entries = self.inner.entries.lock();
+            let Some(entry) = entries.iter_mut().find(|entry| entry.item.id == id) else {
+                return;
+            };
+            let (sender, receiver) = watch::channel(Control::Run);
+            entry.control = sender;
+            receiver
+        };
+        tokio::spawn(run(self.inner.clone(), id, control));
+    }
+}
+
+impl Inner {
+    fn items(&self) -> Vec<DownloadItem> {
+        self.entries.lock().iter().map(|entry| entry.item.clone()).collect()
+    }
+
+    fn notify(&self) {
+        (self.on_change)(self.items());
+    }
+
+    /// Changes a download, notifying the change when `notify` is set
+    fn update<T>(&self, id: u64, notify: bool, change: impl FnOnce(&mut DownloadItem) -> T) -> Option<T> {
+        let result = {
+            let mut entries = self.entries.lock();
+            let entry = entries.iter_mut().find(|entry| entry.item.id == id)?;
+            change(&mut entry.item)
+        };
+        if notify {
+            self.notify();
+        }
+        Some(result)
+    }
+
+    /// Sends a stop order to a download that is not over
+    ///
+    /// # Returns
+    /// The path of the download when no task was running for it
+    fn stop(&self, id: u64, control: Control, status: DownloadStatus) -> Option<PathBuf> {
+        let idle = {
+            let mut entries = self.entries.lock();
+            let entry = entries.iter_mut().find(|entry| entry.item.id == id)?;
+            let idle = matches!(entry.item.status, DownloadStatus::Paused | DownloadStatus::Failed(_));
+            let over = entry.item.status.is_over();
+            if over || (idle && control == Control::Pause) {
+                return None;
+            }
+            entry.item.status = status;
+            let _ = entry.control.send(control);
+            idle.then(|| entry.item.path.clone())
+        };
+        self.notify();
+        idle
+    }
+
+    fn item(&self, id: u64) -> Option<DownloadItem> {
+        let entries = self.entries.lock();
+        entries.iter().find(|entry| entry.item.id == id).map(|entry| entry.item.clone())
+    }
+
+    /// Starts the request of a download, from `offset` when part of it is already written
+    async fn open(&self, source: &DownloadSource, offset: u64) -> eyre::Result<(String, Response)> {
+        match source {
+            DownloadSource::Set(set_id) => {
+                let mirrors = self.mirrors.lock().clone();
+                mirrors.open(*set_id, offset).await
+            }
+            DownloadSource::Link(link) => {
+                let response = with_range(self.client.get(link), offset).send().await?;
+                Ok((String::from("lien direct"), check_response(response)?))
+            }
+        }
+    }
+}
+
+/// Resolves once the download is asked to stop
+async fn stopped(control: &mut watch::Receiver<Control>) {
+    while *control.borrow_and_update() == Control::Run {
+        if control.changed().await.is_err() {
+            // The download was removed from the list
+            return;
+        }
+    }
+}
+
+async fn run(inner: Arc<Inner>, id: u64, mut control: watch::Receiver<Control>) {
+    let permit = tokio::select! {
+        permit = inner.semaphore.clone().acquire_owned() => permit.ok(),
+        _ = stopped(&mut control) => None,
+    };
+    let Some(item) = inner.item(id) else {
+        return;
+    };
+    if permit.is_some() && download_with_retries(&inner, id, &item.source, &item.path, &mut control).await {
+        if let Some(songs_dir) = item.install_dir {
+            install(&inner, id, item.path.clone(), songs_dir).await;
+        }
+    }
+    if *control.borrow() == Control::Cancel {
+        let _ = tokio::fs::remove_file(part_path(&item.path)).await;
+    }
+}
+
+/// Extracts a downloaded archive in the Songs folder
+async fn install(inner: &Inner, id: u64, path: PathBuf, songs_dir: PathBuf) {
+    let status = match tokio::task::spawn_blocking(move || extract_osz(&path, &songs_dir)).await {
+        Ok(Ok(_)) => DownloadStatus::Installed,
+        Ok(Err(e)) => DownloadStatus::Failed(format!("extraction impossible : {}", e)),
+        Err(e) => DownloadStatus::Failed(format!("extraction impossible : {}", e)),
+    };
+    inner.update(id, true, |item| item.status = status);
+}
+
+/// Downloads until the file is complete, the retries are exhausted or the download is stopped
+///
+/// # Returns
+/// Whether the file was downloaded
+async fn download_with_retries(
+    inner: &Inner,
+    id: u64,
+    source: &DownloadSource,
+    path: &Path,
+    control: &mut watch::Receiver<Control>,
+) -> bool {
+    let mut attempt = 0;
+    while *control.borrow() == Control::Run {
+        inner.update(id, true, |item| item.status = DownloadStatus::Downloading);
+        match download_once(inner, id, source, path, control).await {
+            Ok(Outcome::Completed(mirror)) => {
+                inner.update(id, true, |item| {
+                    item.status = DownloadStatus::Completed;
+                    item.progress = 1.0;
+                    item.mirror = Some(mirror);
+                });
+                return true;
+            }
+            Ok(Outcome::Stopped) => return false,
+            Err(e) => {
+                attempt += 1;
+                if attempt > MAX_RETRIES {
+                    eprintln!("Erreur lors du téléchargement: {}", e);
+                    inner.update(id, true, |item| item.status = DownloadStatus::Failed(e.to_string()));
+                    return false;
+                }
+                inner.update(id, true, |item| item.status = DownloadStatus::Retrying(attempt));
+                let delay = RETRY_BASE_DELAY * 2u32.pow(attempt - 1);
+                tokio::select! {
+                    _ = tokio::time::sleep(delay) => {}
+                    _ = stopped(control) => return false,
+                }
+            }
+        }
+    }
+    false
+}
+
+/// Downloads the file once, continuing the partial file if the server allows it
+async fn download_once(
+    inner: &Inner,
+    id: u64,
+    source: &DownloadSource,
+    path: &Path,
+    control: &mut watch::Receiver<Control>,
+) -> eyre::Result<Outcome> {
+    let part = part_path(path);
+    let offset = tokio::fs::metadata(&part).await.map(|metadata| metadata.len()).unwrap_or(0);
+    let (mirror, response) = tokio::select! {
+        opened = inner.open(source, offset) => opened?,
+        _ = stopped(control) => return Ok(Outcome::Stopped),
+    };
+
+    // A server ignoring the range sends the whole file again
+    let resumed = offset > 0 && response.status() == StatusCode::PARTIAL_CONTENT;
+    let mut downloaded = if resumed { offset } else { 0 };
+    let total_size = response.content_length().map(|length| length + downloaded).unwrap_or(0);
+    inner.update(id, true, |item| item.mirror = Some(mirror.clone()));
+
+    let mut options = tokio::fs::OpenOptions::new();
+    options.create(true);
+    if resumed {
+        options.append(true);
+    } else {
+        options.write(true).truncate(true);
+    }
+    let mut file = options.open(&part).await?;
+    let mut stream = response.bytes_stream();
+    let mut notified = 0.0;
+
+    loop {
+        let chunk = tokio::select! {
+            chunk = stream.next() => chunk,
+            _ = stopped(control) => {
+                file.flush().await?;
+                return Ok(Outcome::Stopped);
+            }
+        };
+        let Some(chunk) = chunk else {
+            break;
+        };
+        let chunk = chunk?;
+        file.write_all(&chunk).await?;
+        downloaded += chunk.len() as u64;
+
+        if total_size > 0 {
+            let progress = downloaded as f32 / total_size as f32;
+            let notify = progress - notified >= PROGRESS_STEP;
+            if notify {
+                notified = progress;
+            }
+            inner.update(id, notify, |item| item.progress = progress);
+        }
+    }
+    file.flush().await?;
+    drop(file);
+
+    if total_size > 0 && downloaded != total_size {
+        return Err(eyre::eyre!("fichier incomplet ({} / {} octets)", downloaded, total_size));
+    }
+    // A corrupted archive is downloaded again from the start
+    let checked = part.clone();
+    if let Err(e) = tokio::task::spawn_blocking(move || verify_osz(&checked)).await? {
+        let _ = tokio::fs::remove_file(&part).await;
+        return Err(eyre::eyre!("archive invalide : {}", e));
+    }
+    tokio::fs::rename(&part, path).await?;
+    Ok(Outcome::Completed(mirror))
+}
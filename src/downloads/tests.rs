@@ -0,0 +1,235 @@
+use std::io::{Cursor, Write};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, LazyLock};
+use std::time::Duration;
+use axum::extract::State;
+use axum::http::{header, HeaderMap, StatusCode};
+use axum::response::{IntoResponse, Response};
+use axum::routing::get;
+use axum::Router;
+use reqwest::Client;
+use zip::write::SimpleFileOptions;
+use zip::ZipWriter;
+use crate::downloads::install::{extract_osz, is_installed, osz_file_name, verify_osz};
+use crate::downloads::{part_path, DownloadItem, DownloadManager, DownloadSource, DownloadStatus, MAX_QUEUED};
+use crate::utils::mirror::{CommunityMirror, MirrorList};
+
+/// Beatmapset with one difficulty and its background
+static CONTENT: LazyLock<Vec<u8>> = LazyLock::new(|| {
+    let mut writer = ZipWriter::new(Cursor::new(Vec::new()));
+    writer.start_file("Artist - Title (Mapper) [Hard].osu", SimpleFileOptions::default()).unwrap();
+    writer.write_all(b"osu file format v14\n\n[General]\nMode: 3\n").unwrap();
+    writer.start_file("bg/background.jpg", SimpleFileOptions::default()).unwrap();
+    writer.write_all(&[0xFF, 0xD8, 0xFF, 0xE0]).unwrap();
+    writer.finish().unwrap().into_inner()
+});
+
+/// Serves `CONTENT` with range support, fails twice on `/flaky` and never answers on `/hang`
+async fn mock_server() -> String {
+    async fn file(headers: HeaderMap) -> Response {
+        let start = headers
+            .get(header::RANGE)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.strip_prefix("bytes="))
+            .and_then(|value| value.trim_end_matches('-').parse::<usize>().ok());
+        match start {
+            Some(start) if start < CONTENT.len() => (StatusCode::PARTIAL_CONTENT, CONTENT[start..].to_vec()).into_response(),
+            _ => CONTENT.to_vec().into_response(),
+        }
+    }
+
+    async fn flaky(State(calls): State<Arc<AtomicUsize>>, headers: HeaderMap) -> Response {
+        if calls.fetch_add(1, Ordering::SeqCst) < 2 {
+            return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+        }
+        file(headers).await
+    }
+
+    let app = Router::new()
+        .route("/file", get(file))
+        .route("/d/:id", get(file))
+        .route("/flaky", get(flaky))
+        .route("/missing", get(|| async { StatusCode::NOT_FOUND }))
+        .route("/corrupt", get(|| async { CONTENT[..CONTENT.len() / 2].to_vec() }))
+        .route(
+            "/hang",
+            get(|| async {
+                tokio::time::sleep(Duration::from_secs(60)).await;
+                StatusCode::OK
+            }),
+        )
+        .with_state(Arc::new(AtomicUsize::new(0)));
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let address = listener.local_addr().unwrap();
+    tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+    format!("http://{}", address)
+}
+
+fn manager(base: &str, parallelism: usize) -> DownloadManager {
+    let mirror = CommunityMirror::new("mock", &format!("{}/d/{{id}}", base));
+    let mirrors = MirrorList::new(Client::new(), vec![Box::new(mirror)]);
+    DownloadManager::new(Client::new(), Arc::new(mirrors), parallelism, |_| {})
+}
+
+fn temp_dir(name: &str) -> PathBuf {
+    let path = std::env::temp_dir().join(format!("cobra_songs_{}_{}", name, std::process::id()));
+    let _ = std::fs::remove_dir_all(&path);
+    std::fs::create_dir_all(&path).unwrap();
+    path
+}
+
+fn temp_file(name: &str) -> PathBuf {
+    let path = std::env::temp_dir().join(format!("cobra_download_{}_{}.osz", name, std::process::id()));
+    let _ = std::fs::remove_file(&path);
+    let _ = std::fs::remove_file(part_path(&path));
+    path
+}
+
+/// Waits until the download matches `done`, failing after a few seconds
+async fn wait_for(downloads: &DownloadManager, id: u64, done: impl Fn(&DownloadItem) -> bool) -> DownloadItem {
+    for _ in 0..200 {
+        if let Some(item) = downloads.items().into_iter().find(|item| item.id == id) {
+            if done(&item) {
+                return item;
+            }
+        }
+        tokio::time::sleep(Duration::from_millis(25)).await;
+    }
+    panic!("le téléchargement {} n'a pas abouti: {:?}", id, downloads.items());
+}
+
+#[tokio::test]
+async fn downloads_from_the_mirrors() {
+    let base = mock_server().await;
+    let downloads = manager(&base, 2);
+    let path = temp_file("mirror");
+
+    let id = downloads.enqueue(DownloadSource::Set(42), "Artist - Title", path.clone(), None).unwrap();
+    let item = wait_for(&downloads, id, |item| item.status.is_finished()).await;
+
+    assert_eq!(item.status, DownloadStatus::Completed);
+    assert_eq!(item.mirror.as_deref(), Some("mock"));
+    assert_eq!(std::fs::read(&path).unwrap(), *CONTENT);
+    assert!(!part_path(&path).exists());
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[tokio::test]
+async fn resumes_partial_files() {
+    let base = mock_server().await;
+    let downloads = manager(&base, 1);
+    let path = temp_file("resume");
+    std::fs::write(part_path(&path), &CONTENT[..10]).unwrap();
+
+    let id = downloads.enqueue(DownloadSource::Link(format!("{}/file", base)), "resume", path.clone(), None).unwrap();
+    wait_for(&downloads, id, |item| item.status == DownloadStatus::Completed).await;
+
+    assert_eq!(std::fs::read(&path).unwrap(), *CONTENT);
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[tokio::test]
+async fn retries_after_failures() {
+    let base = mock_server().await;
+    let downloads = manager(&base, 1);
+    let path = temp_file("flaky");
+
+    let id = downloads.enqueue(DownloadSource::Link(format!("{}/flaky", base)), "flaky", path.clone(), None).unwrap();
+    wait_for(&downloads, id, |item| item.status == DownloadStatus::Completed).await;
+    std::fs::remove_file(&path).unwrap();
+
+    let id = downloads.enqueue(DownloadSource::Link(format!("{}/missing", base)), "missing", temp_file("missing"), None).unwrap();
+    let item = wait_for(&downloads, id, |item| item.status.is_finished()).await;
+    assert!(matches!(item.status, DownloadStatus::Failed(_)));
+}
+
+#[tokio::test]
+async fn pauses_cancels_and_bounds_the_queue() {
+    let base = mock_server().await;
+    let downloads = manager(&base, 1);
+    let hang = DownloadSource::Link(format!("{}/hang", base));
+
+    let running = downloads.enqueue(hang.clone(), "running", temp_file("running"), None).unwrap();
+    let queued = downloads.enqueue(hang.clone(), "queued", temp_file("queued"), None).unwrap();
+    wait_for(&downloads, running, |item| item.status == DownloadStatus::Downloading).await;
+
+    downloads.pause(running);
+    wait_for(&downloads, running, |item| item.status == DownloadStatus::Paused).await;
+    // The slot of the paused download goes to the next one
+    wait_for(&downloads, queued, |item| item.status == DownloadStatus::Downloading).await;
+
+    let path = temp_file("cancelled");
+    std::fs::write(part_path(&path), b"partial").unwrap();
+    let cancelled = downloads.enqueue(hang.clone(), "cancelled", path.clone(), None).unwrap();
+    downloads.cancel(cancelled);
+    wait_for(&downloads, cancelled, |item| item.status == DownloadStatus::Cancelled).await;
+    for _ in 0..40 {
+        if !part_path(&path).exists() {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(25)).await;
+    }
+    assert!(!part_path(&path).exists());
+
+    downloads.clear_finished();
+    let pending = downloads.items().len();
+    for i in pending..MAX_QUEUED {
+        downloads.enqueue(hang.clone(), "filler", temp_file(&format!("filler_{}", i)), None).unwrap();
+    }
+    assert!(downloads.enqueue(hang, "overflow", temp_file("overflow"), None).is_err());
+}
+
+#[tokio::test]
+async fn refuses_corrupted_archives() {
+    let base = mock_server().await;
+    let downloads = manager(&base, 1);
+    let path = temp_file("corrupt");
+
+    let id = downloads.enqueue(DownloadSource::Link(format!("{}/corrupt", base)), "corrupt", path.clone(), None).unwrap();
+    let item = wait_for(&downloads, id, |item| item.status.is_finished()).await;
+
+    assert!(matches!(item.status, DownloadStatus::Failed(ref error) if error.contains("archive invalide")));
+    assert!(!path.exists() && !part_path(&path).exists());
+}
+
+#[tokio::test]
+async fn installs_in_the_songs_folder_once() {
+    let base = mock_server().await;
+    let downloads = manager(&base, 1);
+    let songs = temp_dir("install");
+    let path = songs.join(osz_file_name(Some(42), "Artist", "Title"));
+
+    let id = downloads.enqueue(DownloadSource::Set(42), "Artist - Title", path.clone(), Some(songs.clone())).unwrap();
+    wait_for(&downloads, id, |item| item.status == DownloadStatus::Installed).await;
+    assert!(songs.join("42 Artist - Title").join("Artist - Title (Mapper) [Hard].osu").exists());
+    assert!(!path.exists());
+
+    let id = downloads.enqueue(DownloadSource::Set(42), "Artist - Title", path, Some(songs.clone())).unwrap();
+    let item = downloads.items().into_iter().find(|item| item.id == id).unwrap();
+    assert_eq!(item.status, DownloadStatus::AlreadyInstalled);
+    std::fs::remove_dir_all(&songs).unwrap();
+}
+
+#[test]
+fn names_verifies_and_extracts_osz_files() {
+    assert_eq!(osz_file_name(Some(42), "Art:ist", "Ti/tle?"), "42 Artist - Title.osz");
+    assert_eq!(osz_file_name(None, "Artist", "Title..."), "Artist - Title.osz");
+
+    let songs = temp_dir("extract");
+    let path = songs.join("7 Artist - Title.osz");
+    std::fs::write(&path, &*CONTENT).unwrap();
+    assert_eq!(verify_osz(&path).unwrap(), 1);
+    assert!(is_installed(&songs, 7));
+    assert!(!is_installed(&songs, 70));
+
+    let folder = extract_osz(&path, &songs).unwrap();
+    assert_eq!(folder, songs.join("7 Artist - Title"));
+    assert!(folder.join("bg").join("background.jpg").exists());
+    assert!(!path.exists() && is_installed(&songs, 7));
+
+    std::fs::write(&path, b"not an archive").unwrap();
+    assert!(verify_osz(&path).is_err());
+    std::fs::remove_dir_all(&songs).unwrap();
+}
@@ -0,0 +1,71 @@
+use slint::{ComponentHandle, Model, ModelRc, SharedString, VecModel, Weak};
+use crate::downloads::{DownloadItem, DownloadSource, DownloadStatus, MAX_RETRIES};
+use crate::library::InstallStatus;
+use crate::utils::mirror::parse_set_id;
+use crate::{DownloadData, DownloadState, LoginPage, MapData, MapSetState};
+
+fn status_text(status: &DownloadStatus) -> String {
+    match status {
+        DownloadStatus::Queued => "En attente".to_string(),
+        DownloadStatus::Downloading => "Téléchargement".to_string(),
+        DownloadStatus::Retrying(attempt) => format!("Nouvelle tentative ({}/{})", attempt, MAX_RETRIES),
+        DownloadStatus::Paused => "En pause".to_string(),
+        DownloadStatus::Completed => "Terminé".to_string(),
+        DownloadStatus::Installed => "Installé".to_string(),
+        DownloadStatus::AlreadyInstalled => "Déjà installé".to_string(),
+        DownloadStatus::Failed(error) => format!("Échec : {}", error),
+        DownloadStatus::Cancelled => "Annulé".to_string(),
+    }
+}
+
+fn download_data(item: &DownloadItem) -> DownloadData {
+    DownloadData {
+        id: item.id as i32,
+        title: SharedString::from(item.title.as_str()),
+        status: SharedString::from(status_text(&item.status)),
+        mirror: SharedString::from(item.mirror.clone().unwrap_or_default()),
+        progress: item.progress,
+        can_pause: matches!(
+            item.status,
+            DownloadStatus::Queued | DownloadStatus::Downloading | DownloadStatus::Retrying(_)
+        ),
+        can_resume: matches!(item.status, DownloadStatus::Paused | DownloadStatus::Failed(_)),
+        can_cancel: !item.status.is_over(),
+    }
+}
+
+/// Whether a download was started from a map of the browse page
+fn is_download_of(item: &DownloadItem, map: &MapData) -> bool {
+    match &item.source {
+        DownloadSource::Set(set_id) => parse_set_id(&map.link) == Some(*set_id),
+        DownloadSource::Link(link) => map.link.as_str() == link,
+    }
+}
+
+/// Shows the downloads on the Downloads page and their progress on the browse page
+///
+/// # Arguments
+/// * `items` - Every download of the manager
+/// * `weak` - Weak reference to the main window
+pub fn refresh_downloads_ui(items: Vec<DownloadItem>, weak: Weak<LoginPage>) {
+    let _ = weak.upgrade_in_event_loop(move |handle| {
+        let rows: Vec<DownloadData> = items.iter().map(download_data).collect();
+        handle.global::<DownloadState>().set_downloads(ModelRc::new(VecModel::from(rows)));
+
+        let maps = handle.global::<MapSetState>().get_maps();
+        for index in 0..maps.row_count() {
+            let Some(mut map) = maps.row_data(index) else {
+                continue;
+            };
+            if let Some(item) = items.iter().rev().find(|item| is_download_of(item, &map)) {
+                let installed = matches!(item.status, DownloadStatus::Installed | DownloadStatus::AlreadyInstalled);
+                let install_status = if installed { InstallStatus::Installed.as_ui() } else { map.install_status };
+                if map.download_progress != item.progress || map.install_status != install_status {
+                    map.download_progress = item.progress;
+                    map.install_status = install_status;
+                    maps.set_row_data(index, map);
+                }
+            }
+        }
+    });
+}
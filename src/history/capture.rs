@@ -0,0 +1,99 @@
+use std::thread;
+use crate::history::{now, ScoreHistory, ScoreRecord};
+use crate::reader::events::{listen, EventBus, GameEvent};
+use crate::reader::structs::{BeatmapInfo, Hit, HitErrors, ResultScreenValues};
+use crate::utils::modes::MANIA_MODE;
+use crate::utils::mods::clock_rate;
+use crate::utils::rate::rate_from_version;
+use crate::utils::scoring::{mania_accuracy_v2, wife3_accuracy, WIFE3_J4};
+
+/// Builds the record of a play shown on the result screen
+///
+/// # Arguments
+/// * `map` - Beatmap of the play
+/// * `result` - Values read on the result screen
+/// * `timestamp` - Unix timestamp of the play, in seconds
+pub fn build_record(map: &BeatmapInfo, result: &ResultScreenValues, timestamp: u64) -> ScoreRecord {
+    let (pp, stars) = calc_score_pp(&map.path, result.mods, result.max_combo, &result.hit).unwrap_or_else(|e| {
+        eprintln!("Impossible de calculer les pp du score: {}", e);
+        (0.0, 0.0)
+    });
+    let hit_errors = HitErrors { errors: result.hit_errors.clone() };
+    let rate = clock_rate(result.mods) * rate_from_version(&map.difficulty).unwrap_or(1.0);
+    let is_mania = result.mode == MANIA_MODE;
+
+    ScoreRecord {
+        timestamp,
+        beatmap_md5: map.md5.clone(),
+        title: map.song.clone(),
+        difficulty: map.difficulty.clone(),
+        path: map.path.clone(),
+        username: result.username.clone(),
+        mode: result.mode,
+        mods: result.mods,
+        rate,
+        score: result.score,
+        max_combo: result.max_combo,
+        hit: result.hit.clone(),
+        accuracy: result.accuracy,
+        accuracy_v2: is_mania.then(|| mania_accuracy_v2(&result.hit)),
+        // Rate-changed difficulties are played at 1.0x, only speed mods alter the hit errors
+        wife3: if is_mania {
+            wife3_accuracy(&result.hit_errors, result.hit._miss.max(0) as u32, clock_rate(result.mods), WIFE3_J4)
+        } else {
+            None
+        },
+        pp,
+        stars,
+        unstable_rate: hit_errors.unstable_rate(),
+        key_counts: result.key_counts.clone(),
+        replay_md5: None,
+    }
+}
+
+/// Computes the pp and star rating of a finished play
+///
+/// # Arguments
+/// * `path` - Path of the .osu file
+/// * `mods` - Mods of the play
+/// * `max_combo` - Highest combo of the play
+/// * `hit` - Judgements of the play
+///
+/// # Returns
+/// (pp, stars)
+pub(crate) fn calc_score_pp(path: &str, mods: u32, max_combo: i16, hit: &Hit) -> eyre::Result<(f64, f64)> {
+    let map = rosu_pp::Beatmap::from_path(path)?;
+    let attrs = rosu_pp::Performance::new(&map)
+        .mods(mods)
+        .combo(max_combo.max(0) as u32)
+        .n_geki(hit._geki.max(0) as u32)
+        .n300(hit._300.max(0) as u32)
+        .n_katu(hit._katu.max(0) as u32)
+        .n100(hit._100.max(0) as u32)
+        .n50(hit._50.max(0) as u32)
+        .misses(hit._miss.max(0) as u32)
+        .calculate();
+    Ok((attrs.pp(), attrs.stars()))
+}
+
+/// Records every play reaching the result screen into the history
+///
+/// # Arguments
+/// * `events` - Event bus of the reader
+/// * `history` - History the plays are appended to
+/// * `on_recorded` - Called after each play is written
+pub fn subscribe_history(events: &EventBus, history: ScoreHistory, on_recorded: impl Fn(&ScoreRecord) + Send + 'static) {
+    let receiver = events.subscribe();
+    thread::spawn(move || {
+        listen(receiver, |event| {
+            if let GameEvent::ResultScreenShown { map, result } = event {
+                let record = build_record(&map, &result, now());
+                println!("Score enregistré: {} [{}] {:.2}pp", record.title, record.difficulty, record.pp);
+                match history.append(&record) {
+                    Ok(()) => on_recorded(&record),
+                    Err(e) => eprintln!("Impossible d'enregistrer le score: {}", e),
+                }
+            }
+        });
+    });
+}
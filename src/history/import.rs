@@ -0,0 +1,107 @@
+use std::collections::HashSet;
+use std::path::Path;
+use rayon::prelude::*;
+use crate::history::capture::calc_score_pp;
+use crate::history::{ScoreHistory, ScoreRecord};
+use crate::library::osu_db::DbBeatmap;
+use crate::utils::modes::MANIA_MODE;
+use crate::library::scores_db::{DbScore, ScoresDb};
+use crate::library::LibraryIndex;
+use crate::utils::mods::clock_rate;
+use crate::utils::rate::rate_from_version;
+use crate::utils::scoring::{accuracy_v1, mania_accuracy_v2};
+
+/// Seconds between the end of a play in scores.db and its capture on the result screen
+const SAME_PLAY_DELAY: u64 = 600;
+
+/// Outcome of an import of scores.db
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ImportSummary {
+    pub imported: usize,
+    /// Plays already in the history, imported before or captured on the result screen
+    pub duplicates: usize,
+    /// Plays of beatmaps missing from osu!.db
+    pub unknown_maps: usize,
+}
+
+/// Builds the history record of a play of scores.db, without pp
+///
+/// # Arguments
+/// * `score` - Play read from scores.db
+/// * `beatmap` - Entry of its beatmap in osu!.db
+/// * `songs_dir` - Songs folder of osu!
+pub fn imported_record(score: &DbScore, beatmap: &DbBeatmap, songs_dir: &Path) -> ScoreRecord {
+    ScoreRecord {
+        timestamp: score.timestamp(),
+        beatmap_md5: score.beatmap_md5.clone(),
+        title: beatmap.title.clone(),
+        difficulty: beatmap.difficulty.clone(),
+        path: beatmap.path(songs_dir).to_string_lossy().into_owned(),
+        username: score.player.clone(),
+        mode: score.mode,
+        mods: score.mods,
+        rate: clock_rate(score.mods) * rate_from_version(&beatmap.difficulty).unwrap_or(1.0),
+        score: score.score,
+        max_combo: score.max_combo,
+        hit: score.hit.clone(),
+        accuracy: accuracy_v1(score.mode, &score.hit).unwrap_or_default(),
+        accuracy_v2: Some(mania_accuracy_v2(&score.hit)),
+        // scores.db keeps no hit errors
+        wife3: None,
+        pp: 0.0,
+        stars: beatmap.stars(0).unwrap_or_default(),
+        unstable_rate: 0.0,
+        key_counts: Vec::new(),
+        replay_md5: Some(score.replay_md5.clone()),
+    }
+}
+
+fn is_same_play(record: &ScoreRecord, score: &DbScore) -> bool {
+    match &record.replay_md5 {
+        Some(replay_md5) => *replay_md5 == score.replay_md5,
+        None => {
+            record.beatmap_md5 == score.beatmap_md5
+                && record.score == score.score
+                && record.timestamp.abs_diff(score.timestamp()) <= SAME_PLAY_DELAY
+        }
+    }
+}
+
+/// Adds the mania plays of scores.db missing from the history, with their pp
+///
+/// Importing the same file again only adds the plays set since.
+///
+/// # Arguments
+/// * `history` - Score history the plays are appended to
+/// * `scores` - Content of scores.db
+/// * `index` - Library the beatmaps are looked up in
+/// * `songs_dir` - Songs folder of osu!
+pub fn import_scores(history: &ScoreHistory, scores: &ScoresDb, index: &LibraryIndex, songs_dir: &Path) -> eyre::Result<ImportSummary> {
+    let existing = history.load()?;
+    let imported: HashSet<&str> = existing.iter().filter_map(|record| record.replay_md5.as_deref()).collect();
+    let captured: Vec<&ScoreRecord> = existing.iter().filter(|record| record.replay_md5.is_none()).collect();
+
+    let mut summary = ImportSummary::default();
+    let mut pending = Vec::new();
+    for score in scores.scores.iter().filter(|score| score.mode == MANIA_MODE) {
+        if imported.contains(score.replay_md5.as_str()) || captured.iter().any(|record| is_same_play(record, score)) {
+            summary.duplicates += 1;
+            continue;
+        }
+        match index.by_md5(&score.beatmap_md5) {
+            Some(beatmap) => pending.push(imported_record(score, beatmap, songs_dir)),
+            None => summary.unknown_maps += 1,
+        }
+    }
+
+    pending.par_iter_mut().for_each(|record| {
+        if let Ok((pp, stars)) = calc_score_pp(&record.path, record.mods, record.max_combo, &record.hit) {
+            record.pp = pp;
+            record.stars = stars;
+        }
+    });
+    pending.sort_by_key(|record| record.timestamp);
+    history.append_all(&pending)?;
+    summary.imported = pending.len();
+    Ok(summary)
+}
@@ -0,0 +1,123 @@
+use std::fs::OpenOptions;
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+use serde::{Deserialize, Serialize};
+use crate::reader::structs::Hit;
+use crate::utils::data_dir::data_dir;
+
+pub mod capture;
+pub mod import;
+pub mod query;
+pub mod view;
+#[cfg(test)]
+mod tests;
+
+/// File of the score history inside the data folder, one JSON play per line
+const HISTORY_FILE: &str = "scores.jsonl";
+
+/// A play captured on the result screen
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ScoreRecord {
+    /// Unix timestamp of the play, in seconds
+    pub timestamp: u64,
+    pub beatmap_md5: String,
+    pub title: String,
+    pub difficulty: String,
+    /// Path of the .osu file when the play was set
+    pub path: String,
+    pub username: String,
+    pub mode: u8,
+    pub mods: u32,
+    /// Clock rate of the play, from speed mods and rate-changed difficulties
+    pub rate: f64,
+    pub score: i32,
+    pub max_combo: i16,
+    pub hit: Hit,
+    /// Accuracy between 0.0 and 1.0
+    pub accuracy: f64,
+    /// ScoreV2 accuracy, mania only
+    #[serde(default)]
+    pub accuracy_v2: Option<f64>,
+    /// Etterna Wife3 (judge 4) estimate, mania only, from the hit errors
+    #[serde(default)]
+    pub wife3: Option<f64>,
+    pub pp: f64,
+    pub stars: f64,
+    pub unstable_rate: f64,
+    /// Presses of each key (mania columns left to right, or K1 K2 M1 M2)
+    #[serde(default)]
+    pub key_counts: Vec<u32>,
+    /// MD5 of the osu! replay, for plays imported from scores.db
+    #[serde(default)]
+    pub replay_md5: Option<String>,
+}
+
+/// Append-only score history stored as JSON lines
+#[derive(Debug, Clone)]
+pub struct ScoreHistory {
+    path: PathBuf,
+}
+
+impl ScoreHistory {
+    /// Opens the history of the Cobra data folder
+    pub fn open_default() -> eyre::Result<Self> {
+        Ok(Self::open(&data_dir()?.join(HISTORY_FILE)))
+    }
+
+    /// Opens a history file, created on the first play recorded
+    pub fn open(path: &Path) -> Self {
+        Self { path: path.to_path_buf() }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Appends a play at the end of the history
+    pub fn append(&self, record: &ScoreRecord) -> eyre::Result<()> {
+        self.append_all(std::slice::from_ref(record))
+    }
+
+    /// Appends several plays at once, in the given order
+    pub fn append_all(&self, records: &[ScoreRecord]) -> eyre::Result<()> {
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+        for record in records {
+            writeln!(file, "{}", serde_json::to_string(record)?)?;
+        }
+        Ok(())
+    }
+
+    /// Loads every play of the history, oldest first
+    ///
+    /// Lines that can't be parsed (e.g. a write interrupted by a crash) are skipped.
+    pub fn load(&self) -> eyre::Result<Vec<ScoreRecord>> {
+        if !self.path.exists() {
+            return Ok(Vec::new());
+        }
+        let file = std::fs::File::open(&self.path)?;
+        let mut records = Vec::new();
+        for (i, line) in BufReader::new(file).lines().enumerate() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            match serde_json::from_str(&line) {
+                Ok(record) => records.push(record),
+                Err(e) => eprintln!("Ligne {} de l'historique ignorée: {}", i + 1, e),
+            }
+        }
+        Ok(records)
+    }
+}
+
+/// Current Unix timestamp, in seconds
+pub fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
@@ -0,0 +1,108 @@
+use std::collections::HashMap;
+use crate::history::ScoreRecord;
+
+/// Seconds in a day, trends are grouped by UTC day
+const DAY: u64 = 86_400;
+/// Weight decay of the total pp, as on osu! profiles
+const PP_WEIGHT: f64 = 0.95;
+
+/// Aggregated plays of one day
+#[derive(Debug, Clone, PartialEq)]
+pub struct TrendPoint {
+    /// Unix timestamp of the start of the day (UTC)
+    pub day: u64,
+    pub plays: usize,
+    pub average_pp: f64,
+    pub best_pp: f64,
+    /// Average accuracy between 0.0 and 1.0
+    pub average_accuracy: f64,
+}
+
+/// Returns the best play of every beatmap, sorted by pp (highest first)
+///
+/// Plays are compared by pp, then by score when the pp are equal
+/// (e.g. unranked modes or maps that couldn't be loaded).
+///
+/// # Arguments
+/// * `records` - Plays of the history
+pub fn best_per_map(records: &[ScoreRecord]) -> Vec<ScoreRecord> {
+    let mut best: HashMap<&str, &ScoreRecord> = HashMap::new();
+    for record in records {
+        let entry = best.entry(record.beatmap_md5.as_str()).or_insert(record);
+        if (record.pp, record.score) > (entry.pp, entry.score) {
+            *entry = record;
+        }
+    }
+
+    let mut result: Vec<ScoreRecord> = best.into_values().cloned().collect();
+    result.sort_by(|a, b| b.pp.total_cmp(&a.pp).then(b.timestamp.cmp(&a.timestamp)));
+    result
+}
+
+/// Returns the most recent plays, newest first
+///
+/// # Arguments
+/// * `records` - Plays of the history
+/// * `limit` - Maximum number of plays returned
+pub fn recent(records: &[ScoreRecord], limit: usize) -> Vec<ScoreRecord> {
+    let mut result = records.to_vec();
+    result.sort_by(|a, b| b.timestamp.cmp(&a.timestamp));
+    result.truncate(limit);
+    result
+}
+
+/// Returns every play of a beatmap, newest first
+///
+/// # Arguments
+/// * `records` - Plays of the history
+/// * `md5` - MD5 of the .osu file
+pub fn plays_of_map(records: &[ScoreRecord], md5: &str) -> Vec<ScoreRecord> {
+    let mut result: Vec<ScoreRecord> = records
+        .iter()
+        .filter(|record| record.beatmap_md5 == md5)
+        .cloned()
+        .collect();
+    result.sort_by(|a, b| b.timestamp.cmp(&a.timestamp));
+    result
+}
+
+/// Computes the pp and accuracy trend of the plays, one point per day with plays
+///
+/// # Arguments
+/// * `records` - Plays of the history
+/// * `since` - Only plays set at or after this Unix timestamp are used
+pub fn daily_trend(records: &[ScoreRecord], since: u64) -> Vec<TrendPoint> {
+    let mut days: HashMap<u64, Vec<&ScoreRecord>> = HashMap::new();
+    for record in records.iter().filter(|record| record.timestamp >= since) {
+        days.entry(record.timestamp / DAY * DAY).or_default().push(record);
+    }
+
+    let mut trend: Vec<TrendPoint> = days
+        .into_iter()
+        .map(|(day, plays)| {
+            let count = plays.len() as f64;
+            TrendPoint {
+                day,
+                plays: plays.len(),
+                average_pp: plays.iter().map(|r| r.pp).sum::<f64>() / count,
+                best_pp: plays.iter().map(|r| r.pp).fold(0.0, f64::max),
+                average_accuracy: plays.iter().map(|r| r.accuracy).sum::<f64>() / count,
+            }
+        })
+        .collect();
+    trend.sort_by_key(|point| point.day);
+    trend
+}
+
+/// Computes a weighted pp total like osu! profiles (best play counts fully, then 95%, 90.25%...)
+///
+/// # Arguments
+/// * `pps` - pp of the best play of each beatmap, in any order
+pub fn weighted_total(pps: impl IntoIterator<Item = f64>) -> f64 {
+    let mut pps: Vec<f64> = pps.into_iter().collect();
+    pps.sort_by(|a, b| b.total_cmp(a));
+    pps.iter()
+        .enumerate()
+        .map(|(i, pp)| pp * PP_WEIGHT.powi(i as i32))
+        .sum()
+}
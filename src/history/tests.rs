@@ -0,0 +1,159 @@
+use std::path::Path;
+use crate::history::import::import_scores;
+use crate::history::query::{best_per_map, daily_trend, plays_of_map, recent};
+use crate::history::view::format_timestamp;
+use crate::history::{ScoreHistory, ScoreRecord};
+use crate::library::osu_db::{DbBeatmap, OsuDb};
+use crate::library::scores_db::{DbScore, ScoresDb};
+use crate::library::LibraryIndex;
+use crate::reader::structs::Hit;
+
+const DAY: u64 = 86_400;
+
+fn record(md5: &str, timestamp: u64, pp: f64, accuracy: f64) -> ScoreRecord {
+    ScoreRecord {
+        timestamp,
+        beatmap_md5: md5.to_string(),
+        title: format!("Map {}", md5),
+        difficulty: "Insane".to_string(),
+        path: String::new(),
+        username: "Cobra".to_string(),
+        mode: 3,
+        mods: 0,
+        rate: 1.0,
+        score: (pp * 1000.0) as i32,
+        max_combo: 100,
+        hit: Hit::default(),
+        accuracy,
+        accuracy_v2: None,
+        wife3: None,
+        pp,
+        stars: 4.0,
+        unstable_rate: 80.0,
+        key_counts: vec![120, 118, 121, 117],
+        replay_md5: None,
+    }
+}
+
+fn records() -> Vec<ScoreRecord> {
+    vec![
+        record("a", 10 * DAY + 100, 200.0, 0.97),
+        record("a", 10 * DAY + 200, 250.0, 0.98),
+        record("b", 11 * DAY + 50, 300.0, 0.95),
+        record("a", 12 * DAY, 220.0, 0.99),
+    ]
+}
+
+#[test]
+fn best_per_map_keeps_highest_pp() {
+    let best = best_per_map(&records());
+    assert_eq!(best.len(), 2);
+    assert_eq!((best[0].beatmap_md5.as_str(), best[0].pp), ("b", 300.0));
+    assert_eq!((best[1].beatmap_md5.as_str(), best[1].pp), ("a", 250.0));
+}
+
+#[test]
+fn recent_is_newest_first() {
+    let recent = recent(&records(), 2);
+    assert_eq!(recent.iter().map(|r| r.timestamp).collect::<Vec<_>>(), vec![12 * DAY, 11 * DAY + 50]);
+    assert_eq!(plays_of_map(&records(), "a").len(), 3);
+}
+
+#[test]
+fn daily_trend_groups_by_day() {
+    let trend = daily_trend(&records(), 10 * DAY + 150);
+    assert_eq!(trend.len(), 3);
+    assert_eq!(trend[0].day, 10 * DAY);
+    assert_eq!(trend[0].plays, 1);
+    assert_eq!(trend[0].best_pp, 250.0);
+    assert!((trend[2].average_accuracy - 0.99).abs() < 1e-9);
+}
+
+#[test]
+fn history_roundtrips_and_skips_broken_lines() {
+    let path = std::env::temp_dir().join(format!("cobra_history_{}.jsonl", std::process::id()));
+    let history = ScoreHistory::open(&path);
+    for record in records() {
+        history.append(&record).unwrap();
+    }
+    std::fs::OpenOptions::new()
+        .append(true)
+        .open(&path)
+        .and_then(|mut file| std::io::Write::write_all(&mut file, b"{\"timestamp\": 1"))
+        .unwrap();
+
+    let loaded = history.load().unwrap();
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(loaded, records());
+}
+
+#[test]
+fn formats_timestamps() {
+    assert_eq!(format_timestamp(0), "1970-01-01 00:00");
+    assert_eq!(format_timestamp(1_709_210_096), "2024-02-29 12:34");
+}
+
+fn db_score(md5: &str, replay_md5: &str, timestamp: i64, mode: u8) -> DbScore {
+    DbScore {
+        mode,
+        beatmap_md5: md5.to_string(),
+        player: "Cobra".to_string(),
+        replay_md5: replay_md5.to_string(),
+        hit: Hit { _geki: 300, _300: 100, _katu: 0, _100: 0, _50: 0, _miss: 0 },
+        score: 1_000_000,
+        max_combo: 400,
+        mods: 64,
+        date: 621_355_968_000_000_000 + timestamp * 10_000_000,
+        ..DbScore::default()
+    }
+}
+
+#[test]
+fn imports_scores_db_once() {
+    let path = std::env::temp_dir().join(format!("cobra_import_{}.jsonl", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+    let history = ScoreHistory::open(&path);
+    let beatmap = |md5: &str| DbBeatmap {
+        md5: md5.to_string(),
+        title: "Blue Zenith".to_string(),
+        difficulty: "Insane 1.1x".to_string(),
+        folder: "1 Blue Zenith".to_string(),
+        file_name: "map.osu".to_string(),
+        mode: 3,
+        ..DbBeatmap::default()
+    };
+    let index = LibraryIndex::from_db(OsuDb { beatmaps: vec![beatmap("a"), beatmap("b")], ..OsuDb::default() });
+
+    // Already captured on the result screen, a few seconds after the end of the play
+    let mut captured = record("b", 5 * DAY + 30, 100.0, 1.0);
+    captured.score = 1_000_000;
+    history.append(&captured).unwrap();
+
+    let scores = ScoresDb {
+        version: 20250107,
+        scores: vec![
+            db_score("a", "r1", (3 * DAY) as i64, 3),
+            db_score("b", "r2", (5 * DAY) as i64, 3),
+            db_score("c", "r3", (6 * DAY) as i64, 3),
+            db_score("a", "r4", (7 * DAY) as i64, 0),
+            db_score("a", "r5", (2 * DAY) as i64, 3),
+        ],
+    };
+    let summary = import_scores(&history, &scores, &index, Path::new("Songs")).unwrap();
+    assert_eq!((summary.imported, summary.duplicates, summary.unknown_maps), (2, 1, 1));
+
+    let records = history.load().unwrap();
+    let replays: Vec<Option<&str>> = records.iter().map(|record| record.replay_md5.as_deref()).collect();
+    assert_eq!(replays, [None, Some("r5"), Some("r1")]);
+    let imported = &records[2];
+    assert_eq!(imported.timestamp, 3 * DAY);
+    assert_eq!(imported.title, "Blue Zenith");
+    assert!((imported.rate - 1.65).abs() < 1e-9);
+    assert_eq!(imported.accuracy, 1.0);
+    assert_eq!(imported.path, Path::new("Songs").join("1 Blue Zenith").join("map.osu").to_string_lossy());
+    assert_eq!((imported.username.as_str(), imported.max_combo, imported.pp), ("Cobra", 400, 0.0));
+
+    let summary = import_scores(&history, &scores, &index, Path::new("Songs")).unwrap();
+    assert_eq!((summary.imported, summary.duplicates), (0, 3));
+    std::fs::remove_file(&path).unwrap();
+}
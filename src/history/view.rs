@@ -0,0 +1,122 @@
+use std::path::Path;
+use slint::{ComponentHandle, ModelRc, SharedString, VecModel, Weak};
+use crate::history::import::import_scores;
+use crate::history::query::{best_per_map, daily_trend, recent, TrendPoint};
+use crate::history::{now, ScoreHistory, ScoreRecord};
+use crate::library::scores_db::read_scores_db;
+use crate::library::view::library_index;
+use crate::utils::mods::mods_to_string;
+use crate::{HistoryState, LoginPage, ScoreData, TrendData};
+
+/// Number of plays shown in the recent and best lists
+const LIST_SIZE: usize = 50;
+/// Number of days shown in the trend graph
+const TREND_DAYS: u64 = 30;
+
+/// Formats a Unix timestamp as "YYYY-MM-DD HH:MM" (UTC)
+pub fn format_timestamp(timestamp: u64) -> String {
+    let days = (timestamp / 86_400) as i64;
+    let seconds = timestamp % 86_400;
+
+    // Civil date from days since 1970-01-01 (Howard Hinnant's algorithm)
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z - era * 146_097;
+    let yoe = (doe - doe / 1_460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+
+    format!("{:04}-{:02}-{:02} {:02}:{:02}", year, month, day, seconds / 3_600, seconds % 3_600 / 60)
+}
+
+fn score_data(record: &ScoreRecord) -> ScoreData {
+    ScoreData {
+        title: SharedString::from(record.title.as_str()),
+        difficulty: SharedString::from(record.difficulty.as_str()),
+        mods: SharedString::from(mods_to_string(record.mods)),
+        rate: record.rate as f32,
+        accuracy: (record.accuracy * 100.0) as f32,
+        pp: record.pp as f32,
+        unstable_rate: record.unstable_rate as f32,
+        hits: SharedString::from(format!(
+            "{} / {} / {} / {} / {} / {}",
+            record.hit._geki, record.hit._300, record.hit._katu,
+            record.hit._100, record.hit._50, record.hit._miss
+        )),
+        date: SharedString::from(format_timestamp(record.timestamp)),
+    }
+}
+
+fn trend_data(point: &TrendPoint) -> TrendData {
+    TrendData {
+        date: SharedString::from(format_timestamp(point.day)[..10].to_string()),
+        plays: point.plays as i32,
+        average_pp: point.average_pp as f32,
+        best_pp: point.best_pp as f32,
+        accuracy: (point.average_accuracy * 100.0) as f32,
+    }
+}
+
+/// Reloads the history and updates the History page
+///
+/// # Arguments
+/// * `history` - Score history to read
+/// * `weak` - Handle of the main window
+pub fn refresh_history_ui(history: &ScoreHistory, weak: Weak<LoginPage>) {
+    let records = match history.load() {
+        Ok(records) => records,
+        Err(e) => {
+            eprintln!("Impossible de lire l'historique des scores: {}", e);
+            return;
+        }
+    };
+    let recent: Vec<ScoreRecord> = recent(&records, LIST_SIZE);
+    let mut best = best_per_map(&records);
+    best.truncate(LIST_SIZE);
+    let trend = daily_trend(&records, now().saturating_sub(TREND_DAYS * 86_400));
+    let trend_max = trend.iter().map(|p| p.best_pp).fold(1.0, f64::max) as f32;
+    let total = records.len() as i32;
+
+    let _ = weak.upgrade_in_event_loop(move |handle| {
+        let history_state = handle.global::<HistoryState>();
+        history_state.set_total_plays(total);
+        history_state.set_recent(ModelRc::new(VecModel::from(
+            recent.iter().map(score_data).collect::<Vec<ScoreData>>()
+        )));
+        history_state.set_best(ModelRc::new(VecModel::from(
+            best.iter().map(score_data).collect::<Vec<ScoreData>>()
+        )));
+        history_state.set_trend(ModelRc::new(VecModel::from(
+            trend.iter().map(trend_data).collect::<Vec<TrendData>>()
+        )));
+        history_state.set_trend_max(trend_max);
+    });
+}
+
+/// Imports the mania plays of scores.db into the history, then updates the History page
+///
+/// # Arguments
+/// * `history` - Score history the plays are added to
+/// * `songs_dir` - Songs folder of osu!
+/// * `weak` - Handle of the main window
+pub fn import_scores_db(history: &ScoreHistory, songs_dir: &Path, weak: Weak<LoginPage>) {
+    let result = library_index(songs_dir).and_then(|index| {
+        let game_dir = index.game_dir().ok_or_else(|| eyre::eyre!("dossier osu! inconnu"))?;
+        let scores = read_scores_db(&game_dir.join("scores.db")).map_err(|e| eyre::eyre!("scores.db illisible : {}", e))?;
+        import_scores(history, &scores, &index, songs_dir)
+    });
+    let status = match result {
+        Ok(summary) => format!(
+            "{} scores importés, {} déjà présents, {} sur des maps absentes de osu!.db",
+            summary.imported, summary.duplicates, summary.unknown_maps
+        ),
+        Err(e) => format!("Import impossible : {}", e),
+    };
+    let _ = weak.upgrade_in_event_loop(move |handle| {
+        handle.global::<HistoryState>().set_import_status(SharedString::from(status));
+    });
+    refresh_history_ui(history, weak);
+}
@@ -0,0 +1,253 @@
+use std::collections::HashMap;
+use std::fs::OpenOptions;
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use crate::library::osu_db::DbBeatmap;
+use crate::utils::modes::MANIA_MODE;
+use crate::library::LibraryIndex;
+use crate::utils::data_dir::data_dir;
+use crate::utils::msd::{calc_msd, msd_for_rate};
+use crate::utils::nps::get_nps;
+use crate::utils::pattern_detector::{dominant_patterns, get_patterns, pattern_in_family};
+
+/// File of the analysis cache inside the data folder, one JSON map per line
+const ANALYSIS_FILE: &str = "analysis.jsonl";
+/// Maps analyzed between two saves of the cache
+const BATCH_SIZE: usize = 64;
+
+/// Analysis of a mania map of the library
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MapAnalysis {
+    pub md5: String,
+    pub artist: String,
+    pub title: String,
+    pub difficulty: String,
+    /// Path of the .osu file when it was analyzed
+    pub path: String,
+    pub key_count: u8,
+    /// Drain time in seconds, 0 for results cached before it was saved
+    #[serde(default)]
+    pub drain_time: u32,
+    /// Star rating without mods
+    pub stars: f64,
+    /// Overall MSD at 1.0x, 4K only
+    pub msd: Option<f64>,
+    pub avg_nps: f64,
+    pub max_nps: f64,
+    /// Dominant patterns, strongest first
+    pub patterns: Vec<String>,
+}
+
+/// Filters on the analyzed maps, every filter set must match
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct AnalysisQuery {
+    /// Words looked for in the artist, title and difficulty
+    pub text: String,
+    pub key_count: Option<u8>,
+    pub star_min: Option<f64>,
+    pub star_max: Option<f64>,
+    pub msd_min: Option<f64>,
+    pub msd_max: Option<f64>,
+    /// Bounds on the drain time, in seconds
+    pub length_min: Option<u32>,
+    pub length_max: Option<u32>,
+    /// Family of one of the dominant patterns (ex: "Jumpstream" for "Light JS"),
+    /// or a detector pattern
+    pub pattern: Option<String>,
+}
+
+fn in_range(value: f64, min: Option<f64>, max: Option<f64>) -> bool {
+    min.is_none_or(|min| value >= min) && max.is_none_or(|max| value <= max)
+}
+
+impl AnalysisQuery {
+    pub fn matches(&self, analysis: &MapAnalysis) -> bool {
+        if self.key_count.is_some_and(|keys| keys != analysis.key_count) {
+            return false;
+        }
+        if !in_range(analysis.stars, self.star_min, self.star_max) {
+            return false;
+        }
+        let length = f64::from(analysis.drain_time);
+        if !in_range(length, self.length_min.map(f64::from), self.length_max.map(f64::from)) {
+            return false;
+        }
+        if self.msd_min.is_some() || self.msd_max.is_some() {
+            // Maps without MSD are only shown when the MSD is not filtered
+            match analysis.msd {
+                Some(msd) if in_range(msd, self.msd_min, self.msd_max) => {}
+                _ => return false,
+            }
+        }
+        if let Some(pattern) = &self.pattern {
+            if !analysis.patterns.iter().any(|found| pattern_in_family(found, pattern)) {
+                return false;
+            }
+        }
+        let haystack = format!("{} {} {}", analysis.artist, analysis.title, analysis.difficulty).to_lowercase();
+        self.text.to_lowercase().split_whitespace().all(|word| haystack.contains(word))
+    }
+}
+
+/// Analysis results kept between launches, keyed by MD5 so edited maps are analyzed again
+#[derive(Debug, Default)]
+pub struct AnalysisCache {
+    path: PathBuf,
+    entries: HashMap<String, MapAnalysis>,
+}
+
+impl AnalysisCache {
+    /// Opens the cache of the Cobra data folder
+    pub fn open_default() -> eyre::Result<Self> {
+        Self::open(&data_dir()?.join(ANALYSIS_FILE))
+    }
+
+    /// Opens a cache file, created on the first save
+    ///
+    /// Lines that can't be parsed (e.g. a write interrupted by a crash) are skipped.
+    pub fn open(path: &Path) -> eyre::Result<Self> {
+        let mut cache = Self { path: path.to_path_buf(), entries: HashMap::new() };
+        if !path.exists() {
+            return Ok(cache);
+        }
+        let file = std::fs::File::open(path)?;
+        for (i, line) in BufReader::new(file).lines().enumerate() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            match serde_json::from_str::<MapAnalysis>(&line) {
+                Ok(analysis) => {
+                    cache.entries.insert(analysis.md5.clone(), analysis);
+                }
+                Err(e) => eprintln!("Ligne {} du cache d'analyse ignorée: {}", i + 1, e),
+            }
+        }
+        Ok(cache)
+    }
+
+    /// Number of maps analyzed
+    pub fn count(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Saves new analyses at the end of the cache file
+    pub fn append(&mut self, analyses: Vec<MapAnalysis>) -> eyre::Result<()> {
+        if analyses.is_empty() {
+            return Ok(());
+        }
+        let mut file = OpenOptions::new().create(true).append(true).open(&self.path)?;
+        for analysis in analyses {
+            writeln!(file, "{}", serde_json::to_string(&analysis)?)?;
+            self.entries.insert(analysis.md5.clone(), analysis);
+        }
+        Ok(())
+    }
+
+    /// Analyzed maps matching a search, sorted by MSD then stars, hardest first
+    pub fn search(&self, query: &AnalysisQuery) -> Vec<&MapAnalysis> {
+        let mut found: Vec<&MapAnalysis> = self.entries.values().filter(|analysis| query.matches(analysis)).collect();
+        found.sort_by(|a, b| {
+            let key = |analysis: &MapAnalysis| (analysis.msd.unwrap_or(0.0), analysis.stars);
+            key(b).partial_cmp(&key(a)).unwrap_or(std::cmp::Ordering::Equal)
+        });
+        found
+    }
+
+    /// Mania maps of the library that are not in the cache yet
+    pub fn pending(&self, index: &LibraryIndex) -> Vec<DbBeatmap> {
+        index
+            .beatmaps()
+            .iter()
+            .filter(|beatmap| beatmap.mode == MANIA_MODE && !self.entries.contains_key(&beatmap.md5))
+            .cloned()
+            .collect()
+    }
+}
+
+/// Runs the pattern detection, NPS, stars and MSD of a map
+///
+/// # Arguments
+/// * `path` - Path of the .osu file
+/// * `beatmap` - Entry of the map in osu!.db
+pub fn analyze_map(path: &Path, beatmap: &DbBeatmap) -> eyre::Result<MapAnalysis> {
+    let path_str = path
+        .to_str()
+        .ok_or_else(|| eyre::eyre!("Chemin invalide: {}", path.display()))?;
+    let map = rosu_map::from_path::<rosu_map::Beatmap>(path)
+        .map_err(|e| eyre::eyre!("Impossible de lire la map {}: {}", path.display(), e))?;
+
+    let ((msd, stars), (nps, patterns)) = rayon::join(
+        || {
+            let msd = calc_msd(&map).ok().map(|msds| msd_for_rate(&msds, 1.0));
+            // The star rating cached by osu! avoids a computation when present
+            let stars = beatmap.stars(0).unwrap_or_else(|| {
+                rosu_pp::Beatmap::from_path(path)
+                    .map(|map| rosu_pp::Difficulty::new().calculate(&map).stars())
+                    .unwrap_or_default()
+            });
+            (msd, stars)
+        },
+        || (get_nps(path_str, 1.0), get_patterns(path_str)),
+    );
+    let nps = nps?;
+    let max_nps = nps.iter().map(|kv| kv.value).fold(0.0, f64::max);
+    let avg_nps = if nps.is_empty() { 0.0 } else { nps.iter().map(|kv| kv.value).sum::<f64>() / nps.len() as f64 };
+
+    Ok(MapAnalysis {
+        md5: beatmap.md5.clone(),
+        artist: beatmap.artist.clone(),
+        title: beatmap.title.clone(),
+        difficulty: beatmap.difficulty.clone(),
+        path: path_str.to_string(),
+        key_count: beatmap.key_count().unwrap_or(map.circle_size as u8),
+        drain_time: beatmap.drain_time.max(0) as u32,
+        stars,
+        msd,
+        avg_nps,
+        max_nps,
+        patterns: patterns.map(|value| dominant_patterns(&value)).unwrap_or_default(),
+    })
+}
+
+/// Analyzes maps in parallel, by batches so the results can be saved along the way
+///
+/// Maps that can't be read are skipped and tried again on the next run.
+///
+/// # Arguments
+/// * `beatmaps` - Maps to analyze, usually `AnalysisCache::pending`
+/// * `songs_dir` - Songs folder of osu!
+/// * `cancel` - Stops the analysis after the current batch once set
+/// * `on_batch` - Called with the results of each batch, the maps done and the total
+///
+/// # Returns
+/// Number of maps processed
+pub fn analyze_library(
+    beatmaps: &[DbBeatmap],
+    songs_dir: &Path,
+    cancel: &AtomicBool,
+    mut on_batch: impl FnMut(Vec<MapAnalysis>, usize, usize),
+) -> usize {
+    let mut done = 0;
+    for batch in beatmaps.chunks(BATCH_SIZE) {
+        if cancel.load(Ordering::Relaxed) {
+            break;
+        }
+        let results = batch
+            .par_iter()
+            .filter_map(|beatmap| match analyze_map(&beatmap.path(songs_dir), beatmap) {
+                Ok(analysis) => Some(analysis),
+                Err(e) => {
+                    eprintln!("Analyse impossible de {}: {}", beatmap.file_name, e);
+                    None
+                }
+            })
+            .collect();
+        done += batch.len();
+        on_batch(results, done, beatmaps.len());
+    }
+    done
+}
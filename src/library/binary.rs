@@ -0,0 +1,129 @@
+use std::io::{Read, Write};
+
+/// Reads the little endian values of the osu! database files
+pub struct OsuReader<R: Read> {
+    inner: R,
+}
+
+impl<R: Read> OsuReader<R> {
+    pub fn new(inner: R) -> Self {
+        Self { inner }
+    }
+
+    fn read_array<const N: usize>(&mut self) -> eyre::Result<[u8; N]> {
+        let mut buffer = [0; N];
+        self.inner.read_exact(&mut buffer)?;
+        Ok(buffer)
+    }
+
+    pub fn read_u8(&mut self) -> eyre::Result<u8> {
+        Ok(self.read_array::<1>()?[0])
+    }
+
+    pub fn read_bool(&mut self) -> eyre::Result<bool> {
+        Ok(self.read_u8()? != 0)
+    }
+
+    pub fn read_i16(&mut self) -> eyre::Result<i16> {
+        Ok(i16::from_le_bytes(self.read_array()?))
+    }
+
+    pub fn read_i32(&mut self) -> eyre::Result<i32> {
+        Ok(i32::from_le_bytes(self.read_array()?))
+    }
+
+    pub fn read_i64(&mut self) -> eyre::Result<i64> {
+        Ok(i64::from_le_bytes(self.read_array()?))
+    }
+
+    pub fn read_f32(&mut self) -> eyre::Result<f32> {
+        Ok(f32::from_le_bytes(self.read_array()?))
+    }
+
+    pub fn read_f64(&mut self) -> eyre::Result<f64> {
+        Ok(f64::from_le_bytes(self.read_array()?))
+    }
+
+    /// Reads raw bytes, without allocating more than the file contains
+    pub fn read_bytes(&mut self, length: usize) -> eyre::Result<Vec<u8>> {
+        let mut bytes = Vec::new();
+        (&mut self.inner).take(length as u64).read_to_end(&mut bytes)?;
+        if bytes.len() != length {
+            return Err(eyre::eyre!("fin de fichier inattendue"));
+        }
+        Ok(bytes)
+    }
+
+    /// Reads a variable length integer, 7 bits per byte
+    pub fn read_uleb128(&mut self) -> eyre::Result<u64> {
+        let mut value = 0u64;
+        let mut shift = 0;
+        loop {
+            let byte = self.read_u8()?;
+            if shift >= 64 {
+                return Err(eyre::eyre!("entier ULEB128 trop long"));
+            }
+            value |= u64::from(byte & 0x7F) << shift;
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+            shift += 7;
+        }
+    }
+
+    /// Reads a string, `0x00` meaning absent and `0x0b` being followed by its length
+    pub fn read_string(&mut self) -> eyre::Result<String> {
+        match self.read_u8()? {
+            0x00 => Ok(String::new()),
+            0x0b => {
+                let length = self.read_uleb128()? as usize;
+                Ok(String::from_utf8(self.read_bytes(length)?)?)
+            }
+            marker => Err(eyre::eyre!("chaîne invalide (octet {:#04x})", marker)),
+        }
+    }
+}
+
+/// Writes values in the format of the osu! database files
+pub struct OsuWriter<W: Write> {
+    inner: W,
+}
+
+impl<W: Write> OsuWriter<W> {
+    pub fn new(inner: W) -> Self {
+        Self { inner }
+    }
+
+    pub fn into_inner(self) -> W {
+        self.inner
+    }
+
+    pub fn write_u8(&mut self, value: u8) -> eyre::Result<()> {
+        Ok(self.inner.write_all(&[value])?)
+    }
+
+    pub fn write_i32(&mut self, value: i32) -> eyre::Result<()> {
+        Ok(self.inner.write_all(&value.to_le_bytes())?)
+    }
+
+    pub fn write_uleb128(&mut self, mut value: u64) -> eyre::Result<()> {
+        loop {
+            let byte = (value & 0x7F) as u8;
+            value >>= 7;
+            if value == 0 {
+                return self.write_u8(byte);
+            }
+            self.write_u8(byte | 0x80)?;
+        }
+    }
+
+    /// Writes a string, an empty one being written as absent
+    pub fn write_string(&mut self, value: &str) -> eyre::Result<()> {
+        if value.is_empty() {
+            return self.write_u8(0x00);
+        }
+        self.write_u8(0x0b)?;
+        self.write_uleb128(value.len() as u64)?;
+        Ok(self.inner.write_all(value.as_bytes())?)
+    }
+}
@@ -0,0 +1,169 @@
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+use crate::history::now;
+use crate::library::analysis::{AnalysisCache, AnalysisQuery};
+use crate::library::binary::{OsuReader, OsuWriter};
+use crate::utils::pattern_detector::PATTERN_FAMILIES;
+
+/// Prefix of the collections generated by Cobra, the only ones replaced on a new generation
+pub const GENERATED_PREFIX: &str = "Cobra – ";
+/// Version written when osu! has no collection.db yet
+const DEFAULT_VERSION: i32 = 20150203;
+/// Drain time of the maps of the stamina collection, in seconds
+const STAMINA_LENGTH: u32 = 180;
+
+/// Collection of osu!, listing its beatmaps by MD5
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Collection {
+    pub name: String,
+    pub md5s: Vec<String>,
+}
+
+/// Content of collection.db
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CollectionDb {
+    pub version: i32,
+    pub collections: Vec<Collection>,
+}
+
+impl CollectionDb {
+    /// Replaces the collections generated previously by new ones, the others being kept as they are
+    ///
+    /// # Arguments
+    /// * `generated` - Collections to add, their names starting with `GENERATED_PREFIX`
+    pub fn merge_generated(&mut self, generated: Vec<Collection>) {
+        self.collections.retain(|collection| !collection.name.starts_with(GENERATED_PREFIX));
+        self.collections.extend(generated);
+    }
+}
+
+/// Reads collection.db
+///
+/// # Arguments
+/// * `path` - Path of collection.db, in the osu! folder
+pub fn read_collection_db(path: &Path) -> eyre::Result<CollectionDb> {
+    parse_collection_db(BufReader::new(File::open(path)?))
+}
+
+/// Parses the content of collection.db
+///
+/// # Arguments
+/// * `reader` - Content of the file
+pub fn parse_collection_db(reader: impl Read) -> eyre::Result<CollectionDb> {
+    let mut reader = OsuReader::new(reader);
+    let version = reader.read_i32()?;
+    let count = reader.read_i32()?.max(0) as usize;
+    let mut collections = Vec::with_capacity(count.min(1_000));
+    for index in 0..count {
+        let name = reader.read_string()?;
+        let size = reader.read_i32()?.max(0) as usize;
+        let mut md5s = Vec::with_capacity(size.min(100_000));
+        for _ in 0..size {
+            md5s.push(
+                reader
+                    .read_string()
+                    .map_err(|e| eyre::eyre!("collection {} / {} illisible : {}", index + 1, count, e))?,
+            );
+        }
+        collections.push(Collection { name, md5s });
+    }
+    Ok(CollectionDb { version, collections })
+}
+
+/// Writes the content of collection.db
+///
+/// # Arguments
+/// * `db` - Collections to write
+/// * `writer` - Destination of the content
+pub fn write_collection_db(db: &CollectionDb, writer: impl Write) -> eyre::Result<()> {
+    let mut writer = OsuWriter::new(writer);
+    writer.write_i32(db.version)?;
+    writer.write_i32(db.collections.len() as i32)?;
+    for collection in &db.collections {
+        writer.write_string(&collection.name)?;
+        writer.write_i32(collection.md5s.len() as i32)?;
+        for md5 in &collection.md5s {
+            writer.write_string(md5)?;
+        }
+    }
+    Ok(writer.into_inner().flush()?)
+}
+
+/// Collection to generate and the analyzed maps it contains
+#[derive(Debug, Clone)]
+pub struct CollectionRule {
+    pub name: String,
+    pub query: AnalysisQuery,
+}
+
+/// One collection per pattern family and star range, from 1* to 10*, and one of long maps
+pub fn default_rules() -> Vec<CollectionRule> {
+    let mut rules = Vec::new();
+    for (pattern, _) in PATTERN_FAMILIES {
+        for stars in 1..10 {
+            rules.push(CollectionRule {
+                name: format!("{}{} {}–{}*", GENERATED_PREFIX, pattern, stars, stars + 1),
+                query: AnalysisQuery {
+                    pattern: Some(pattern.to_string()),
+                    star_min: Some(f64::from(stars)),
+                    // The upper bound belongs to the next range
+                    star_max: Some(f64::from(stars + 1) - 1e-9),
+                    ..AnalysisQuery::default()
+                },
+            });
+        }
+    }
+    rules.push(CollectionRule {
+        name: format!("{}Stamina > {} min", GENERATED_PREFIX, STAMINA_LENGTH / 60),
+        query: AnalysisQuery { length_min: Some(STAMINA_LENGTH + 1), ..AnalysisQuery::default() },
+    });
+    rules
+}
+
+/// Builds the collections of the rules from the analyzed maps, empty ones being left out
+///
+/// # Arguments
+/// * `cache` - Analysis cache
+/// * `rules` - Collections to generate, usually `default_rules`
+pub fn generate_collections(cache: &AnalysisCache, rules: &[CollectionRule]) -> Vec<Collection> {
+    rules
+        .iter()
+        .map(|rule| Collection {
+            name: rule.name.clone(),
+            md5s: cache.search(&rule.query).into_iter().map(|analysis| analysis.md5.clone()).collect(),
+        })
+        .filter(|collection| !collection.md5s.is_empty())
+        .collect()
+}
+
+/// Adds generated collections to collection.db, after saving a copy of the original file
+///
+/// The file is written next to it then renamed, so an interruption never leaves it half written.
+/// osu! rewrites collection.db when it closes, it should not be running.
+///
+/// # Arguments
+/// * `game_dir` - Folder containing osu!.exe
+/// * `generated` - Collections replacing the ones generated before
+///
+/// # Returns
+/// Path of the backup, `None` when osu! had no collection.db yet
+pub fn save_generated(game_dir: &Path, generated: Vec<Collection>) -> eyre::Result<Option<PathBuf>> {
+    let path = game_dir.join("collection.db");
+    let (mut db, backup) = if path.exists() {
+        let db = read_collection_db(&path).map_err(|e| eyre::eyre!("collection.db illisible : {}", e))?;
+        let backup = game_dir.join(format!("collection.db.{}.bak", now()));
+        std::fs::copy(&path, &backup)?;
+        (db, Some(backup))
+    } else {
+        (CollectionDb { version: DEFAULT_VERSION, collections: Vec::new() }, None)
+    };
+    db.merge_generated(generated);
+
+    let temp = game_dir.join("collection.db.tmp");
+    let mut writer = BufWriter::new(File::create(&temp)?);
+    write_collection_db(&db, &mut writer)?;
+    writer.into_inner().map_err(|e| e.into_error())?.sync_all()?;
+    std::fs::rename(&temp, &path)?;
+    Ok(backup)
+}
@@ -0,0 +1,143 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, RwLock};
+use crate::library::osu_db::{read_osu_db, DbBeatmap, OsuDb};
+use crate::library::search::LibraryQuery;
+
+pub mod analysis;
+pub mod binary;
+pub mod collection;
+pub mod osu_db;
+pub mod scores_db;
+pub mod search;
+pub mod view;
+#[cfg(test)]
+pub(crate) mod tests;
+
+/// Index of the installed beatmaps, loaded from osu!.db
+static INDEX: RwLock<Option<Arc<LibraryIndex>>> = RwLock::new(None);
+
+/// Whether a beatmapset of the browse page is in the local library
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InstallStatus {
+    Missing,
+    /// Some difficulties are missing or were updated since
+    Partial,
+    Installed,
+}
+
+impl InstallStatus {
+    /// Value of `MapData.install-status`
+    pub fn as_ui(self) -> i32 {
+        match self {
+            Self::Missing => 0,
+            Self::Partial => 1,
+            Self::Installed => 2,
+        }
+    }
+}
+
+/// Installed beatmapsets and difficulties
+#[derive(Debug, Default)]
+pub struct LibraryIndex {
+    beatmaps: Vec<DbBeatmap>,
+    /// Positions in `beatmaps` of the difficulties of each beatmapset
+    sets: HashMap<u32, Vec<usize>>,
+    /// Position in `beatmaps` of each MD5
+    md5s: HashMap<String, usize>,
+    /// osu! folder osu!.db was read from
+    game_dir: Option<PathBuf>,
+}
+
+impl LibraryIndex {
+    pub fn from_db(db: OsuDb) -> Self {
+        let mut index = Self::default();
+        for beatmap in db.beatmaps {
+            // Beatmaps not processed by osu! yet have no MD5
+            if beatmap.md5.is_empty() || index.md5s.contains_key(&beatmap.md5) {
+                continue;
+            }
+            let position = index.beatmaps.len();
+            if let Ok(set_id) = u32::try_from(beatmap.set_id) {
+                index.sets.entry(set_id).or_default().push(position);
+            }
+            index.md5s.insert(beatmap.md5.clone(), position);
+            index.beatmaps.push(beatmap);
+        }
+        index
+    }
+
+    /// osu! folder of the library, when it was loaded by `load_index`
+    pub fn game_dir(&self) -> Option<&Path> {
+        self.game_dir.as_deref()
+    }
+
+    /// Every difficulty of the library, in the order of osu!.db
+    pub fn beatmaps(&self) -> &[DbBeatmap] {
+        &self.beatmaps
+    }
+
+    pub fn set_count(&self) -> usize {
+        self.sets.len()
+    }
+
+    pub fn has_set(&self, set_id: u32) -> bool {
+        self.sets.contains_key(&set_id)
+    }
+
+    pub fn has_md5(&self, md5: &str) -> bool {
+        self.md5s.contains_key(md5)
+    }
+
+    pub fn by_md5(&self, md5: &str) -> Option<&DbBeatmap> {
+        self.md5s.get(md5).map(|&position| &self.beatmaps[position])
+    }
+
+    /// Difficulties of a beatmapset, empty when it is not installed
+    pub fn set(&self, set_id: u32) -> Vec<&DbBeatmap> {
+        self.sets
+            .get(&set_id)
+            .map(|positions| positions.iter().map(|&position| &self.beatmaps[position]).collect())
+            .unwrap_or_default()
+    }
+
+    /// Difficulties matching a search, without reading the .osu files
+    pub fn search(&self, query: &LibraryQuery) -> Vec<&DbBeatmap> {
+        self.beatmaps.iter().filter(|beatmap| query.matches(beatmap)).collect()
+    }
+
+    /// Compares a beatmapset of the browse page to the library
+    ///
+    /// # Arguments
+    /// * `set_id` - Id of the beatmapset, when known
+    /// * `md5s` - MD5 of its difficulties, may be empty
+    pub fn status(&self, set_id: Option<u32>, md5s: &[String]) -> InstallStatus {
+        let has_set = set_id.is_some_and(|set_id| self.has_set(set_id));
+        let owned = md5s.iter().filter(|md5| self.has_md5(md5)).count();
+        if (owned > 0 && owned == md5s.len()) || (md5s.is_empty() && has_set) {
+            InstallStatus::Installed
+        } else if owned > 0 || has_set {
+            InstallStatus::Partial
+        } else {
+            InstallStatus::Missing
+        }
+    }
+}
+
+/// Loads osu!.db from the osu! folder and keeps its index for the browse page
+///
+/// # Arguments
+/// * `game_dir` - Folder containing osu!.exe
+pub fn load_index(game_dir: &Path) -> eyre::Result<Arc<LibraryIndex>> {
+    let db = read_osu_db(&game_dir.join("osu!.db"))?;
+    let mut index = LibraryIndex::from_db(db);
+    index.game_dir = Some(game_dir.to_path_buf());
+    let index = Arc::new(index);
+    *INDEX.write().unwrap() = Some(index.clone());
+    Ok(index)
+}
+
+/// Index loaded by the last `load_index`, if any
+pub fn current_index() -> Option<Arc<LibraryIndex>> {
+    INDEX.read().unwrap().clone()
+}
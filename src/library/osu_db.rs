@@ -0,0 +1,273 @@
+use std::fs::File;
+use std::io::{BufReader, Read};
+use std::path::{Path, PathBuf};
+use crate::library::binary::OsuReader;
+use crate::utils::modes::MANIA_MODE;
+
+/// First version storing the difficulty settings as floats and caching star ratings
+const FLOAT_DIFFICULTY_VERSION: i32 = 20140609;
+/// First version without the size of each beatmap entry
+const NO_ENTRY_SIZE_VERSION: i32 = 20191106;
+
+/// Star rating computed by osu! for a combination of mods
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct StarRating {
+    pub mods: i32,
+    pub stars: f64,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct DbTimingPoint {
+    /// Milliseconds per beat, negative for inherited points
+    pub beat_length: f64,
+    pub offset: f64,
+    /// Whether the point sets the BPM
+    pub uninherited: bool,
+}
+
+/// Beatmap listed in osu!.db
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct DbBeatmap {
+    pub artist: String,
+    pub artist_unicode: String,
+    pub title: String,
+    pub title_unicode: String,
+    pub creator: String,
+    pub difficulty: String,
+    pub audio_file: String,
+    pub md5: String,
+    /// Name of the .osu file inside `folder`
+    pub file_name: String,
+    /// 0 unknown, 1 unsubmitted, 2 pending/graveyard, 4 ranked, 5 approved, 6 qualified, 7 loved
+    pub ranked_status: u8,
+    pub circles: i16,
+    pub sliders: i16,
+    pub spinners: i16,
+    /// Windows ticks of the last modification
+    pub last_modified: i64,
+    pub ar: f32,
+    pub cs: f32,
+    pub hp: f32,
+    pub od: f32,
+    pub slider_velocity: f64,
+    /// Star ratings cached by osu! for osu!, taiko, catch and mania
+    pub star_ratings: [Vec<StarRating>; 4],
+    /// Drain time in seconds
+    pub drain_time: i32,
+    /// Total time in milliseconds
+    pub total_time: i32,
+    pub preview_time: i32,
+    pub timing_points: Vec<DbTimingPoint>,
+    pub beatmap_id: i32,
+    /// -1 for beatmaps that were never submitted
+    pub set_id: i32,
+    pub thread_id: i32,
+    /// Best grade in osu!, taiko, catch and mania, 9 when never played
+    pub grades: [u8; 4],
+    pub local_offset: i16,
+    pub stack_leniency: f32,
+    /// 0 osu!, 1 taiko, 2 catch, 3 mania
+    pub mode: u8,
+    pub source: String,
+    pub tags: String,
+    pub online_offset: i16,
+    pub title_font: String,
+    pub unplayed: bool,
+    /// Windows ticks of the last play
+    pub last_played: i64,
+    pub osz2: bool,
+    /// Folder of the beatmapset inside the Songs folder
+    pub folder: String,
+    pub last_checked: i64,
+    pub ignore_sound: bool,
+    pub ignore_skin: bool,
+    pub disable_storyboard: bool,
+    pub disable_video: bool,
+    pub visual_override: bool,
+    pub mania_scroll_speed: u8,
+}
+
+impl DbBeatmap {
+    /// Number of keys, for osu!mania beatmaps only
+    pub fn key_count(&self) -> Option<u8> {
+        (self.mode == MANIA_MODE).then(|| self.cs.round() as u8)
+    }
+
+    /// Star rating cached by osu! in the mode of the beatmap
+    ///
+    /// # Arguments
+    /// * `mods` - Difficulty changing mods, 0 for no mod
+    pub fn stars(&self, mods: i32) -> Option<f64> {
+        self.star_ratings
+            .get(self.mode as usize)?
+            .iter()
+            .find(|rating| rating.mods == mods)
+            .map(|rating| rating.stars)
+    }
+
+    /// Lowest and highest BPM of the beatmap
+    pub fn bpm_range(&self) -> Option<(f64, f64)> {
+        self.timing_points
+            .iter()
+            .filter(|point| point.uninherited && point.beat_length > 0.0)
+            .map(|point| 60_000.0 / point.beat_length)
+            .fold(None, |range, bpm| match range {
+                None => Some((bpm, bpm)),
+                Some((min, max)) => Some((f64::min(min, bpm), f64::max(max, bpm))),
+            })
+    }
+
+    /// Path of the .osu file
+    ///
+    /// # Arguments
+    /// * `songs_dir` - Songs folder of osu!
+    pub fn path(&self, songs_dir: &Path) -> PathBuf {
+        songs_dir.join(&self.folder).join(&self.file_name)
+    }
+}
+
+/// Content of osu!.db
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct OsuDb {
+    pub version: i32,
+    pub folder_count: i32,
+    pub account_unlocked: bool,
+    pub unlock_date: i64,
+    pub player: String,
+    pub beatmaps: Vec<DbBeatmap>,
+    pub permissions: i32,
+}
+
+/// Reads osu!.db
+///
+/// # Arguments
+/// * `path` - Path of osu!.db, in the osu! folder
+pub fn read_osu_db(path: &Path) -> eyre::Result<OsuDb> {
+    parse_osu_db(BufReader::new(File::open(path)?))
+}
+
+/// Parses the content of osu!.db, older formats with byte difficulties and entry sizes included
+///
+/// # Arguments
+/// * `reader` - Content of the file
+pub fn parse_osu_db(reader: impl Read) -> eyre::Result<OsuDb> {
+    let mut reader = OsuReader::new(reader);
+    let version = reader.read_i32()?;
+    let folder_count = reader.read_i32()?;
+    let account_unlocked = reader.read_bool()?;
+    let unlock_date = reader.read_i64()?;
+    let player = reader.read_string()?;
+
+    let count = reader.read_i32()?.max(0) as usize;
+    // The count comes from the file, it is not trusted for the allocation
+    let mut beatmaps = Vec::with_capacity(count.min(100_000));
+    for index in 0..count {
+        let beatmap = read_beatmap(&mut reader, version)
+            .map_err(|e| eyre::eyre!("beatmap {} / {} illisible : {}", index + 1, count, e))?;
+        beatmaps.push(beatmap);
+    }
+    // Missing in some old files
+    let permissions = reader.read_i32().unwrap_or_default();
+    Ok(OsuDb { version, folder_count, account_unlocked, unlock_date, player, beatmaps, permissions })
+}
+
+fn read_difficulty<R: Read>(reader: &mut OsuReader<R>, version: i32) -> eyre::Result<f32> {
+    if version >= FLOAT_DIFFICULTY_VERSION {
+        reader.read_f32()
+    } else {
+        Ok(f32::from(reader.read_u8()?))
+    }
+}
+
+fn read_star_ratings<R: Read>(reader: &mut OsuReader<R>) -> eyre::Result<Vec<StarRating>> {
+    let count = reader.read_i32()?.max(0) as usize;
+    let mut ratings = Vec::with_capacity(count.min(64));
+    for _ in 0..count {
+        // Each value is preceded by its type: 0x08 int, 0x0c float, 0x0d double
+        reader.read_u8()?;
+        let mods = reader.read_i32()?;
+        let stars = match reader.read_u8()? {
+            0x0c => f64::from(reader.read_f32()?),
+            0x0d => reader.read_f64()?,
+            marker => return Err(eyre::eyre!("type de star rating inconnu : {:#04x}", marker)),
+        };
+        ratings.push(StarRating { mods, stars });
+    }
+    Ok(ratings)
+}
+
+fn read_beatmap<R: Read>(reader: &mut OsuReader<R>, version: i32) -> eyre::Result<DbBeatmap> {
+    if version < NO_ENTRY_SIZE_VERSION {
+        let _entry_size = reader.read_i32()?;
+    }
+    let mut beatmap = DbBeatmap {
+        artist: reader.read_string()?,
+        artist_unicode: reader.read_string()?,
+        title: reader.read_string()?,
+        title_unicode: reader.read_string()?,
+        creator: reader.read_string()?,
+        difficulty: reader.read_string()?,
+        audio_file: reader.read_string()?,
+        md5: reader.read_string()?,
+        file_name: reader.read_string()?,
+        ranked_status: reader.read_u8()?,
+        circles: reader.read_i16()?,
+        sliders: reader.read_i16()?,
+        spinners: reader.read_i16()?,
+        last_modified: reader.read_i64()?,
+        ar: read_difficulty(reader, version)?,
+        cs: read_difficulty(reader, version)?,
+        hp: read_difficulty(reader, version)?,
+        od: read_difficulty(reader, version)?,
+        slider_velocity: reader.read_f64()?,
+        ..DbBeatmap::default()
+    };
+    if version >= FLOAT_DIFFICULTY_VERSION {
+        for ratings in beatmap.star_ratings.iter_mut() {
+            *ratings = read_star_ratings(reader)?;
+        }
+    }
+    beatmap.drain_time = reader.read_i32()?;
+    beatmap.total_time = reader.read_i32()?;
+    beatmap.preview_time = reader.read_i32()?;
+
+    let timing_points = reader.read_i32()?.max(0) as usize;
+    beatmap.timing_points = Vec::with_capacity(timing_points.min(10_000));
+    for _ in 0..timing_points {
+        beatmap.timing_points.push(DbTimingPoint {
+            beat_length: reader.read_f64()?,
+            offset: reader.read_f64()?,
+            uninherited: reader.read_bool()?,
+        });
+    }
+
+    beatmap.beatmap_id = reader.read_i32()?;
+    beatmap.set_id = reader.read_i32()?;
+    beatmap.thread_id = reader.read_i32()?;
+    for grade in beatmap.grades.iter_mut() {
+        *grade = reader.read_u8()?;
+    }
+    beatmap.local_offset = reader.read_i16()?;
+    beatmap.stack_leniency = reader.read_f32()?;
+    beatmap.mode = reader.read_u8()?;
+    beatmap.source = reader.read_string()?;
+    beatmap.tags = reader.read_string()?;
+    beatmap.online_offset = reader.read_i16()?;
+    beatmap.title_font = reader.read_string()?;
+    beatmap.unplayed = reader.read_bool()?;
+    beatmap.last_played = reader.read_i64()?;
+    beatmap.osz2 = reader.read_bool()?;
+    beatmap.folder = reader.read_string()?;
+    beatmap.last_checked = reader.read_i64()?;
+    beatmap.ignore_sound = reader.read_bool()?;
+    beatmap.ignore_skin = reader.read_bool()?;
+    beatmap.disable_storyboard = reader.read_bool()?;
+    beatmap.disable_video = reader.read_bool()?;
+    beatmap.visual_override = reader.read_bool()?;
+    if version < FLOAT_DIFFICULTY_VERSION {
+        let _unknown = reader.read_i16()?;
+    }
+    let _last_modification = reader.read_i32()?;
+    beatmap.mania_scroll_speed = reader.read_u8()?;
+    Ok(beatmap)
+}
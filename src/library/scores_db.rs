@@ -0,0 +1,115 @@
+use std::fs::File;
+use std::io::{BufReader, Read};
+use std::path::Path;
+use crate::library::binary::OsuReader;
+use crate::reader::structs::Hit;
+
+/// Target Practice, the only mod adding a value to the score
+const TARGET_PRACTICE: u32 = 1 << 23;
+/// Windows ticks at the Unix epoch
+const UNIX_EPOCH_TICKS: i64 = 621_355_968_000_000_000;
+const TICKS_PER_SECOND: i64 = 10_000_000;
+
+/// Local play stored in scores.db
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct DbScore {
+    /// 0 osu!, 1 taiko, 2 catch, 3 mania
+    pub mode: u8,
+    /// Version of osu! the play was set on
+    pub version: i32,
+    pub beatmap_md5: String,
+    pub player: String,
+    /// MD5 of the replay, names the file in Data/r
+    pub replay_md5: String,
+    pub hit: Hit,
+    pub score: i32,
+    pub max_combo: i16,
+    pub perfect: bool,
+    pub mods: u32,
+    /// Windows ticks of the play
+    pub date: i64,
+    /// 0 for plays that were not submitted
+    pub online_id: i64,
+    /// Accuracy shown for Target Practice plays
+    pub target_accuracy: Option<f64>,
+}
+
+impl DbScore {
+    /// Unix timestamp of the play, in seconds
+    pub fn timestamp(&self) -> u64 {
+        ((self.date - UNIX_EPOCH_TICKS) / TICKS_PER_SECOND).max(0) as u64
+    }
+}
+
+/// Content of scores.db
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ScoresDb {
+    pub version: i32,
+    /// Plays of every beatmap, grouped by beatmap in the file
+    pub scores: Vec<DbScore>,
+}
+
+/// Reads scores.db
+///
+/// # Arguments
+/// * `path` - Path of scores.db, in the osu! folder
+pub fn read_scores_db(path: &Path) -> eyre::Result<ScoresDb> {
+    parse_scores_db(BufReader::new(File::open(path)?))
+}
+
+/// Parses the content of scores.db
+///
+/// # Arguments
+/// * `reader` - Content of the file
+pub fn parse_scores_db(reader: impl Read) -> eyre::Result<ScoresDb> {
+    let mut reader = OsuReader::new(reader);
+    let version = reader.read_i32()?;
+    let count = reader.read_i32()?.max(0) as usize;
+    let mut scores = Vec::new();
+    for index in 0..count {
+        let _beatmap_md5 = reader.read_string()?;
+        let plays = reader.read_i32()?.max(0) as usize;
+        for _ in 0..plays {
+            let (score, _replay) = read_score(&mut reader)
+                .map_err(|e| eyre::eyre!("beatmap {} / {} illisible : {}", index + 1, count, e))?;
+            scores.push(score);
+        }
+    }
+    Ok(ScoresDb { version, scores })
+}
+
+/// Reads a play as stored in scores.db and in the header of .osr files
+///
+/// # Returns
+/// The play and its compressed replay data, empty in scores.db
+pub(crate) fn read_score<R: Read>(reader: &mut OsuReader<R>) -> eyre::Result<(DbScore, Vec<u8>)> {
+    let mut score = DbScore {
+        mode: reader.read_u8()?,
+        version: reader.read_i32()?,
+        beatmap_md5: reader.read_string()?,
+        player: reader.read_string()?,
+        replay_md5: reader.read_string()?,
+        ..DbScore::default()
+    };
+    score.hit._300 = reader.read_i16()?;
+    score.hit._100 = reader.read_i16()?;
+    score.hit._50 = reader.read_i16()?;
+    score.hit._geki = reader.read_i16()?;
+    score.hit._katu = reader.read_i16()?;
+    score.hit._miss = reader.read_i16()?;
+    score.score = reader.read_i32()?;
+    score.max_combo = reader.read_i16()?;
+    score.perfect = reader.read_bool()?;
+    score.mods = reader.read_i32()? as u32;
+    // Life bar graph, always empty in scores.db
+    let _life_bar = reader.read_string()?;
+    score.date = reader.read_i64()?;
+    // Size of the replay data, -1 in scores.db
+    let replay_size = reader.read_i32()?.max(0) as usize;
+    let replay = reader.read_bytes(replay_size)?;
+    score.online_id = reader.read_i64()?;
+    if score.mods & TARGET_PRACTICE != 0 {
+        score.target_accuracy = Some(reader.read_f64()?);
+    }
+    Ok((score, replay))
+}
@@ -0,0 +1,53 @@
+use crate::library::osu_db::DbBeatmap;
+
+/// Filters on the local library, every filter set must match
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct LibraryQuery {
+    /// Words looked for in the artist, title, creator, difficulty, source and tags
+    pub text: String,
+    /// 0 osu!, 1 taiko, 2 catch, 3 mania
+    pub mode: Option<u8>,
+    pub key_count: Option<u8>,
+    /// Bounds on the star rating cached by osu!, without mods
+    pub star_min: Option<f64>,
+    pub star_max: Option<f64>,
+}
+
+impl LibraryQuery {
+    pub fn matches(&self, beatmap: &DbBeatmap) -> bool {
+        if self.mode.is_some_and(|mode| mode != beatmap.mode) {
+            return false;
+        }
+        if self.key_count.is_some() && beatmap.key_count() != self.key_count {
+            return false;
+        }
+        if self.star_min.is_some() || self.star_max.is_some() {
+            let Some(stars) = beatmap.stars(0) else {
+                return false;
+            };
+            if self.star_min.is_some_and(|min| stars < min) || self.star_max.is_some_and(|max| stars > max) {
+                return false;
+            }
+        }
+        self.matches_text(beatmap)
+    }
+
+    fn matches_text(&self, beatmap: &DbBeatmap) -> bool {
+        let haystack = [
+            &beatmap.artist,
+            &beatmap.artist_unicode,
+            &beatmap.title,
+            &beatmap.title_unicode,
+            &beatmap.creator,
+            &beatmap.difficulty,
+            &beatmap.source,
+            &beatmap.tags,
+        ]
+        .map(|field| field.to_lowercase())
+        .join(" ");
+        self.text
+            .to_lowercase()
+            .split_whitespace()
+            .all(|word| haystack.contains(word))
+    }
+}
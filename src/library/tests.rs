@@ -0,0 +1,462 @@
+use std::io::Cursor;
+use std::path::Path;
+use crate::reader::structs::Hit;
+use crate::library::analysis::{AnalysisCache, AnalysisQuery, MapAnalysis};
+use crate::library::binary::OsuReader;
+use crate::library::collection::{
+    default_rules, generate_collections, parse_collection_db, read_collection_db, save_generated, write_collection_db, Collection,
+    CollectionDb, GENERATED_PREFIX,
+};
+use crate::library::osu_db::{parse_osu_db, DbBeatmap, DbTimingPoint, OsuDb, StarRating};
+use crate::library::scores_db::{parse_scores_db, DbScore};
+use crate::library::search::LibraryQuery;
+use crate::library::{InstallStatus, LibraryIndex};
+
+/// Writes the values the way osu! does, to build databases and replays in memory
+#[derive(Default)]
+pub(crate) struct DbWriter {
+    pub(crate) bytes: Vec<u8>,
+}
+
+impl DbWriter {
+    pub(crate) fn u8(&mut self, value: u8) -> &mut Self {
+        self.bytes.push(value);
+        self
+    }
+
+    pub(crate) fn i16(&mut self, value: i16) -> &mut Self {
+        self.bytes.extend_from_slice(&value.to_le_bytes());
+        self
+    }
+
+    pub(crate) fn i32(&mut self, value: i32) -> &mut Self {
+        self.bytes.extend_from_slice(&value.to_le_bytes());
+        self
+    }
+
+    pub(crate) fn i64(&mut self, value: i64) -> &mut Self {
+        self.bytes.extend_from_slice(&value.to_le_bytes());
+        self
+    }
+
+    pub(crate) fn f32(&mut self, value: f32) -> &mut Self {
+        self.bytes.extend_from_slice(&value.to_le_bytes());
+        self
+    }
+
+    pub(crate) fn f64(&mut self, value: f64) -> &mut Self {
+        self.bytes.extend_from_slice(&value.to_le_bytes());
+        self
+    }
+
+    pub(crate) fn string(&mut self, value: &str) -> &mut Self {
+        if value.is_empty() {
+            return self.u8(0x00);
+        }
+        self.u8(0x0b);
+        let mut length = value.len();
+        loop {
+            let byte = (length & 0x7F) as u8;
+            length >>= 7;
+            if length == 0 {
+                self.u8(byte);
+                break;
+            }
+            self.u8(byte | 0x80);
+        }
+        self.bytes.extend_from_slice(value.as_bytes());
+        self
+    }
+
+    pub(crate) fn bool(&mut self, value: bool) -> &mut Self {
+        self.u8(value as u8)
+    }
+
+    /// Fields shared by the scores of scores.db and the header of .osr files, up to the mods
+    pub(crate) fn score_header(&mut self, score: &DbScore) -> &mut Self {
+        self.u8(score.mode).i32(score.version).string(&score.beatmap_md5).string(&score.player).string(&score.replay_md5);
+        self.i16(score.hit._300).i16(score.hit._100).i16(score.hit._50);
+        self.i16(score.hit._geki).i16(score.hit._katu).i16(score.hit._miss);
+        self.i32(score.score).i16(score.max_combo).bool(score.perfect).i32(score.mods as i32)
+    }
+
+    fn score(&mut self, score: &DbScore) -> &mut Self {
+        self.score_header(score).string("").i64(score.date).i32(-1).i64(score.online_id);
+        if let Some(accuracy) = score.target_accuracy {
+            self.f64(accuracy);
+        }
+        self
+    }
+
+    fn beatmap(&mut self, version: i32, beatmap: &DbBeatmap) -> &mut Self {
+        if version < 20191106 {
+            // Old versions prefix each beatmap with its size
+            let mut entry = DbWriter::default();
+            entry.beatmap_fields(version, beatmap);
+            self.i32(entry.bytes.len() as i32);
+            self.bytes.extend_from_slice(&entry.bytes);
+            return self;
+        }
+        self.beatmap_fields(version, beatmap)
+    }
+
+    fn beatmap_fields(&mut self, version: i32, beatmap: &DbBeatmap) -> &mut Self {
+        self.string(&beatmap.artist).string(&beatmap.artist_unicode);
+        self.string(&beatmap.title).string(&beatmap.title_unicode);
+        self.string(&beatmap.creator).string(&beatmap.difficulty).string(&beatmap.audio_file);
+        self.string(&beatmap.md5).string(&beatmap.file_name);
+        self.u8(beatmap.ranked_status).i16(beatmap.circles).i16(beatmap.sliders).i16(beatmap.spinners);
+        self.i64(beatmap.last_modified);
+        for value in [beatmap.ar, beatmap.cs, beatmap.hp, beatmap.od] {
+            if version >= 20140609 {
+                self.f32(value);
+            } else {
+                self.u8(value as u8);
+            }
+        }
+        self.f64(beatmap.slider_velocity);
+        if version >= 20140609 {
+            for ratings in &beatmap.star_ratings {
+                self.i32(ratings.len() as i32);
+                for rating in ratings {
+                    self.u8(0x08).i32(rating.mods);
+                    if version >= 20250107 {
+                        self.u8(0x0c).f32(rating.stars as f32);
+                    } else {
+                        self.u8(0x0d).f64(rating.stars);
+                    }
+                }
+            }
+        }
+        self.i32(beatmap.drain_time).i32(beatmap.total_time).i32(beatmap.preview_time);
+        self.i32(beatmap.timing_points.len() as i32);
+        for point in &beatmap.timing_points {
+            self.f64(point.beat_length).f64(point.offset).bool(point.uninherited);
+        }
+        self.i32(beatmap.beatmap_id).i32(beatmap.set_id).i32(beatmap.thread_id);
+        for grade in beatmap.grades {
+            self.u8(grade);
+        }
+        self.i16(beatmap.local_offset).f32(beatmap.stack_leniency).u8(beatmap.mode);
+        self.string(&beatmap.source).string(&beatmap.tags).i16(beatmap.online_offset).string(&beatmap.title_font);
+        self.bool(beatmap.unplayed).i64(beatmap.last_played).bool(beatmap.osz2).string(&beatmap.folder);
+        self.i64(beatmap.last_checked).bool(beatmap.ignore_sound).bool(beatmap.ignore_skin);
+        self.bool(beatmap.disable_storyboard).bool(beatmap.disable_video).bool(beatmap.visual_override);
+        if version < 20140609 {
+            self.i16(0);
+        }
+        self.i32(0).u8(beatmap.mania_scroll_speed)
+    }
+
+    fn osu_db(version: i32, beatmaps: &[DbBeatmap]) -> Vec<u8> {
+        let mut writer = DbWriter::default();
+        writer.i32(version).i32(2).bool(true).i64(0).string("Player");
+        writer.i32(beatmaps.len() as i32);
+        for beatmap in beatmaps {
+            writer.beatmap(version, beatmap);
+        }
+        writer.i32(0);
+        writer.bytes
+    }
+}
+
+fn beatmap(set_id: i32, beatmap_id: i32, md5: &str, mode: u8, keys: f32, stars: f64) -> DbBeatmap {
+    DbBeatmap {
+        artist: "Camellia".to_string(),
+        title: "Exit This Earth's Atomosphere".to_string(),
+        creator: "Mapper".to_string(),
+        difficulty: format!("Diff {}", beatmap_id),
+        audio_file: "audio.mp3".to_string(),
+        md5: md5.to_string(),
+        file_name: format!("map {}.osu", beatmap_id),
+        ranked_status: 4,
+        circles: 1500,
+        sliders: 200,
+        last_modified: 638_000_000_000_000_000,
+        ar: 9.0,
+        cs: keys,
+        hp: 8.0,
+        od: 8.0,
+        slider_velocity: 1.4,
+        star_ratings: std::array::from_fn(|m| {
+            if m == mode as usize {
+                vec![StarRating { mods: 0, stars }, StarRating { mods: 64, stars: stars + 1.5 }]
+            } else {
+                Vec::new()
+            }
+        }),
+        drain_time: 120,
+        total_time: 125_000,
+        preview_time: 40_000,
+        timing_points: vec![
+            DbTimingPoint { beat_length: 300.0, offset: 0.0, uninherited: true },
+            DbTimingPoint { beat_length: -100.0, offset: 16_000.0, uninherited: false },
+            DbTimingPoint { beat_length: 250.0, offset: 32_000.0, uninherited: true },
+        ],
+        beatmap_id,
+        set_id,
+        grades: [9; 4],
+        stack_leniency: 0.7,
+        mode,
+        tags: "jumpstream".to_string(),
+        folder: format!("{} Camellia - Exit This Earth's Atomosphere", set_id),
+        mania_scroll_speed: 24,
+        ..DbBeatmap::default()
+    }
+}
+
+fn library() -> Vec<DbBeatmap> {
+    vec![
+        beatmap(100, 1, "aaaa", 3, 4.0, 4.5),
+        beatmap(100, 2, "bbbb", 3, 7.0, 5.5),
+        beatmap(200, 3, "cccc", 3, 4.0, 2.5),
+        beatmap(-1, 0, "dddd", 0, 4.0, 6.0),
+    ]
+}
+
+#[test]
+fn reads_strings_and_integers() {
+    let mut writer = DbWriter::default();
+    writer.string("").string(&"é".repeat(100)).i32(-5).u8(0x01);
+    let mut reader = OsuReader::new(Cursor::new(writer.bytes));
+    assert_eq!(reader.read_string().unwrap(), "");
+    assert_eq!(reader.read_string().unwrap(), "é".repeat(100));
+    assert_eq!(reader.read_i32().unwrap(), -5);
+    assert!(reader.read_string().is_err());
+}
+
+#[test]
+fn parses_osu_db_versions() {
+    for version in [20131216, 20150203, 20191106, 20250107] {
+        let db = parse_osu_db(Cursor::new(DbWriter::osu_db(version, &library()))).unwrap();
+        let mut expected = library();
+        if version < 20140609 {
+            expected.iter_mut().for_each(|beatmap| beatmap.star_ratings = Default::default());
+        }
+        assert_eq!(db.version, version);
+        assert_eq!(db.player, "Player");
+        assert_eq!(db.beatmaps, expected, "version {}", version);
+    }
+    assert!(parse_osu_db(Cursor::new(DbWriter::osu_db(20191106, &library())[..200].to_vec())).is_err());
+}
+
+#[test]
+fn beatmaps_expose_keys_stars_and_bpm() {
+    let mania = beatmap(100, 1, "aaaa", 3, 7.0, 4.5);
+    assert_eq!(mania.key_count(), Some(7));
+    assert_eq!(mania.stars(0), Some(4.5));
+    assert_eq!(mania.stars(16), None);
+    assert_eq!(mania.bpm_range(), Some((200.0, 240.0)));
+    let path = mania.path(Path::new("C:/osu!/Songs"));
+    assert_eq!(path, Path::new("C:/osu!/Songs").join("100 Camellia - Exit This Earth's Atomosphere").join("map 1.osu"));
+    assert_eq!(beatmap(100, 1, "aaaa", 0, 4.0, 4.5).key_count(), None);
+}
+
+#[test]
+fn searches_the_library_offline() {
+    let db = OsuDb { beatmaps: library(), ..OsuDb::default() };
+    let index = LibraryIndex::from_db(db);
+    assert_eq!(index.set_count(), 2);
+    assert_eq!(index.set(100).len(), 2);
+    assert_eq!(index.by_md5("cccc").map(|beatmap| beatmap.beatmap_id), Some(3));
+
+    let query = LibraryQuery { text: "camellia JUMPSTREAM".to_string(), mode: Some(3), ..LibraryQuery::default() };
+    assert_eq!(index.search(&query).len(), 3);
+    let query = LibraryQuery { key_count: Some(4), star_min: Some(3.0), star_max: Some(5.0), ..LibraryQuery::default() };
+    let md5s: Vec<&str> = index.search(&query).iter().map(|beatmap| beatmap.md5.as_str()).collect();
+    assert_eq!(md5s, ["aaaa"]);
+    let query = LibraryQuery { text: "diff 2 atomosphere".to_string(), ..LibraryQuery::default() };
+    assert_eq!(index.search(&query).len(), 1);
+}
+
+#[test]
+fn index_compares_sets_and_difficulties() {
+    let db = OsuDb { beatmaps: library(), ..OsuDb::default() };
+    let index = LibraryIndex::from_db(db);
+    let md5s = |values: &[&str]| values.iter().map(|md5| md5.to_string()).collect::<Vec<_>>();
+
+    assert_eq!(index.status(Some(100), &md5s(&["aaaa", "bbbb"])), InstallStatus::Installed);
+    assert_eq!(index.status(Some(100), &md5s(&["aaaa", "eeee"])), InstallStatus::Partial);
+    // The beatmapset was updated since it was downloaded
+    assert_eq!(index.status(Some(200), &md5s(&["ffff"])), InstallStatus::Partial);
+    assert_eq!(index.status(Some(200), &[]), InstallStatus::Installed);
+    assert_eq!(index.status(Some(300), &md5s(&["ffff"])), InstallStatus::Missing);
+    assert_eq!(index.status(None, &md5s(&["dddd"])), InstallStatus::Installed);
+}
+
+fn analysis(md5: &str, keys: u8, stars: f64, msd: Option<f64>, patterns: &[&str]) -> MapAnalysis {
+    MapAnalysis {
+        md5: md5.to_string(),
+        artist: "Camellia".to_string(),
+        title: "Exit This Earth's Atomosphere".to_string(),
+        difficulty: format!("{}K", keys),
+        path: String::new(),
+        key_count: keys,
+        drain_time: 200,
+        stars,
+        msd,
+        avg_nps: 12.0,
+        max_nps: 20.0,
+        patterns: patterns.iter().map(|pattern| pattern.to_string()).collect(),
+    }
+}
+
+#[test]
+fn analysis_cache_persists_and_filters() {
+    let path = std::env::temp_dir().join(format!("cobra_analysis_{}.jsonl", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+
+    let mut cache = AnalysisCache::open(&path).unwrap();
+    cache
+        .append(vec![
+            analysis("aaaa", 4, 4.5, Some(23.5), &["Light JS", "Dense HS"]),
+            analysis("bbbb", 4, 5.0, Some(26.0), &["JT"]),
+            analysis("eeee", 7, 4.8, None, &["Anchor JS"]),
+            analysis("ffff", 4, 4.0, Some(24.0), &["HS"]),
+        ])
+        .unwrap();
+    let cache = AnalysisCache::open(&path).unwrap();
+    assert_eq!(cache.count(), 4);
+
+    let query = AnalysisQuery {
+        key_count: Some(4),
+        msd_min: Some(22.0),
+        msd_max: Some(25.0),
+        pattern: Some("jumpstream".to_string()),
+        ..AnalysisQuery::default()
+    };
+    let md5s: Vec<&str> = cache.search(&query).iter().map(|analysis| analysis.md5.as_str()).collect();
+    assert_eq!(md5s, ["aaaa"]);
+    let md5s: Vec<&str> = cache.search(&AnalysisQuery::default()).iter().map(|analysis| analysis.md5.as_str()).collect();
+    assert_eq!(md5s, ["bbbb", "ffff", "aaaa", "eeee"]);
+    // A family covers its variants, a detector pattern only itself
+    let search = |pattern: &str| -> Vec<String> {
+        let query = AnalysisQuery { pattern: Some(pattern.to_string()), ..AnalysisQuery::default() };
+        cache.search(&query).iter().map(|analysis| analysis.md5.clone()).collect()
+    };
+    assert_eq!(search("Jumpstream"), ["bbbb", "aaaa", "eeee"]);
+    assert_eq!(search("Handstream"), ["ffff", "aaaa"]);
+    assert_eq!(search("Light JS"), ["aaaa"]);
+    assert!(search("Chordjack").is_empty());
+
+    // Only the mania maps missing from the cache are analyzed
+    let index = LibraryIndex::from_db(OsuDb { beatmaps: library(), ..OsuDb::default() });
+    let pending: Vec<String> = cache.pending(&index).into_iter().map(|beatmap| beatmap.md5).collect();
+    assert_eq!(pending, ["cccc"]);
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn collection_db_round_trips() {
+    let db = CollectionDb {
+        version: 20250107,
+        collections: vec![
+            Collection { name: "Favoris ★".to_string(), md5s: vec!["aaaa".to_string(), "bbbb".to_string()] },
+            Collection { name: "Vide".to_string(), md5s: Vec::new() },
+        ],
+    };
+    let mut bytes = Vec::new();
+    write_collection_db(&db, &mut bytes).unwrap();
+    assert_eq!(bytes[..4], 20250107i32.to_le_bytes());
+    assert_eq!(bytes[4..10], [0x02, 0x00, 0x00, 0x00, 0x0b, "Favoris ★".len() as u8]);
+    assert_eq!(parse_collection_db(Cursor::new(bytes.as_slice())).unwrap(), db);
+    assert!(parse_collection_db(Cursor::new(&bytes[..bytes.len() - 2])).is_err());
+}
+
+#[test]
+fn generates_and_merges_collections() {
+    let path = std::env::temp_dir().join(format!("cobra_collections_{}.jsonl", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+    let mut cache = AnalysisCache::open(&path).unwrap();
+    let mut short = analysis("cccc", 4, 3.2, None, &["Speedjack"]);
+    short.drain_time = 90;
+    cache
+        .append(vec![
+            analysis("aaaa", 4, 4.5, Some(23.5), &["Dense Chordjack", "Light JS"]),
+            analysis("bbbb", 4, 5.0, Some(26.0), &["Chordjack", "ChordStream"]),
+            short,
+        ])
+        .unwrap();
+
+    let collections = generate_collections(&cache, &default_rules());
+    let names: Vec<&str> = collections.iter().map(|collection| collection.name.as_str()).collect();
+    assert_eq!(
+        names,
+        [
+            "Cobra – Jack 3–4*",
+            "Cobra – Jack 4–5*",
+            "Cobra – Jack 5–6*",
+            "Cobra – Chordjack 4–5*",
+            "Cobra – Chordjack 5–6*",
+            "Cobra – Speedjack 3–4*",
+            "Cobra – Jumpstream 4–5*",
+            "Cobra – Chordstream 5–6*",
+            "Cobra – Stamina > 3 min",
+        ]
+    );
+    assert_eq!(collections[0].md5s, ["cccc"]);
+    assert_eq!(collections[3].md5s, ["aaaa"]);
+    assert_eq!(collections[6].md5s, ["aaaa"]);
+    assert_eq!(collections[7].md5s, ["bbbb"]);
+    let mut stamina = collections[8].md5s.clone();
+    stamina.sort();
+    assert_eq!(stamina, ["aaaa", "bbbb"]);
+
+    // The collections of the player are kept, the generated ones replaced
+    let game_dir = std::env::temp_dir().join(format!("cobra_game_{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&game_dir);
+    std::fs::create_dir_all(&game_dir).unwrap();
+    assert_eq!(save_generated(&game_dir, vec![Collection { name: format!("{}Ancienne", GENERATED_PREFIX), md5s: vec!["ffff".to_string()] }]).unwrap(), None);
+    let mut original = read_collection_db(&game_dir.join("collection.db")).unwrap();
+    original.collections.insert(0, Collection { name: "Favoris".to_string(), md5s: vec!["dddd".to_string()] });
+    let mut bytes = Vec::new();
+    write_collection_db(&original, &mut bytes).unwrap();
+    std::fs::write(game_dir.join("collection.db"), &bytes).unwrap();
+
+    let backup = save_generated(&game_dir, collections.clone()).unwrap().unwrap();
+    assert_eq!(std::fs::read(&backup).unwrap(), bytes);
+    let saved = read_collection_db(&game_dir.join("collection.db")).unwrap();
+    assert_eq!(saved.version, original.version);
+    assert_eq!(saved.collections[0], original.collections[0]);
+    assert_eq!(saved.collections[1..], collections[..]);
+    std::fs::remove_dir_all(&game_dir).unwrap();
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn parses_scores_db() {
+    let mania = DbScore {
+        mode: 3,
+        version: 20250107,
+        beatmap_md5: "aaaa".to_string(),
+        player: "Cobra".to_string(),
+        replay_md5: "r1".to_string(),
+        hit: Hit { _geki: 800, _300: 400, _katu: 50, _100: 20, _50: 5, _miss: 3 },
+        score: 950_000,
+        max_combo: 900,
+        perfect: false,
+        mods: 64,
+        // 2024-02-29 12:34:56 UTC
+        date: 621_355_968_000_000_000 + 1_709_210_096 * 10_000_000,
+        online_id: 0,
+        target_accuracy: None,
+    };
+    let target = DbScore {
+        mode: 0,
+        beatmap_md5: "dddd".to_string(),
+        replay_md5: "r2".to_string(),
+        mods: 1 << 23,
+        target_accuracy: Some(0.75),
+        ..mania.clone()
+    };
+    let mut writer = DbWriter::default();
+    writer.i32(20250107).i32(2);
+    writer.string("aaaa").i32(1).score(&mania);
+    writer.string("dddd").i32(1).score(&target);
+
+    let db = parse_scores_db(Cursor::new(writer.bytes.as_slice())).unwrap();
+    assert_eq!(db.version, 20250107);
+    assert_eq!(db.scores, [mania.clone(), target]);
+    assert_eq!(mania.timestamp(), 1_709_210_096);
+    assert!(parse_scores_db(Cursor::new(&writer.bytes[..writer.bytes.len() - 4])).is_err());
+}
@@ -0,0 +1,183 @@
+use std::path::Path;
+use std::sync::atomic::AtomicBool;
+use std::sync::{Arc, Mutex};
+use slint::{ComponentHandle, Model, ModelRc, SharedString, VecModel, Weak};
+use crate::library::analysis::{analyze_library, AnalysisCache, AnalysisQuery, MapAnalysis};
+use crate::library::collection::{default_rules, generate_collections, save_generated};
+use crate::library::{current_index, load_index, LibraryIndex};
+use crate::reader::osu_running;
+use crate::utils::mirror::parse_set_id;
+use crate::{LibraryMapData, LibraryState, LoginPage, MapSetState};
+
+/// Number of analyzed maps listed on the library page
+const LIST_SIZE: usize = 200;
+
+/// Value of `MapData.install-status` for a beatmapset of the browse page
+///
+/// # Arguments
+/// * `index` - Installed beatmaps
+/// * `link` - Link of the beatmapset
+/// * `md5s` - MD5 of its difficulties, separated by commas
+pub fn install_status(index: &LibraryIndex, link: &str, md5s: &str) -> i32 {
+    let md5s: Vec<String> = md5s
+        .split(',')
+        .filter(|md5| !md5.is_empty())
+        .map(str::to_string)
+        .collect();
+    index.status(parse_set_id(link), &md5s).as_ui()
+}
+
+/// Marks the beatmapsets of the browse page with the current library index
+///
+/// # Arguments
+/// * `weak` - Weak reference to the main window
+pub fn refresh_install_status(weak: Weak<LoginPage>) {
+    let Some(index) = current_index() else {
+        return;
+    };
+    let _ = weak.upgrade_in_event_loop(move |handle| {
+        let maps = handle.global::<MapSetState>().get_maps();
+        for row in 0..maps.row_count() {
+            let Some(mut map) = maps.row_data(row) else {
+                continue;
+            };
+            let status = install_status(&index, &map.link, &map.md5);
+            // Never downgraded, osu!.db only lists the maps imported before it was read
+            if status > map.install_status {
+                map.install_status = status;
+                maps.set_row_data(row, map);
+            }
+        }
+    });
+}
+
+/// Builds the search of the library page from its filters
+///
+/// # Arguments
+/// * `window` - Main window holding `LibraryState`
+pub fn library_query(window: &LoginPage) -> AnalysisQuery {
+    let state = window.global::<LibraryState>();
+    let number = |text: SharedString| text.trim().replace(',', ".").parse::<f64>().ok();
+    let choice = |value: SharedString, any: &str| (value.as_str() != any).then(|| value.to_string());
+
+    AnalysisQuery {
+        text: state.get_text().to_string(),
+        key_count: choice(state.get_keys(), "Toutes").and_then(|keys| keys.trim_end_matches('K').parse().ok()),
+        star_min: number(state.get_star_min()),
+        star_max: number(state.get_star_max()),
+        msd_min: number(state.get_msd_min()),
+        msd_max: number(state.get_msd_max()),
+        pattern: choice(state.get_pattern(), "Tous"),
+        ..AnalysisQuery::default()
+    }
+}
+
+fn library_map_data(analysis: &MapAnalysis) -> LibraryMapData {
+    LibraryMapData {
+        title: SharedString::from(format!("{} - {}", analysis.artist, analysis.title)),
+        difficulty: SharedString::from(analysis.difficulty.as_str()),
+        keys: analysis.key_count as i32,
+        stars: analysis.stars as f32,
+        msd: analysis.msd.map(|msd| msd as f32).unwrap_or(-1.0),
+        max_nps: analysis.max_nps as f32,
+        patterns: SharedString::from(analysis.patterns.join(", ")),
+    }
+}
+
+/// Lists the analyzed maps matching the filters of the library page
+///
+/// # Arguments
+/// * `cache` - Analysis cache
+/// * `window` - Main window
+pub fn refresh_library_ui(cache: &AnalysisCache, window: &LoginPage) {
+    let found = cache.search(&library_query(window));
+    let rows: Vec<LibraryMapData> = found.iter().take(LIST_SIZE).map(|analysis| library_map_data(analysis)).collect();
+    let state = window.global::<LibraryState>();
+    state.set_total(found.len() as i32);
+    state.set_maps(ModelRc::new(VecModel::from(rows)));
+    if !state.get_running() {
+        state.set_status(SharedString::from(format!("{} maps analysées", cache.count())));
+    }
+}
+
+fn show_progress(weak: &Weak<LoginPage>, status: String, progress: f32, running: bool) {
+    let _ = weak.upgrade_in_event_loop(move |handle| {
+        let state = handle.global::<LibraryState>();
+        state.set_status(SharedString::from(status));
+        state.set_progress(progress);
+        state.set_running(running);
+        if !running {
+            state.invoke_filter();
+        }
+    });
+}
+
+/// Index of the library, osu!.db being read next to the Songs folder when osu! was not detected
+pub fn library_index(songs_dir: &Path) -> eyre::Result<Arc<LibraryIndex>> {
+    match current_index() {
+        Some(index) => Ok(index),
+        None => match songs_dir.parent() {
+            Some(game_dir) if !songs_dir.as_os_str().is_empty() => load_index(game_dir),
+            _ => Err(eyre::eyre!("dossier Songs inconnu")),
+        },
+    }
+}
+
+/// Analyzes the mania maps of the library missing from the cache, blocking until done or cancelled
+///
+/// # Arguments
+/// * `cache` - Analysis cache, saved after each batch
+/// * `cancel` - Set to stop the analysis
+/// * `songs_dir` - Songs folder of osu!, osu!.db being read next to it when osu! is not running
+/// * `weak` - Weak reference to the main window
+pub fn analyze_in_background(cache: &Mutex<AnalysisCache>, cancel: &AtomicBool, songs_dir: &Path, weak: Weak<LoginPage>) {
+    let index = match library_index(songs_dir) {
+        Ok(index) => index,
+        Err(e) => {
+            show_progress(&weak, format!("osu!.db illisible : {}", e), 0.0, false);
+            return;
+        }
+    };
+
+    let pending = cache.lock().unwrap().pending(&index);
+    show_progress(&weak, format!("Analyse : 0 / {} maps", pending.len()), 0.0, true);
+    let done = analyze_library(&pending, songs_dir, cancel, |results, done, total| {
+        if let Err(e) = cache.lock().unwrap().append(results) {
+            eprintln!("Impossible de sauvegarder le cache d'analyse: {}", e);
+        }
+        show_progress(&weak, format!("Analyse : {} / {} maps", done, total), done as f32 / total as f32, true);
+    });
+    let analyzed = cache.lock().unwrap().count();
+    show_progress(&weak, format!("{} maps parcourues, {} maps analysées", done, analyzed), 1.0, false);
+}
+
+/// Generates the pattern collections from the analysis cache and saves them in collection.db
+///
+/// # Arguments
+/// * `cache` - Analysis cache
+/// * `songs_dir` - Songs folder of osu!
+/// * `weak` - Weak reference to the main window
+pub fn save_collections(cache: &Mutex<AnalysisCache>, songs_dir: &Path, weak: Weak<LoginPage>) {
+    let result = library_index(songs_dir).and_then(|index| {
+        let game_dir = index
+            .game_dir()
+            .ok_or_else(|| eyre::eyre!("dossier osu! inconnu"))?
+            .to_path_buf();
+        // osu! would overwrite the file with its own collections when closing
+        if osu_running() {
+            return Err(eyre::eyre!("osu! est ouvert, fermez-le avant de générer les collections"));
+        }
+        let collections = generate_collections(&cache.lock().unwrap(), &default_rules());
+        let count = collections.len();
+        let backup = save_generated(&game_dir, collections)?;
+        Ok((count, backup))
+    });
+    let status = match result {
+        Ok((count, Some(backup))) => format!("{} collections générées, sauvegarde : {}", count, backup.display()),
+        Ok((count, None)) => format!("{} collections générées", count),
+        Err(e) => format!("Génération des collections impossible : {}", e),
+    };
+    let _ = weak.upgrade_in_event_loop(move |handle| {
+        handle.global::<LibraryState>().set_status(SharedString::from(status));
+    });
+}
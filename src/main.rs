@@ -1,8 +1,15 @@
 mod utils;
 mod structs;
 mod reader;
-
-use std::path::Path;
+mod history;
+mod session;
+mod overlay;
+mod downloads;
+mod library;
+mod replay;
+
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
 use std::sync::Arc;
 use futures_util::StreamExt;
 use reqwest;
@@ -15,8 +22,23 @@ use tokio::fs::File;
 use tokio::io::AsyncWriteExt;
 use std::sync::Mutex;
 use futures_util::future::Shared;
-use crate::reader::controlla;
-use crate::utils::api::Api;
+use crate::reader::{controlla, subscribe_pp_counter, subscribe_ui};
+use crate::reader::events::EventBus;
+use crate::reader::input::{set_mania_keyboard, set_mania_keys};
+use crate::history::ScoreHistory;
+use crate::history::capture::subscribe_history;
+use crate::history::view::{import_scores_db, refresh_history_ui};
+use crate::session::{refresh_session_ui, subscribe_session, Session};
+use crate::overlay::{subscribe_overlay, OverlayServer, SharedOverlayState, DEFAULT_PORT};
+use crate::utils::api::{Api, MapsetQuery, MapsetSort, MapsetStatus};
+use crate::utils::mirror::{parse_set_id, MirrorList, MirrorSettings};
+use crate::downloads::{DownloadManager, DownloadSource, DEFAULT_PARALLELISM};
+use crate::downloads::install::osz_file_name;
+use crate::downloads::view::refresh_downloads_ui;
+use crate::library::analysis::AnalysisCache;
+use crate::library::current_index;
+use crate::library::view::{analyze_in_background, install_status, refresh_library_ui, save_collections};
+use crate::replay::view::analyze_replay_file;
 use crate::structs::MapSet;
 use crate::structs::Map;
 use crate::utils::rate::{change_audio_speed, change_osu_speed};
@@ -49,12 +71,11 @@ pub async fn main() -> Result<(), Box<dyn std::error::Error>>
 
 
     let window_handle = login_page.as_weak();
-    let api_for_closure = api.clone();
 
     let pp_window: Arc<Mutex<Option<PPWindow>>> = Arc::new(Mutex::new(None));
     let pp_window_clone = pp_window.clone();
 
-    let pp_window_clone = pp_window.clone();
+    let pp_window_for_update = pp_window.clone();
 
     if let Ok(window_lock) = pp_window.lock() {
         if let Some(window) = window_lock.as_ref() {
@@ -96,6 +117,12 @@ pub async fn main() -> Result<(), Box<dyn std::error::Error>>
 
     });
 
+    login_page.global::<AppState>().on_update_pp_window(move |text| {
+        if let Some(window) = pp_window_for_update.lock().unwrap().as_ref() {
+            window.invoke_update_text(text);
+        }
+    });
+
     let window_handle = login_page.as_weak();
 
     login_page.global::<AppState>().on_toggle_pp_window(move |checked| {
@@ -121,76 +148,124 @@ pub async fn main() -> Result<(), Box<dyn std::error::Error>>
 
 
 
+    let mirror_settings = Arc::new(Mutex::new(MirrorSettings::load()));
+    let downloads_weak = login_page.as_weak();
+    let downloads = DownloadManager::new(
+        mirror_client(),
+        Arc::new(MirrorList::from_settings(mirror_client(), &mirror_settings.lock().unwrap())),
+        DEFAULT_PARALLELISM,
+        move |items| refresh_downloads_ui(items, downloads_weak.clone()),
+    );
+    login_page.global::<DownloadState>().set_parallelism(DEFAULT_PARALLELISM as i32);
+    apply_mirror_settings(&mirror_settings.lock().unwrap(), &downloads, &login_page);
+    login_page.global::<MapSetState>().on_mirror_toggled({
+        let (settings, downloads, weak) = (mirror_settings.clone(), downloads.clone(), login_page.as_weak());
+        move |index, enabled| {
+            let mut settings = settings.lock().unwrap();
+            if let Some(mirror) = settings.mirrors.get_mut(index as usize) {
+                mirror.enabled = enabled;
+            }
+            if let Some(window) = weak.upgrade() {
+                apply_mirror_settings(&settings, &downloads, &window);
+            }
+        }
+    });
+    login_page.global::<MapSetState>().on_mirror_moved({
+        let (settings, downloads, weak) = (mirror_settings.clone(), downloads.clone(), login_page.as_weak());
+        move |index, offset| {
+            let mut settings = settings.lock().unwrap();
+            settings.move_mirror(index as usize, offset);
+            if let Some(window) = weak.upgrade() {
+                apply_mirror_settings(&settings, &downloads, &window);
+            }
+        }
+    });
+    login_page.global::<MapSetState>().on_osu_credentials_changed({
+        let (settings, downloads, weak) = (mirror_settings.clone(), downloads.clone(), login_page.as_weak());
+        move |client_id, client_secret| {
+            let mut settings = settings.lock().unwrap();
+            settings.osu_client_id = client_id.trim().to_string();
+            settings.osu_client_secret = client_secret.trim().to_string();
+            if let Some(window) = weak.upgrade() {
+                apply_mirror_settings(&settings, &downloads, &window);
+            }
+        }
+    });
+
+    let download_state = login_page.global::<DownloadState>();
+    download_state.on_pause({
+        let downloads = downloads.clone();
+        move |id| downloads.pause(id as u64)
+    });
+    download_state.on_resume({
+        let downloads = downloads.clone();
+        move |id| downloads.resume(id as u64)
+    });
+    download_state.on_cancel({
+        let downloads = downloads.clone();
+        move |id| downloads.cancel(id as u64)
+    });
+    download_state.on_clear_finished({
+        let downloads = downloads.clone();
+        move || downloads.clear_finished()
+    });
+    download_state.on_parallelism_changed({
+        let downloads = downloads.clone();
+        move |parallelism| downloads.set_parallelism(parallelism.max(1) as usize)
+    });
+
     login_page
         .global::<MapSetState>()
         .on_download(move |link, index| {
-            let api = api_for_closure.clone();
-            let lien = link.to_string();
-            let weak = window_handle.clone();
-            let path = format!("{}/{}_map.osz",
-                               window_handle.clone().upgrade().unwrap().global::<MapSetState>().get_osu_path(),
-                               index
-            );
-            if let Some(window) = pp_window.lock().unwrap().as_ref() {
-                window.invoke_update_text("140".into());
+            let Some(window) = window_handle.upgrade() else {
+                return;
+            };
+            let state = window.global::<MapSetState>();
+            let set_id = parse_set_id(&link);
+            let (artist, song) = state
+                .get_maps()
+                .row_data(index as usize)
+                .map(|map| (map.author.to_string(), map.song.to_string()))
+                .unwrap_or_else(|| (String::from("Inconnu"), link.to_string()));
+            let songs_dir = PathBuf::from(state.get_osu_path().as_str());
+            let path = songs_dir.join(osz_file_name(set_id, &artist, &song));
+            // Without a Songs folder the .osz is kept next to the application
+            let extract = state.get_auto_extract() && !songs_dir.as_os_str().is_empty();
+            let install_dir = extract.then_some(songs_dir);
+            let source = match set_id {
+                Some(set_id) => DownloadSource::Set(set_id),
+                None => DownloadSource::Link(link.to_string()),
+            };
+            let title = format!("{} - {}", artist, song);
+            if let Err(e) = downloads.enqueue(source, &title, path, install_dir) {
+                eprintln!("Erreur lors du téléchargement: {}", e);
             }
-            tokio::spawn(async move {
-                let weak_clone = weak.clone();
-                let progress_callback = move |progress: f32| {
-                    let weak = weak_clone.clone();
-                    slint::invoke_from_event_loop(move || {
-                        if let Some(handle) = weak.upgrade() {
-                            let current_model = handle.global::<MapSetState>().get_maps();
-                            if let Some(model) =
-                                current_model.as_any().downcast_ref::<VecModel<MapData>>()
-                            {
-                                if let Some(mut item) = model.row_data(index as usize) {
-                                    item.download_progress = progress;
-                                    model.set_row_data(index as usize, item);
-                                }
-                            }
-                        }
-                    })
-                    .ok();
-                };
-                match api.download_files(&lien, &path, progress_callback).await {
-                    Ok(_) => {
-                        if let Some(handle) = weak.upgrade() {
-                            let current_model = handle.global::<MapSetState>().get_maps();
-                            if let Some(model) =
-                                current_model.as_any().downcast_ref::<VecModel<MapData>>()
-                            {
-                                if let Some(mut item) = model.row_data(index as usize) {
-                                    item.download_progress = 1.0;
-                                    model.set_row_data(index as usize, item);
-                                }
-                            }
-                        }
-                    }
-                    Err(e) => {
-                        eprintln!("Erreur lors du téléchargement: {}", e);
-                    }
-                }
-            });
         });
+    // Incremented on each search, pages of a previous search are dropped when they arrive
+    let search_generation = Arc::new(AtomicU64::new(0));
     let api_for_closure = api.clone();
     login_page.on_load_more({
         let weak = login_page.as_weak().clone();
+        let search_generation = search_generation.clone();
 
         move || {
             let api = api_for_closure.clone();
 
             if let Some(window) = weak.upgrade() {
                 let current_page = window.get_current_page();
+                let query = mapset_query(&window);
                 let weak_clone = weak.clone();
+                let generation = search_generation.load(Ordering::Relaxed);
+                let search_generation = search_generation.clone();
+                let is_current = move || search_generation.load(Ordering::Relaxed) == generation;
 
                 slint::spawn_local(async move {
-                    if let Err(e) = get_maps(api,current_page as usize, &weak_clone).await {
+                    if let Err(e) = get_maps(api, current_page as usize, &query, &weak_clone, &is_current).await {
                         eprintln!("Erreur lors du chargement des maps: {}", e);
                         return;
                     }
 
-                    if let Some(window) = weak_clone.upgrade() {
+                    if let Some(window) = weak_clone.upgrade().filter(|_| is_current()) {
                         window.set_current_page(current_page + 10);
                     }
                 });
@@ -200,6 +275,18 @@ pub async fn main() -> Result<(), Box<dyn std::error::Error>>
 
 
 
+    login_page.global::<MapSearchState>().on_search({
+        let weak = login_page.as_weak();
+        move || {
+            if let Some(window) = weak.upgrade() {
+                search_generation.fetch_add(1, Ordering::Relaxed);
+                window.global::<MapSetState>().set_maps(ModelRc::new(VecModel::<MapData>::default()));
+                window.set_current_page(0);
+                window.invoke_load_more();
+            }
+        }
+    });
+
     let api_for_closure = api.clone();
 
     login_page.on_login_requested({
@@ -228,17 +315,249 @@ pub async fn main() -> Result<(), Box<dyn std::error::Error>>
     });
     let weak = login_page.as_weak();
 
-    tokio::spawn(async move {
-        controlla(weak);
+    let events = EventBus::new();
+    subscribe_ui(&events, weak.clone());
+    subscribe_pp_counter(&events, weak);
+    let overlay_state = SharedOverlayState::default();
+    subscribe_overlay(&events, overlay_state.clone());
+    let overlay_server: Arc<Mutex<Option<OverlayServer>>> = Arc::new(Mutex::new(None));
+    let runtime = tokio::runtime::Handle::current();
+    let overlay_weak = login_page.as_weak();
+    login_page.global::<AppState>().on_toggle_overlay_server(move |enabled| {
+        let overlay_server = overlay_server.clone();
+        let overlay_state = overlay_state.clone();
+        let weak = overlay_weak.clone();
+        runtime.spawn(async move {
+            if let Some(server) = overlay_server.lock().unwrap().take() {
+                server.stop();
+            }
+            let status = if enabled {
+                match OverlayServer::start(overlay_state, DEFAULT_PORT).await {
+                    Ok(server) => {
+                        let status = format!("Overlays sur http://127.0.0.1:{}/", server.port());
+                        *overlay_server.lock().unwrap() = Some(server);
+                        status
+                    }
+                    Err(e) => format!("Impossible de démarrer le serveur d'overlay: {}", e),
+                }
+            } else {
+                String::new()
+            };
+            let _ = weak.upgrade_in_event_loop(move |handle| {
+                handle.global::<AppState>().set_overlay_status(SharedString::from(status));
+            });
+        });
+    });
+
+    login_page.global::<AppState>().on_mania_keys_changed(|keys| set_mania_keys(&keys));
+    login_page.global::<AppState>().on_mania_keyboard_toggled(set_mania_keyboard);
+    login_page.global::<AppState>().on_analyze_replay({
+        let weak = login_page.as_weak();
+        move || {
+            let Some(window) = weak.upgrade() else {
+                return;
+            };
+            let path = PathBuf::from(window.global::<AppState>().get_replay_path().trim());
+            let songs_dir = PathBuf::from(window.global::<MapSetState>().get_osu_path().as_str());
+            let weak = weak.clone();
+            std::thread::spawn(move || analyze_replay_file(&path, &songs_dir, weak));
+        }
+    });
+
+    let history = ScoreHistory::open_default();
+    let previous_records = match &history {
+        Ok(history) => history.load().unwrap_or_default(),
+        Err(_) => Vec::new(),
+    };
+    let session = Arc::new(Mutex::new(Session::new(&previous_records)));
+    subscribe_session(&events, session.clone(), login_page.as_weak());
+    let export_session = session.clone();
+    login_page.global::<SessionState>().on_export_summary(move || {
+        match export_session.lock().unwrap().export_summary() {
+            Ok(path) => println!("Résumé de session écrit dans {}", path.display()),
+            Err(e) => eprintln!("Impossible d'exporter la session: {}", e),
+        }
+    });
+
+    match history {
+        Ok(history) => {
+            refresh_history_ui(&history, login_page.as_weak());
+            let refresh_history = history.clone();
+            let refresh_weak = login_page.as_weak();
+            login_page.global::<HistoryState>().on_refresh(move || {
+                refresh_history_ui(&refresh_history, refresh_weak.clone());
+            });
+            let import_history = history.clone();
+            let import_weak = login_page.as_weak();
+            login_page.global::<HistoryState>().on_import_scores(move || {
+                let Some(window) = import_weak.upgrade() else {
+                    return;
+                };
+                window.global::<HistoryState>().set_import_status(SharedString::from("Import de scores.db..."));
+                let songs_dir = PathBuf::from(window.global::<MapSetState>().get_osu_path().as_str());
+                let (history, weak) = (import_history.clone(), import_weak.clone());
+                std::thread::spawn(move || import_scores_db(&history, &songs_dir, weak));
+            });
+            let recorded_history = history.clone();
+            let recorded_weak = login_page.as_weak();
+            let recorded_session = session.clone();
+            subscribe_history(&events, history, move |record| {
+                let mut session = recorded_session.lock().unwrap();
+                session.on_record(record);
+                refresh_session_ui(&session, recorded_weak.clone());
+                refresh_history_ui(&recorded_history, recorded_weak.clone());
+            });
+        }
+        Err(e) => eprintln!("Historique des scores indisponible: {}", e),
+    }
+
+    let analysis_cache = Arc::new(Mutex::new(AnalysisCache::open_default().unwrap_or_else(|e| {
+        eprintln!("Cache d'analyse indisponible: {}", e);
+        AnalysisCache::default()
+    })));
+    let analysis_cancel = Arc::new(AtomicBool::new(false));
+    let library_state = login_page.global::<LibraryState>();
+    library_state.on_filter({
+        let cache = analysis_cache.clone();
+        let weak = login_page.as_weak();
+        move || {
+            if let Some(window) = weak.upgrade() {
+                refresh_library_ui(&cache.lock().unwrap(), &window);
+            }
+        }
+    });
+    library_state.on_cancel({
+        let cancel = analysis_cancel.clone();
+        move || cancel.store(true, Ordering::Relaxed)
+    });
+    library_state.on_generate_collections({
+        let cache = analysis_cache.clone();
+        let weak = login_page.as_weak();
+        move || {
+            let Some(window) = weak.upgrade() else {
+                return;
+            };
+            window.global::<LibraryState>().set_status(SharedString::from("Génération des collections..."));
+            let songs_dir = PathBuf::from(window.global::<MapSetState>().get_osu_path().as_str());
+            let (cache, weak) = (cache.clone(), weak.clone());
+            std::thread::spawn(move || save_collections(&cache, &songs_dir, weak));
+        }
+    });
+    library_state.on_analyze({
+        let weak = login_page.as_weak();
+        move || {
+            let Some(window) = weak.upgrade() else {
+                return;
+            };
+            window.global::<LibraryState>().set_running(true);
+            let songs_dir = PathBuf::from(window.global::<MapSetState>().get_osu_path().as_str());
+            let (cache, cancel, weak) = (analysis_cache.clone(), analysis_cancel.clone(), weak.clone());
+            cancel.store(false, Ordering::Relaxed);
+            std::thread::spawn(move || analyze_in_background(&cache, &cancel, &songs_dir, weak));
+        }
+    });
+
+    std::thread::spawn(move || {
+        controlla(events);
     });
     login_page.show();
     slint::run_event_loop();
+
+    let session = session.lock().unwrap();
+    if session.attempts() > 0 {
+        if let Err(e) = session.export_summary() {
+            eprintln!("Impossible d'exporter la session: {}", e);
+        }
+    }
     Ok(())
 }
 
-async fn get_maps(api: Arc<Api>, page: usize, weak: &Weak<LoginPage>) -> Result<(), Box<dyn std::error::Error>> {
-    if let Ok(response) = api.fetch_mapsets(page as i32).await {
-        for map_set in response.mapSet{
+/// HTTP client used to download from the mirrors
+fn mirror_client() -> Client {
+    Client::builder()
+        .user_agent("Cobra/0.2.0")
+        .build()
+        .expect("Impossible de construire le client Reqwest")
+}
+
+/// Saves the mirror settings, rebuilds the mirrors used for downloads and shows them
+///
+/// # Arguments
+/// * `settings` - Mirror settings after the change
+/// * `downloads` - Download manager using the mirrors
+/// * `window` - Main window holding `MapSetState`
+fn apply_mirror_settings(settings: &MirrorSettings, downloads: &DownloadManager, window: &LoginPage) {
+    if let Err(e) = settings.save() {
+        eprintln!("Impossible de sauvegarder les miroirs: {}", e);
+    }
+    downloads.set_mirrors(Arc::new(MirrorList::from_settings(mirror_client(), settings)));
+
+    let state = window.global::<MapSetState>();
+    let rows: Vec<MirrorData> = settings
+        .mirrors
+        .iter()
+        .map(|mirror| MirrorData { name: SharedString::from(mirror.kind.label()), enabled: mirror.enabled })
+        .collect();
+    state.set_mirrors(ModelRc::new(VecModel::from(rows)));
+    state.set_osu_client_id(SharedString::from(&settings.osu_client_id));
+    state.set_osu_client_secret(SharedString::from(&settings.osu_client_secret));
+}
+
+/// Builds the search of the browse page from its search bar and filters
+///
+/// # Arguments
+/// * `window` - Main window holding `MapSearchState`
+fn mapset_query(window: &LoginPage) -> MapsetQuery {
+    let search = window.global::<MapSearchState>();
+    let number = |text: SharedString| text.trim().replace(',', ".").parse::<f64>().ok();
+    let limit = |value: i32| (value > 0).then_some(value);
+    let choice = |value: SharedString, any: &str| (value.as_str() != any).then(|| value.to_string());
+
+    MapsetQuery {
+        text: Some(search.get_text().to_string()),
+        key_count: choice(search.get_keys(), "Toutes").and_then(|keys| keys.trim_end_matches('K').parse().ok()),
+        star_min: number(search.get_star_min()),
+        star_max: number(search.get_star_max()),
+        msd_min: number(search.get_msd_min()),
+        msd_max: number(search.get_msd_max()),
+        pattern: choice(search.get_pattern(), "Tous"),
+        status: match search.get_status().as_str() {
+            "Ranked" => Some(MapsetStatus::Ranked),
+            "Approved" => Some(MapsetStatus::Approved),
+            "Qualified" => Some(MapsetStatus::Qualified),
+            "Loved" => Some(MapsetStatus::Loved),
+            "Pending" => Some(MapsetStatus::Pending),
+            "Graveyard" => Some(MapsetStatus::Graveyard),
+            _ => None,
+        },
+        length_min: limit(search.get_length_min()).map(|v| v as u32),
+        length_max: limit(search.get_length_max()).map(|v| v as u32),
+        bpm_min: limit(search.get_bpm_min()).map(f64::from),
+        bpm_max: limit(search.get_bpm_max()).map(f64::from),
+        sort: match search.get_sort().as_str() {
+            "Étoiles ↑" => MapsetSort::StarsAsc,
+            "Étoiles ↓" => MapsetSort::StarsDesc,
+            "Durée" => MapsetSort::Length,
+            "BPM" => MapsetSort::Bpm,
+            "Titre" => MapsetSort::Title,
+            _ => MapsetSort::Recent,
+        },
+    }
+}
+
+/// Fetches a page of the browse search and appends its mapsets to the list
+///
+/// # Arguments
+/// * `is_current` - False once another search started, the mapsets are then dropped
+async fn get_maps(
+    api: Arc<Api>,
+    page: usize,
+    query: &MapsetQuery,
+    weak: &Weak<LoginPage>,
+    is_current: &impl Fn() -> bool,
+) -> Result<(), Box<dyn std::error::Error>> {
+    if let Ok(response) = api.fetch_mapsets(page as i32, query).await {
+        for map_set in response.mapSet.into_iter().filter(|map_set| query.keeps(map_set)) {
             let difficulties = map_set
                 .maps
                 .iter()
@@ -261,8 +580,13 @@ async fn get_maps(api: Arc<Api>, page: usize, weak: &Weak<LoginPage>) -> Result<
                 }
             };
 
+            // The cover download can outlast a new search
+            if !is_current() {
+                return Ok(());
+            }
+            let md5s = map_set.maps.iter().map(|m| m.md5.as_str()).collect::<Vec<_>>().join(",");
             let weak_clone = weak.clone();
-            update_ui(weak_clone, map_set, difficulties, image_data)?;
+            update_ui(weak_clone, map_set, difficulties, md5s, image_data)?;
         }
     }
     Ok(())
@@ -285,6 +609,7 @@ fn update_ui(
     weak: Weak<LoginPage>,
     map_set: MapSet,
     difficulties: String,
+    md5s: String,
     image_data: Option<(u32, u32, Vec<u8>)>
 ) -> Result<(), Box<dyn std::error::Error>> {
     slint::spawn_local(async move {
@@ -302,7 +627,10 @@ fn update_ui(
                 },
                 link: SharedString::from(&map_set.link),
                 difficulties: SharedString::from(difficulties),
-                md5 : SharedString::from(String::new()),
+                install_status: current_index()
+                    .map(|index| install_status(&index, &map_set.link, &md5s))
+                    .unwrap_or_default(),
+                md5: SharedString::from(md5s),
                 download_progress: 0.0,
             };
 
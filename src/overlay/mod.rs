@@ -0,0 +1,151 @@
+use std::path::{Component, Path, PathBuf};
+use std::time::Duration;
+use axum::extract::ws::{Message, WebSocket, WebSocketUpgrade};
+use axum::extract::{Path as UrlPath, State};
+use axum::http::{header, StatusCode};
+use axum::response::{IntoResponse, Response};
+use axum::routing::get;
+use axum::{Json, Router};
+use tokio::sync::oneshot;
+use tower_http::services::ServeDir;
+use crate::utils::data_dir::data_dir;
+
+pub mod schema;
+pub mod state;
+#[cfg(test)]
+mod tests;
+
+pub use state::{subscribe_overlay, SharedOverlayState};
+
+/// Port used by gosumemory, overlays expect it by default
+pub const DEFAULT_PORT: u16 = 24050;
+/// Delay between two messages sent on `/ws`
+const WS_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Local HTTP and WebSocket server for stream overlays, stopped when `stop` is called
+pub struct OverlayServer {
+    port: u16,
+    shutdown: Option<oneshot::Sender<()>>,
+}
+
+/// Folder served at the root of the server, holding one folder per overlay
+pub fn overlays_dir() -> eyre::Result<PathBuf> {
+    let dir = data_dir()?.join("overlays");
+    std::fs::create_dir_all(&dir)?;
+    Ok(dir)
+}
+
+impl OverlayServer {
+    /// Starts the server on localhost
+    ///
+    /// Routes:
+    /// * `/json` - Current state in the gosumemory format
+    /// * `/ws` - Same state pushed every 100 ms
+    /// * `/Songs/...` - Files of the songs folder (backgrounds, audio)
+    /// * anything else - Files of the overlays folder
+    ///
+    /// # Arguments
+    /// * `state` - State kept up to date by `subscribe_overlay`
+    /// * `port` - Port to listen on, `DEFAULT_PORT` for gosumemory compatibility
+    pub async fn start(state: SharedOverlayState, port: u16) -> eyre::Result<Self> {
+        let app = Router::new()
+            .route("/json", get(json))
+            .route("/ws", get(ws))
+            .route("/Songs/*path", get(songs_file))
+            .fallback_service(ServeDir::new(overlays_dir()?))
+            .with_state(state);
+
+        let listener = tokio::net::TcpListener::bind(("127.0.0.1", port)).await?;
+        let (shutdown, stopped) = oneshot::channel::<()>();
+        tokio::spawn(async move {
+            let server = axum::serve(listener, app).with_graceful_shutdown(async {
+                let _ = stopped.await;
+            });
+            if let Err(e) = server.await {
+                eprintln!("Erreur du serveur d'overlay: {}", e);
+            }
+        });
+        Ok(Self { port, shutdown: Some(shutdown) })
+    }
+
+    pub fn port(&self) -> u16 {
+        self.port
+    }
+
+    pub fn stop(mut self) {
+        if let Some(shutdown) = self.shutdown.take() {
+            let _ = shutdown.send(());
+        }
+    }
+}
+
+async fn json(State(state): State<SharedOverlayState>) -> impl IntoResponse {
+    let state = state.read().clone();
+    Json(state)
+}
+
+async fn ws(upgrade: WebSocketUpgrade, State(state): State<SharedOverlayState>) -> Response {
+    upgrade.on_upgrade(move |socket| stream_state(socket, state))
+}
+
+async fn stream_state(mut socket: WebSocket, state: SharedOverlayState) {
+    let mut interval = tokio::time::interval(WS_INTERVAL);
+    loop {
+        interval.tick().await;
+        let message = match serde_json::to_string(&*state.read()) {
+            Ok(message) => message,
+            Err(e) => {
+                eprintln!("Impossible de sérialiser l'état de l'overlay: {}", e);
+                break;
+            }
+        };
+        if socket.send(Message::Text(message)).await.is_err() {
+            break;
+        }
+    }
+}
+
+/// Resolves a path of the `/Songs/` route inside the songs folder
+///
+/// Only plain file and folder names are accepted, so `..`, absolute paths and
+/// drive letters can't point outside of the folder.
+fn songs_path(songs: &str, path: &str) -> Option<PathBuf> {
+    if songs.is_empty() {
+        return None;
+    }
+    let mut resolved = PathBuf::from(songs);
+    for part in path.split(['/', '\\']) {
+        let mut components = Path::new(part).components();
+        match (components.next(), components.next()) {
+            (Some(Component::Normal(name)), None) if !part.contains(':') => resolved.push(name),
+            _ => return None,
+        }
+    }
+    Some(resolved)
+}
+
+async fn songs_file(UrlPath(path): UrlPath<String>, State(state): State<SharedOverlayState>) -> Response {
+    let songs = state.read().settings.folders.songs.clone();
+    let Some(file) = songs_path(&songs, &path) else {
+        return StatusCode::NOT_FOUND.into_response();
+    };
+    match tokio::fs::read(file).await {
+        Ok(bytes) => ([(header::CONTENT_TYPE, content_type(&path))], bytes).into_response(),
+        Err(_) => StatusCode::NOT_FOUND.into_response(),
+    }
+}
+
+fn content_type(path: &str) -> &'static str {
+    let extension = Path::new(path)
+        .extension()
+        .map(|e| e.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+    match extension.as_str() {
+        "jpg" | "jpeg" => "image/jpeg",
+        "png" => "image/png",
+        "mp3" => "audio/mpeg",
+        "ogg" => "audio/ogg",
+        "wav" => "audio/wav",
+        _ => "application/octet-stream",
+    }
+}
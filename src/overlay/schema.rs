@@ -0,0 +1,232 @@
+use serde::Serialize;
+
+/// Reader state in the gosumemory `/json` and `/ws` format
+///
+/// Only the fields used by common overlays are filled, the others keep
+/// their default value so overlays written for gosumemory still parse them.
+#[derive(Debug, Default, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GosuState {
+    pub settings: Settings,
+    pub menu: Menu,
+    pub gameplay: Gameplay,
+    pub results_screen: ResultsScreen,
+}
+
+#[derive(Debug, Default, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Settings {
+    pub show_interface: bool,
+    pub folders: Folders,
+}
+
+#[derive(Debug, Default, Clone, Serialize)]
+pub struct Folders {
+    pub game: String,
+    pub skin: String,
+    pub songs: String,
+}
+
+#[derive(Debug, Default, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Menu {
+    /// osu! status, same values as `GameState`
+    pub state: u32,
+    pub game_mode: u8,
+    pub bm: Beatmap,
+    pub mods: Mods,
+    pub pp: MenuPp,
+}
+
+#[derive(Debug, Default, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Beatmap {
+    pub time: BeatmapTime,
+    pub md5: String,
+    pub metadata: Metadata,
+    pub stats: Stats,
+    pub path: BeatmapPath,
+}
+
+#[derive(Debug, Default, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BeatmapTime {
+    pub first_obj: i32,
+    pub current: i32,
+    pub full: i32,
+    pub mp3: i32,
+}
+
+#[derive(Debug, Default, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Metadata {
+    pub artist: String,
+    pub title: String,
+    pub mapper: String,
+    pub difficulty: String,
+}
+
+#[derive(Debug, Default, Clone, Serialize)]
+pub struct Stats {
+    #[serde(rename = "AR")]
+    pub ar: f32,
+    #[serde(rename = "CS")]
+    pub cs: f32,
+    #[serde(rename = "OD")]
+    pub od: f32,
+    #[serde(rename = "HP")]
+    pub hp: f32,
+    /// Star rating with the selected mods
+    #[serde(rename = "SR")]
+    pub sr: f64,
+    #[serde(rename = "BPM")]
+    pub bpm: Bpm,
+    #[serde(rename = "fullSR")]
+    pub full_sr: f64,
+    #[serde(rename = "maxCombo")]
+    pub max_combo: u32,
+}
+
+#[derive(Debug, Default, Clone, Serialize)]
+pub struct Bpm {
+    pub min: f64,
+    pub max: f64,
+}
+
+/// Paths of the beatmap, relative to the songs folder
+#[derive(Debug, Default, Clone, Serialize)]
+pub struct BeatmapPath {
+    /// Background, as `folder/bg`
+    pub full: String,
+    pub folder: String,
+    pub file: String,
+    pub bg: String,
+    pub audio: String,
+}
+
+#[derive(Debug, Default, Clone, Serialize)]
+pub struct Mods {
+    pub num: u32,
+    #[serde(rename = "str")]
+    pub text: String,
+}
+
+/// pp of the selected beatmap for several accuracies
+#[derive(Debug, Default, Clone, Serialize)]
+pub struct MenuPp {
+    #[serde(rename = "100")]
+    pub pp_100: i32,
+    #[serde(rename = "99")]
+    pub pp_99: i32,
+    #[serde(rename = "98")]
+    pub pp_98: i32,
+    #[serde(rename = "97")]
+    pub pp_97: i32,
+    #[serde(rename = "96")]
+    pub pp_96: i32,
+    #[serde(rename = "95")]
+    pub pp_95: i32,
+    pub strains: Vec<f64>,
+}
+
+#[derive(Debug, Default, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Gameplay {
+    pub game_mode: u8,
+    pub name: String,
+    pub score: i32,
+    /// Accuracy in percent
+    pub accuracy: f64,
+    pub combo: Combo,
+    pub hp: Hp,
+    pub hits: Hits,
+    pub pp: GameplayPp,
+    pub key_overlay: KeyOverlay,
+}
+
+/// Keys of the play, `k1` to `m2` follow gosumemory, `columns` and `kps` are
+/// extra fields filled in mania
+#[derive(Debug, Default, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct KeyOverlay {
+    pub k1: KeyButton,
+    pub k2: KeyButton,
+    pub m1: KeyButton,
+    pub m2: KeyButton,
+    pub columns: Vec<KeyButton>,
+    pub kps: f64,
+}
+
+#[derive(Debug, Default, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct KeyButton {
+    #[serde(skip_serializing_if = "String::is_empty")]
+    pub name: String,
+    pub is_pressed: bool,
+    pub count: u32,
+}
+
+#[derive(Debug, Default, Clone, Serialize)]
+pub struct Combo {
+    pub current: i16,
+    pub max: i16,
+}
+
+#[derive(Debug, Default, Clone, Serialize)]
+pub struct Hp {
+    pub normal: f64,
+    pub smooth: f64,
+}
+
+#[derive(Debug, Default, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Hits {
+    #[serde(rename = "300")]
+    pub n300: i16,
+    pub geki: i16,
+    #[serde(rename = "100")]
+    pub n100: i16,
+    pub katu: i16,
+    #[serde(rename = "50")]
+    pub n50: i16,
+    #[serde(rename = "0")]
+    pub miss: i16,
+    pub slider_breaks: i16,
+    pub grade: Grade,
+    pub unstable_rate: f64,
+    pub hit_error_array: Vec<i32>,
+}
+
+#[derive(Debug, Default, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Grade {
+    pub current: String,
+    pub max_this_play: String,
+}
+
+#[derive(Debug, Default, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GameplayPp {
+    pub current: i32,
+    pub fc: i32,
+    pub max_this_play: i32,
+}
+
+#[derive(Debug, Default, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ResultsScreen {
+    pub name: String,
+    pub score: i32,
+    pub max_combo: i16,
+    pub mods: Mods,
+    #[serde(rename = "300")]
+    pub n300: i16,
+    pub geki: i16,
+    #[serde(rename = "100")]
+    pub n100: i16,
+    pub katu: i16,
+    #[serde(rename = "50")]
+    pub n50: i16,
+    #[serde(rename = "0")]
+    pub miss: i16,
+}
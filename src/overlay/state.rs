@@ -0,0 +1,196 @@
+use std::path::Path;
+use std::sync::Arc;
+use std::thread;
+use parking_lot::RwLock;
+use crate::overlay::schema::{
+    BeatmapPath, BeatmapTime, Bpm, Gameplay, GosuState, Grade, KeyButton, KeyOverlay, MenuPp, Metadata, Mods,
+    ResultsScreen, Stats,
+};
+use crate::reader::events::{listen, EventBus, GameEvent};
+use crate::reader::structs::{BeatmapInfo, GameplayValues, KeyStats, ResultScreenValues};
+use crate::utils::modes::MANIA_MODE;
+use crate::utils::mods::{clock_rate, mods_to_string};
+use crate::utils::scoring::grade;
+
+/// Reader state shared between the event subscriber and the server
+pub type SharedOverlayState = Arc<RwLock<GosuState>>;
+
+/// Weight of the new value in the smoothed HP
+const HP_SMOOTHING: f64 = 0.3;
+
+fn file_name(path: &Path) -> String {
+    path.file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_default()
+}
+
+/// Computes the stats, pp and length of a beatmap with the given mods
+fn analyse_map(path: &str, mods: u32) -> eyre::Result<(Stats, MenuPp, BeatmapTime)> {
+    let map = rosu_pp::Beatmap::from_path(path)?;
+    let attributes = map.attributes().mods(mods).build();
+    let difficulty = rosu_pp::Difficulty::new().mods(mods).calculate(&map);
+    let rate = clock_rate(mods);
+
+    let bpms: Vec<f64> = map
+        .timing_points
+        .iter()
+        .filter(|point| point.beat_len > 0.0)
+        .map(|point| 60_000.0 / point.beat_len * rate)
+        .collect();
+    let stats = Stats {
+        ar: attributes.ar as f32,
+        cs: attributes.cs as f32,
+        od: attributes.od as f32,
+        hp: attributes.hp as f32,
+        sr: difficulty.stars(),
+        bpm: Bpm {
+            min: bpms.iter().cloned().reduce(f64::min).unwrap_or(0.0),
+            max: bpms.iter().cloned().reduce(f64::max).unwrap_or(0.0),
+        },
+        full_sr: difficulty.stars(),
+        max_combo: difficulty.max_combo(),
+    };
+
+    let pp_at = |accuracy: f64| {
+        rosu_pp::Performance::new(difficulty.clone())
+            .mods(mods)
+            .accuracy(accuracy)
+            .calculate()
+            .pp()
+            .round() as i32
+    };
+    let pp = MenuPp {
+        pp_100: pp_at(100.0),
+        pp_99: pp_at(99.0),
+        pp_98: pp_at(98.0),
+        pp_97: pp_at(97.0),
+        pp_96: pp_at(96.0),
+        pp_95: pp_at(95.0),
+        strains: Vec::new(),
+    };
+
+    let time = BeatmapTime {
+        first_obj: map.hit_objects.first().map_or(0.0, |h| h.start_time / rate) as i32,
+        full: map.hit_objects.last().map_or(0.0, |h| h.start_time / rate) as i32,
+        ..Default::default()
+    };
+    Ok((stats, pp, time))
+}
+
+fn update_map(state: &SharedOverlayState, map: &BeatmapInfo, mods: u32) {
+    let (stats, pp, time) = analyse_map(&map.path, mods).unwrap_or_else(|e| {
+        eprintln!("Impossible d'analyser la map pour l'overlay: {}", e);
+        Default::default()
+    });
+    let osu_file = Path::new(&map.path);
+    let folder_path = osu_file.parent().unwrap_or(Path::new(""));
+    let folder = file_name(folder_path);
+    let bg = file_name(Path::new(&map.cover_path));
+    let songs = folder_path.parent().map(|p| p.display().to_string()).unwrap_or_default();
+
+    let mut state = state.write();
+    state.settings.folders.songs = songs;
+    state.menu.mods = Mods { num: mods, text: mods_to_string(mods) };
+    state.menu.pp = pp;
+    state.menu.bm.md5 = map.md5.clone();
+    state.menu.bm.metadata = Metadata {
+        artist: map.author.clone(),
+        title: map.song.clone(),
+        mapper: map.creator.clone(),
+        difficulty: map.difficulty.clone(),
+    };
+    state.menu.bm.stats = stats;
+    state.menu.bm.time = time;
+    state.menu.bm.path = BeatmapPath {
+        full: format!("{}/{}", folder, bg),
+        file: file_name(osu_file),
+        audio: file_name(Path::new(&map.audio_path)),
+        folder,
+        bg,
+    };
+}
+
+fn key_overlay(stats: &KeyStats, mode: u8) -> KeyOverlay {
+    let buttons: Vec<KeyButton> = stats
+        .keys
+        .iter()
+        .map(|key| KeyButton { name: key.name.clone(), is_pressed: key.pressed, count: key.count })
+        .collect();
+    if mode == MANIA_MODE {
+        return KeyOverlay { columns: buttons, kps: stats.kps, ..Default::default() };
+    }
+    let button = |i: usize| buttons.get(i).cloned().unwrap_or_default();
+    KeyOverlay { k1: button(0), k2: button(1), m1: button(2), m2: button(3), columns: Vec::new(), kps: stats.kps }
+}
+
+fn update_gameplay(state: &SharedOverlayState, values: GameplayValues) {
+    let grade = grade(values.mode, &values.hit).unwrap_or("D").to_string();
+
+    let mut state = state.write();
+    state.menu.game_mode = values.mode;
+    let gameplay = &mut state.gameplay;
+    gameplay.game_mode = values.mode;
+    gameplay.name = values.username;
+    gameplay.score = values.score;
+    gameplay.accuracy = values.accuracy * 100.0;
+    gameplay.combo.current = values.combo;
+    gameplay.combo.max = values.max_combo;
+    gameplay.hp.smooth += (values.hp - gameplay.hp.smooth) * HP_SMOOTHING;
+    gameplay.hp.normal = values.hp;
+    gameplay.hits.n300 = values.hit._300;
+    gameplay.hits.geki = values.hit._geki;
+    gameplay.hits.n100 = values.hit._100;
+    gameplay.hits.katu = values.hit._katu;
+    gameplay.hits.n50 = values.hit._50;
+    gameplay.hits.miss = values.hit._miss;
+    gameplay.hits.grade = Grade { current: grade.clone(), max_this_play: grade };
+    gameplay.hits.unstable_rate = values.unstable_rate;
+    gameplay.hits.hit_error_array.extend(values.new_hit_errors);
+    gameplay.key_overlay = key_overlay(&values.keys, values.mode);
+    if let Some((current, fc)) = values.live_pp {
+        gameplay.pp.current = current.round() as i32;
+        gameplay.pp.fc = fc.round() as i32;
+        gameplay.pp.max_this_play = gameplay.pp.max_this_play.max(gameplay.pp.current);
+    }
+}
+
+fn results_screen(result: &ResultScreenValues) -> ResultsScreen {
+    ResultsScreen {
+        name: result.username.clone(),
+        score: result.score,
+        max_combo: result.max_combo,
+        mods: Mods { num: result.mods, text: mods_to_string(result.mods) },
+        n300: result.hit._300,
+        geki: result.hit._geki,
+        n100: result.hit._100,
+        katu: result.hit._katu,
+        n50: result.hit._50,
+        miss: result.hit._miss,
+    }
+}
+
+/// Keeps the overlay state up to date from the reader events
+///
+/// # Arguments
+/// * `events` - Event bus of the reader
+/// * `state` - State served to the overlays
+pub fn subscribe_overlay(events: &EventBus, state: SharedOverlayState) {
+    let receiver = events.subscribe();
+    thread::spawn(move || {
+        listen(receiver, |event| match event {
+            GameEvent::StateChanged { to, .. } => state.write().menu.state = to as u32,
+            GameEvent::MapChanged { map, mods } | GameEvent::ModsChanged { map, mods } => {
+                update_map(&state, &map, mods);
+            }
+            GameEvent::PlayStarted { map, mods } => {
+                update_map(&state, &map, mods);
+                state.write().gameplay = Gameplay::default();
+            }
+            GameEvent::GameplayUpdated(values) => update_gameplay(&state, values),
+            GameEvent::ResultScreenShown { result, .. } => {
+                state.write().results_screen = results_screen(&result);
+            }
+            _ => {}
+        });
+    });
+}
@@ -0,0 +1,98 @@
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use axum::extract::{Path as UrlPath, State};
+use axum::http::{header, StatusCode};
+use parking_lot::RwLock;
+use crate::overlay::schema::{GosuState, KeyButton};
+use crate::overlay::{songs_file, SharedOverlayState};
+
+/// Songs folder holding one background, next to a file that must stay private
+fn songs_dir() -> PathBuf {
+    let root = std::env::temp_dir().join(format!("cobra_overlay_{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&root);
+    std::fs::create_dir_all(root.join("Songs").join("123 Artist - Title")).unwrap();
+    std::fs::write(root.join("Songs").join("123 Artist - Title").join("bg.jpg"), b"jpg").unwrap();
+    std::fs::write(root.join("secret.txt"), b"secret").unwrap();
+    root
+}
+
+fn overlay_state(songs: &Path) -> SharedOverlayState {
+    let mut state = GosuState::default();
+    state.settings.folders.songs = songs.display().to_string();
+    Arc::new(RwLock::new(state))
+}
+
+#[tokio::test]
+async fn serves_only_the_songs_folder() {
+    let root = songs_dir();
+    let state = overlay_state(&root.join("Songs"));
+    let status = |path: String| {
+        let state = state.clone();
+        async move { songs_file(UrlPath(path), State(state)).await.status() }
+    };
+
+    let response = songs_file(UrlPath("123 Artist - Title/bg.jpg".to_string()), State(state.clone())).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.headers()[header::CONTENT_TYPE], "image/jpeg");
+
+    let secret = root.join("secret.txt").display().to_string();
+    assert_eq!(status(secret).await, StatusCode::NOT_FOUND);
+    assert_eq!(status("../secret.txt".to_string()).await, StatusCode::NOT_FOUND);
+    assert_eq!(status("123 Artist - Title\\..\\..\\secret.txt".to_string()).await, StatusCode::NOT_FOUND);
+    assert_eq!(status("C:/Windows/win.ini".to_string()).await, StatusCode::NOT_FOUND);
+    assert_eq!(status("123 Artist - Title/C:\\secret.txt".to_string()).await, StatusCode::NOT_FOUND);
+    assert_eq!(status("123 Artist - Title//bg.jpg".to_string()).await, StatusCode::NOT_FOUND);
+
+    // Nothing is served before the songs folder is known
+    let empty = Arc::new(RwLock::new(GosuState::default()));
+    let response = songs_file(UrlPath("123 Artist - Title/bg.jpg".to_string()), State(empty)).await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    std::fs::remove_dir_all(&root).unwrap();
+}
+
+#[test]
+fn serializes_gosumemory_fields() {
+    let mut state = GosuState::default();
+    state.settings.folders.songs = "C:/osu!/Songs".to_string();
+    state.menu.state = 2;
+    state.menu.bm.stats.ar = 9.0;
+    state.menu.bm.stats.full_sr = 5.5;
+    state.menu.bm.time.first_obj = 1200;
+    state.menu.bm.path.bg = "bg.jpg".to_string();
+    state.menu.mods.text = "HDDT".to_string();
+    state.menu.pp.pp_100 = 300;
+    state.gameplay.hits.n300 = 500;
+    state.gameplay.hits.miss = 3;
+    state.gameplay.hits.hit_error_array = vec![-5, 8];
+    state.gameplay.hits.grade.max_this_play = "S".to_string();
+    state.gameplay.pp.max_this_play = 250;
+    state.gameplay.key_overlay.k1 = KeyButton { name: String::new(), is_pressed: true, count: 42 };
+    state.results_screen.max_combo = 700;
+
+    let json = serde_json::to_value(&state).unwrap();
+    let field = |pointer: &str| json.pointer(pointer).cloned().unwrap_or_else(|| panic!("champ absent: {}", pointer));
+    assert_eq!(field("/settings/folders/songs"), "C:/osu!/Songs");
+    assert_eq!(field("/settings/showInterface"), false);
+    assert_eq!(field("/menu/state"), 2);
+    assert_eq!(field("/menu/gameMode"), 0);
+    assert_eq!(field("/menu/bm/stats/AR"), 9.0);
+    assert_eq!(field("/menu/bm/stats/fullSR"), 5.5);
+    assert_eq!(field("/menu/bm/stats/BPM/min"), 0.0);
+    assert_eq!(field("/menu/bm/stats/maxCombo"), 0);
+    assert_eq!(field("/menu/bm/time/firstObj"), 1200);
+    assert_eq!(field("/menu/bm/path/bg"), "bg.jpg");
+    assert_eq!(field("/menu/mods/str"), "HDDT");
+    assert_eq!(field("/menu/pp/100"), 300);
+    assert_eq!(field("/gameplay/hits/300"), 500);
+    assert_eq!(field("/gameplay/hits/0"), 3);
+    assert_eq!(field("/gameplay/hits/sliderBreaks"), 0);
+    assert_eq!(field("/gameplay/hits/unstableRate"), 0.0);
+    assert_eq!(field("/gameplay/hits/hitErrorArray"), serde_json::json!([-5, 8]));
+    assert_eq!(field("/gameplay/hits/grade/maxThisPlay"), "S");
+    assert_eq!(field("/gameplay/pp/maxThisPlay"), 250);
+    assert_eq!(field("/gameplay/keyOverlay/k1/isPressed"), true);
+    assert_eq!(field("/gameplay/keyOverlay/k1/count"), 42);
+    assert_eq!(field("/resultsScreen/maxCombo"), 700);
+    // Key names are only sent for the mania columns
+    assert!(json.pointer("/gameplay/keyOverlay/k1/name").is_none());
+}
@@ -0,0 +1,52 @@
+use std::fmt;
+use eyre::Report;
+use rosu_mem::error::ProcessError;
+
+/// Errors returned by the memory readers
+#[derive(Debug)]
+pub enum ReaderError {
+    /// A pointer of a chain was null, usually while osu! switches between screens
+    NullPointer { addr: i32 },
+    /// The osu! process is not running anymore
+    ProcessGone,
+    /// The value read does not match what the current game state should expose
+    StaleState(String),
+}
+
+impl fmt::Display for ReaderError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ReaderError::NullPointer { addr } => write!(f, "Null pointer read at 0x{:X}", addr),
+            ReaderError::ProcessGone => write!(f, "osu! process is gone"),
+            ReaderError::StaleState(reason) => write!(f, "Stale state: {}", reason),
+        }
+    }
+}
+
+impl std::error::Error for ReaderError {}
+
+/// What the reading loop should do after an error
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Recovery {
+    /// Find the process again and re-resolve the static addresses
+    Reinitialize,
+    /// Keep the addresses and retry on the next tick
+    Retry,
+}
+
+/// Decides how the reading loop recovers from an error returned by a reader
+pub fn recovery_for(e: &Report) -> Recovery {
+    if let Some(reader_error) = e.downcast_ref::<ReaderError>() {
+        return match reader_error {
+            ReaderError::ProcessGone => Recovery::Reinitialize,
+            ReaderError::NullPointer { .. } | ReaderError::StaleState(_) => Recovery::Retry,
+        };
+    }
+
+    match e.downcast_ref::<ProcessError>() {
+        Some(&ProcessError::ProcessNotFound) => Recovery::Reinitialize,
+        #[cfg(target_os = "windows")]
+        Some(&ProcessError::OsError { .. }) => Recovery::Reinitialize,
+        Some(_) | None => Recovery::Retry,
+    }
+}
@@ -0,0 +1,83 @@
+use tokio::sync::broadcast;
+use tokio::sync::broadcast::error::RecvError;
+use crate::reader::structs::{BeatmapInfo, GameState, GameplayValues, HitErrors, ResultScreenValues};
+
+/// Number of events kept for slow subscribers before they start lagging
+const EVENT_CAPACITY: usize = 256;
+
+/// Events emitted by the game state machine
+#[derive(Debug, Clone)]
+pub enum GameEvent {
+    /// osu! switched to another screen
+    StateChanged { from: GameState, to: GameState },
+    /// Another beatmap was selected outside of gameplay
+    MapChanged { map: BeatmapInfo, mods: u32 },
+    /// Mods were changed in song select without changing the beatmap
+    ModsChanged { map: BeatmapInfo, mods: u32 },
+    /// A play started, also emitted again after a retry
+    PlayStarted { map: BeatmapInfo, mods: u32 },
+    /// Values of the current play, emitted on every gameplay tick
+    GameplayUpdated(GameplayValues),
+    /// The player restarted the map
+    Retry,
+    /// The play was paused at the given in-game time
+    Paused { time: i32 },
+    /// The play was resumed at the given in-game time
+    Resumed { time: i32 },
+    /// The HP reached 0 without No Fail
+    Failed(GameplayValues),
+    /// The player left the play before the end
+    PlayQuit { hit_errors: HitErrors },
+    /// The play reached the result screen
+    PlayFinished { values: GameplayValues, hit_errors: HitErrors },
+    /// The result screen of the play is displayed
+    ResultScreenShown { map: BeatmapInfo, result: ResultScreenValues },
+    /// Folders of osu! found once after the reader attaches, `game` being the
+    /// folder of the executable when it is known
+    FoldersFound { game: Option<String>, songs: String },
+}
+
+/// Channel on which the reader publishes its events, every subscriber gets each event
+#[derive(Clone)]
+pub struct EventBus {
+    sender: broadcast::Sender<GameEvent>,
+}
+
+impl Default for EventBus {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl EventBus {
+    pub fn new() -> Self {
+        let (sender, _) = broadcast::channel(EVENT_CAPACITY);
+        Self { sender }
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<GameEvent> {
+        self.sender.subscribe()
+    }
+
+    /// Publishes an event, having no subscriber is not an error
+    pub fn emit(&self, event: GameEvent) {
+        let _ = self.sender.send(event);
+    }
+}
+
+/// Calls `handler` for every event received, blocking the current thread until the bus is dropped
+///
+/// # Arguments
+/// * `receiver` - Receiver obtained with `EventBus::subscribe`
+/// * `handler` - Called with each event, in order
+pub fn listen(mut receiver: broadcast::Receiver<GameEvent>, mut handler: impl FnMut(GameEvent)) {
+    loop {
+        match receiver.blocking_recv() {
+            Ok(event) => handler(event),
+            Err(RecvError::Lagged(skipped)) => {
+                eprintln!("{} évènements ignorés, l'abonné est trop lent", skipped);
+            }
+            Err(RecvError::Closed) => break,
+        }
+    }
+}
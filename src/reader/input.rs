@@ -0,0 +1,390 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
+use std::thread;
+use std::time::{Duration, Instant};
+use keyboard::Keyboard;
+use crate::utils::modes::MANIA_MODE;
+use crate::reader::reader_keys::KeyTracker;
+use crate::reader::structs::KeyStats;
+
+/// Delay between two reads of the keyboard, short enough to catch taps
+const POLL_INTERVAL: Duration = Duration::from_millis(2);
+
+/// Mania keys chosen in the settings, empty to use the osu! defaults
+static MANIA_KEYS: RwLock<Vec<String>> = RwLock::new(Vec::new());
+/// Whether the keyboard is read during mania plays, off until enabled in the settings
+static MANIA_KEYBOARD: AtomicBool = AtomicBool::new(false);
+
+/// Sets the mania keys from the settings
+///
+/// # Arguments
+/// * `keys` - Keys separated by spaces, left to right (ex: "D F J K")
+pub fn set_mania_keys(keys: &str) {
+    let keys = keys.split_whitespace().map(|key| key.to_uppercase()).collect();
+    if let Ok(mut mania_keys) = MANIA_KEYS.write() {
+        *mania_keys = keys;
+    }
+}
+
+/// Enables reading the keyboard during mania plays, from the next play on
+pub fn set_mania_keyboard(enabled: bool) {
+    MANIA_KEYBOARD.store(enabled, Ordering::Relaxed);
+}
+
+/// Default mania bindings of osu!, left to right
+fn default_keys(key_count: usize) -> &'static [&'static str] {
+    match key_count {
+        1 => &["SPACE"],
+        2 => &["F", "J"],
+        3 => &["F", "SPACE", "J"],
+        4 => &["D", "F", "J", "K"],
+        5 => &["D", "F", "SPACE", "J", "K"],
+        6 => &["S", "D", "F", "J", "K", "L"],
+        7 => &["S", "D", "F", "SPACE", "J", "K", "L"],
+        8 => &["A", "S", "D", "F", "J", "K", "L", ";"],
+        9 => &["A", "S", "D", "F", "SPACE", "J", "K", "L", ";"],
+        _ => &[],
+    }
+}
+
+/// Keys to poll for a map, the settings win when they match the key count
+fn bindings(key_count: usize) -> Vec<String> {
+    let keys = MANIA_KEYS.read().map(|keys| keys.clone()).unwrap_or_default();
+    if keys.len() == key_count {
+        keys
+    } else {
+        default_keys(key_count).iter().map(|key| key.to_string()).collect()
+    }
+}
+
+/// Keys other than letters and digits: name in the settings, Windows virtual key and X11 keysym
+#[cfg(any(windows, target_os = "linux"))]
+const SPECIAL_KEYS: [(&str, u16, &str); 10] = [
+    ("SPACE", 0x20, "space"),
+    (";", 0xBA, "semicolon"),
+    (",", 0xBC, "comma"),
+    (".", 0xBE, "period"),
+    ("/", 0xBF, "slash"),
+    ("[", 0xDB, "bracketleft"),
+    ("]", 0xDD, "bracketright"),
+    ("'", 0xDE, "apostrophe"),
+    ("LSHIFT", 0xA0, "Shift_L"),
+    ("RSHIFT", 0xA1, "Shift_R"),
+];
+
+/// Letter or digit of a key name
+#[cfg(any(windows, target_os = "linux"))]
+fn alphanumeric_key(name: &str) -> Option<char> {
+    let mut chars = name.chars();
+    match (chars.next(), chars.next()) {
+        (Some(c), None) if c.is_ascii_alphanumeric() => Some(c),
+        _ => None,
+    }
+}
+
+/// Keyboard read with the Win32 API, osu! being focused when it owns the foreground window
+#[cfg(windows)]
+mod keyboard {
+    use windows_sys::Win32::UI::Input::KeyboardAndMouse::GetAsyncKeyState;
+    use windows_sys::Win32::UI::WindowsAndMessaging::{GetForegroundWindow, GetWindowThreadProcessId};
+    use super::{alphanumeric_key, SPECIAL_KEYS};
+
+    /// Windows virtual key code of a key name
+    fn virtual_key(name: &str) -> Option<u16> {
+        match SPECIAL_KEYS.iter().find(|(key, ..)| *key == name) {
+            Some(&(_, code, _)) => Some(code),
+            None => alphanumeric_key(name).map(|c| c.to_ascii_uppercase() as u16),
+        }
+    }
+
+    pub struct Keyboard {
+        pid: u32,
+        keys: Vec<u16>,
+    }
+
+    impl Keyboard {
+        pub fn open(pid: u32, names: &[String]) -> Option<Self> {
+            let keys = names.iter().map(|name| virtual_key(name)).collect::<Option<_>>()?;
+            Some(Self { pid, keys })
+        }
+
+        fn osu_focused(&self) -> bool {
+            let mut pid = 0;
+            unsafe { GetWindowThreadProcessId(GetForegroundWindow(), &mut pid) };
+            pid == self.pid
+        }
+
+        /// Reads the keys, returns false without reading them when osu! is not focused
+        pub fn read(&self, pressed: &mut [bool]) -> bool {
+            if !self.osu_focused() {
+                return false;
+            }
+            for (down, &key) in pressed.iter_mut().zip(&self.keys) {
+                *down = unsafe { GetAsyncKeyState(key as i32) } as u16 & 0x8000 != 0;
+            }
+            true
+        }
+    }
+}
+
+/// Keyboard read through X11, which Wine uses for the osu! window
+///
+/// libX11 is loaded at runtime so Cobra still starts on systems without it.
+#[cfg(target_os = "linux")]
+mod keyboard {
+    use std::ffi::{c_char, c_int, c_long, c_uchar, c_uint, c_ulong, c_void, CString};
+    use std::ptr::null_mut;
+    use super::{alphanumeric_key, SPECIAL_KEYS};
+
+    type Display = c_void;
+    type Window = c_ulong;
+    type Atom = c_ulong;
+    type KeySym = c_ulong;
+
+    /// Type of the _NET_WM_PID property
+    const XA_CARDINAL: Atom = 6;
+
+    struct Xlib {
+        open_display: unsafe extern "C" fn(*const c_char) -> *mut Display,
+        close_display: unsafe extern "C" fn(*mut Display) -> c_int,
+        query_keymap: unsafe extern "C" fn(*mut Display, *mut c_char) -> c_int,
+        string_to_keysym: unsafe extern "C" fn(*const c_char) -> KeySym,
+        keysym_to_keycode: unsafe extern "C" fn(*mut Display, KeySym) -> c_uchar,
+        get_input_focus: unsafe extern "C" fn(*mut Display, *mut Window, *mut c_int) -> c_int,
+        intern_atom: unsafe extern "C" fn(*mut Display, *const c_char, c_int) -> Atom,
+        #[allow(clippy::type_complexity)]
+        get_window_property: unsafe extern "C" fn(
+            *mut Display, Window, Atom, c_long, c_long, c_int, Atom,
+            *mut Atom, *mut c_int, *mut c_ulong, *mut c_ulong, *mut *mut c_uchar,
+        ) -> c_int,
+        query_tree: unsafe extern "C" fn(*mut Display, Window, *mut Window, *mut Window, *mut *mut Window, *mut c_uint) -> c_int,
+        free: unsafe extern "C" fn(*mut c_void) -> c_int,
+    }
+
+    impl Xlib {
+        // Each symbol is transmuted to the type of its field
+        #[allow(clippy::missing_transmute_annotations)]
+        fn load() -> Option<Self> {
+            unsafe {
+                let lib = libc::dlopen(c"libX11.so.6".as_ptr(), libc::RTLD_LAZY);
+                if lib.is_null() {
+                    return None;
+                }
+                macro_rules! symbol {
+                    ($name:literal) => {{
+                        let symbol = libc::dlsym(lib, $name.as_ptr());
+                        if symbol.is_null() {
+                            return None;
+                        }
+                        std::mem::transmute(symbol)
+                    }};
+                }
+                Some(Self {
+                    open_display: symbol!(c"XOpenDisplay"),
+                    close_display: symbol!(c"XCloseDisplay"),
+                    query_keymap: symbol!(c"XQueryKeymap"),
+                    string_to_keysym: symbol!(c"XStringToKeysym"),
+                    keysym_to_keycode: symbol!(c"XKeysymToKeycode"),
+                    get_input_focus: symbol!(c"XGetInputFocus"),
+                    intern_atom: symbol!(c"XInternAtom"),
+                    get_window_property: symbol!(c"XGetWindowProperty"),
+                    query_tree: symbol!(c"XQueryTree"),
+                    free: symbol!(c"XFree"),
+                })
+            }
+        }
+    }
+
+    /// X11 keysym of a key name
+    fn keysym_name(name: &str) -> Option<String> {
+        match SPECIAL_KEYS.iter().find(|(key, ..)| *key == name) {
+            Some(&(.., keysym)) => Some(keysym.to_string()),
+            None => alphanumeric_key(name).map(|c| c.to_ascii_lowercase().to_string()),
+        }
+    }
+
+    pub struct Keyboard {
+        xlib: Xlib,
+        display: *mut Display,
+        pid: u32,
+        pid_atom: Atom,
+        keycodes: Vec<c_uchar>,
+    }
+
+    // The display is only used by the polling thread the keyboard is moved to
+    unsafe impl Send for Keyboard {}
+
+    impl Keyboard {
+        pub fn open(pid: u32, names: &[String]) -> Option<Self> {
+            let xlib = Xlib::load()?;
+            let display = unsafe { (xlib.open_display)(std::ptr::null()) };
+            if display.is_null() {
+                return None;
+            }
+            // Built before the checks below so the display is closed on failure
+            let mut keyboard = Self { xlib, display, pid, pid_atom: 0, keycodes: Vec::new() };
+            keyboard.pid_atom = unsafe { (keyboard.xlib.intern_atom)(display, c"_NET_WM_PID".as_ptr(), 1) };
+            for name in names {
+                let keysym = CString::new(keysym_name(name)?).ok()?;
+                let keycode = unsafe { (keyboard.xlib.keysym_to_keycode)(display, (keyboard.xlib.string_to_keysym)(keysym.as_ptr())) };
+                if keycode == 0 {
+                    return None;
+                }
+                keyboard.keycodes.push(keycode);
+            }
+            (keyboard.pid_atom != 0).then_some(keyboard)
+        }
+
+        /// Process owning a window, from the _NET_WM_PID property set by Wine
+        fn window_pid(&self, window: Window) -> Option<u32> {
+            let (mut actual_type, mut format, mut items, mut after, mut data) = (0, 0, 0, 0, null_mut());
+            let status = unsafe {
+                (self.xlib.get_window_property)(
+                    self.display, window, self.pid_atom, 0, 1, 0, XA_CARDINAL,
+                    &mut actual_type, &mut format, &mut items, &mut after, &mut data,
+                )
+            };
+            if data.is_null() {
+                return None;
+            }
+            let pid = (status == 0 && format == 32 && items == 1).then(|| unsafe { *(data as *const c_ulong) } as u32);
+            unsafe { (self.xlib.free)(data as *mut c_void) };
+            pid
+        }
+
+        fn parent(&self, window: Window) -> Option<Window> {
+            let (mut root, mut parent, mut children, mut count) = (0, 0, null_mut(), 0);
+            if unsafe { (self.xlib.query_tree)(self.display, window, &mut root, &mut parent, &mut children, &mut count) } == 0 {
+                return None;
+            }
+            if !children.is_null() {
+                unsafe { (self.xlib.free)(children as *mut c_void) };
+            }
+            (parent != 0 && parent != root).then_some(parent)
+        }
+
+        fn osu_focused(&self) -> bool {
+            let (mut window, mut revert) = (0, 0);
+            unsafe { (self.xlib.get_input_focus)(self.display, &mut window, &mut revert) };
+            // 0 and 1 are None and PointerRoot, the focus can also be a child of the Wine window
+            while window > 1 {
+                if let Some(pid) = self.window_pid(window) {
+                    return pid == self.pid;
+                }
+                match self.parent(window) {
+                    Some(parent) => window = parent,
+                    None => return false,
+                }
+            }
+            false
+        }
+
+        /// Reads the keys, returns false without reading them when osu! is not focused
+        pub fn read(&self, pressed: &mut [bool]) -> bool {
+            if !self.osu_focused() {
+                return false;
+            }
+            let mut keymap = [0 as c_char; 32];
+            unsafe { (self.xlib.query_keymap)(self.display, keymap.as_mut_ptr()) };
+            for (down, &code) in pressed.iter_mut().zip(&self.keycodes) {
+                *down = keymap[code as usize / 8] as u8 & (1 << (code % 8)) != 0;
+            }
+            true
+        }
+    }
+
+    impl Drop for Keyboard {
+        fn drop(&mut self) {
+            unsafe { (self.xlib.close_display)(self.display) };
+        }
+    }
+}
+
+#[cfg(not(any(windows, target_os = "linux")))]
+mod keyboard {
+    pub struct Keyboard;
+
+    impl Keyboard {
+        pub fn open(_pid: u32, _names: &[String]) -> Option<Self> {
+            None
+        }
+
+        pub fn read(&self, _pressed: &mut [bool]) -> bool {
+            false
+        }
+    }
+}
+
+/// Number of columns of a mania map, stored as its CS
+fn key_count(path: &str) -> Option<usize> {
+    let map = rosu_pp::Beatmap::from_path(path).ok()?;
+    (map.mode as u8 == MANIA_MODE).then(|| map.cs.round() as usize)
+}
+
+/// Reads the keyboard for the columns of a mania play
+///
+/// osu! has no key overlay in mania, so the keys are polled directly on a
+/// dedicated thread, stopped when the value is dropped. The bindings are the
+/// ones set in the Cobra settings, osu! defaults otherwise.
+#[derive(Debug)]
+pub struct ManiaInput {
+    tracker: Arc<Mutex<KeyTracker>>,
+    running: Arc<AtomicBool>,
+}
+
+impl ManiaInput {
+    /// Starts polling the keys of a map
+    ///
+    /// Keys are only counted while osu! has the focus, so typing in another
+    /// window during a play is ignored.
+    ///
+    /// # Arguments
+    /// * `path` - Path of the .osu file
+    /// * `pid` - Process id of osu!
+    ///
+    /// # Returns
+    /// None when reading the keyboard is disabled in the settings, not supported
+    /// on this platform, or when the map or the bindings are not mania ones
+    pub fn start(path: &str, pid: u32) -> Option<Self> {
+        if !MANIA_KEYBOARD.load(Ordering::Relaxed) {
+            return None;
+        }
+        let names = bindings(key_count(path)?);
+        if names.is_empty() {
+            return None;
+        }
+        let keyboard = Keyboard::open(pid, &names)?;
+
+        let tracker = Arc::new(Mutex::new(KeyTracker::new(&names)));
+        let running = Arc::new(AtomicBool::new(true));
+        let (thread_tracker, thread_running) = (tracker.clone(), running.clone());
+        thread::spawn(move || {
+            let mut pressed = vec![false; names.len()];
+            while thread_running.load(Ordering::Relaxed) {
+                // Keys held when osu! loses the focus are released
+                if !keyboard.read(&mut pressed) {
+                    pressed.fill(false);
+                }
+                if let Ok(mut tracker) = thread_tracker.lock() {
+                    tracker.update_pressed(&pressed, Instant::now());
+                }
+                thread::sleep(POLL_INTERVAL);
+            }
+        });
+        Some(Self { tracker, running })
+    }
+
+    /// Keys of the play so far
+    pub fn stats(&self) -> KeyStats {
+        self.tracker
+            .lock()
+            .map(|mut tracker| tracker.stats(Instant::now()))
+            .unwrap_or_default()
+    }
+}
+
+impl Drop for ManiaInput {
+    fn drop(&mut self) {
+        self.running.store(false, Ordering::Relaxed);
+    }
+}
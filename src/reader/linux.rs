@@ -0,0 +1,213 @@
+use std::fs;
+use std::fs::File;
+use std::os::unix::fs::FileExt;
+use std::path::{Path, PathBuf};
+use crate::reader::error::ReaderError;
+use crate::reader::memory::MemorySource;
+
+/// Readable memory region of the process, from /proc/pid/maps
+#[derive(Debug, Clone)]
+struct MemoryRegion {
+    start: usize,
+    size: usize,
+}
+
+/// osu! stable running under Wine, read through /proc
+pub struct WineProcess {
+    pub pid: i32,
+    /// Wine prefix of the process (WINEPREFIX or ~/.wine)
+    pub prefix: PathBuf,
+    executable_dir: Option<PathBuf>,
+    regions: Vec<MemoryRegion>,
+    mem: Option<File>,
+}
+
+impl WineProcess {
+    /// Finds the osu! process running under Wine and opens its memory
+    ///
+    /// # Arguments
+    /// * `name` - Executable name, e.g. "osu!.exe"
+    pub fn initialize(name: &str) -> eyre::Result<Self> {
+        let (pid, executable) = find_process(name)?;
+        let prefix = wine_prefix(pid);
+        let executable_dir = translate_wine_path(&prefix, &executable)
+            .parent()
+            .map(|p| p.to_path_buf());
+
+        Ok(Self {
+            pid,
+            executable_dir,
+            regions: read_regions(pid)?,
+            mem: File::open(format!("/proc/{}/mem", pid)).ok(),
+            prefix,
+        })
+    }
+
+    fn read_process_vm(&self, addr: i32, buff: &mut [u8]) -> bool {
+        let local = libc::iovec {
+            iov_base: buff.as_mut_ptr() as *mut libc::c_void,
+            iov_len: buff.len(),
+        };
+        let remote = libc::iovec {
+            iov_base: addr as u32 as usize as *mut libc::c_void,
+            iov_len: buff.len(),
+        };
+        let read = unsafe { libc::process_vm_readv(self.pid, &local, 1, &remote, 1, 0) };
+        read == buff.len() as isize
+    }
+
+    fn read_proc_mem(&self, addr: i32, buff: &mut [u8]) -> eyre::Result<()> {
+        let mem = self.mem.as_ref().ok_or(ReaderError::ProcessGone)?;
+        mem.read_exact_at(buff, addr as u32 as u64)?;
+        Ok(())
+    }
+}
+
+impl MemorySource for WineProcess {
+    fn read_bytes(&self, addr: i32, len: usize) -> eyre::Result<Vec<u8>> {
+        if !Path::new(&format!("/proc/{}", self.pid)).exists() {
+            return Err(ReaderError::ProcessGone.into());
+        }
+        let mut buff = vec![0u8; len];
+        if !self.read_process_vm(addr, &mut buff) {
+            self.read_proc_mem(addr, &mut buff)?;
+        }
+        Ok(buff)
+    }
+
+    fn executable_dir(&self) -> Option<PathBuf> {
+        self.executable_dir.clone()
+    }
+
+    fn translate_path(&self, path: &str) -> String {
+        translate_wine_path(&self.prefix, path).display().to_string()
+    }
+
+    fn process_id(&self) -> Option<u32> {
+        Some(self.pid as u32)
+    }
+
+    fn read_signature(&self, signature: &str) -> eyre::Result<i32> {
+        let pattern = parse_signature(signature)?;
+        for region in &self.regions {
+            let Ok(bytes) = self.read_bytes(region.start as i32, region.size) else {
+                continue;
+            };
+            if let Some(offset) = find_pattern(&bytes, &pattern) {
+                return Ok((region.start + offset) as i32);
+            }
+        }
+        Err(eyre::eyre!("Signature not found: {}", signature))
+    }
+}
+
+/// Looks for a process whose command line starts with the Windows executable
+fn find_process(name: &str) -> eyre::Result<(i32, String)> {
+    let name = name.to_lowercase();
+    for entry in fs::read_dir("/proc")?.flatten() {
+        let Ok(pid) = entry.file_name().to_string_lossy().parse::<i32>() else {
+            continue;
+        };
+        let Ok(cmdline) = fs::read(entry.path().join("cmdline")) else {
+            continue;
+        };
+        let executable = cmdline
+            .split(|&b| b == 0)
+            .map(|arg| String::from_utf8_lossy(arg).to_string())
+            .find(|arg| arg.to_lowercase().replace('\\', "/").ends_with(&format!("/{}", name))
+                || arg.to_lowercase() == name);
+        if let Some(executable) = executable {
+            return Ok((pid, executable));
+        }
+    }
+    Err(ReaderError::ProcessGone.into())
+}
+
+/// Reads WINEPREFIX from the process environment, defaulting to ~/.wine
+fn wine_prefix(pid: i32) -> PathBuf {
+    let environ = fs::read(format!("/proc/{}/environ", pid)).unwrap_or_default();
+    environ
+        .split(|&b| b == 0)
+        .map(|var| String::from_utf8_lossy(var).to_string())
+        .find_map(|var| var.strip_prefix("WINEPREFIX=").map(PathBuf::from))
+        .unwrap_or_else(|| {
+            let home = std::env::var("HOME").unwrap_or_else(|_| "/root".to_string());
+            Path::new(&home).join(".wine")
+        })
+}
+
+/// Converts a Wine path (`Z:\home\...`, `C:\users\...`) into a Linux path
+///
+/// `Z:` maps to the filesystem root, other drives go through the prefix
+/// dosdevices links and fall back to `drive_c` for `C:`. Relative paths and
+/// paths that are already Unix paths are returned unchanged.
+pub fn translate_wine_path(prefix: &Path, path: &str) -> PathBuf {
+    let bytes = path.as_bytes();
+    if bytes.len() < 2 || bytes[1] != b':' || !bytes[0].is_ascii_alphabetic() {
+        return PathBuf::from(path.replace('\\', "/"));
+    }
+
+    let drive = (bytes[0] as char).to_ascii_lowercase();
+    let rest = path[2..].replace('\\', "/");
+    let rest = rest.trim_start_matches('/');
+    let root = if drive == 'z' {
+        PathBuf::from("/")
+    } else {
+        let device = prefix.join("dosdevices").join(format!("{}:", drive));
+        match fs::canonicalize(&device) {
+            Ok(target) => target,
+            Err(_) if drive == 'c' => prefix.join("drive_c"),
+            Err(_) => device,
+        }
+    };
+    root.join(rest)
+}
+
+fn read_regions(pid: i32) -> eyre::Result<Vec<MemoryRegion>> {
+    let maps = fs::read_to_string(format!("/proc/{}/maps", pid))?;
+    let regions = maps
+        .lines()
+        .filter_map(|line| {
+            let mut parts = line.split_whitespace();
+            let range = parts.next()?;
+            let perms = parts.next()?;
+            if !perms.starts_with('r') {
+                return None;
+            }
+            let (start, end) = range.split_once('-')?;
+            let start = usize::from_str_radix(start, 16).ok()?;
+            let end = usize::from_str_radix(end, 16).ok()?;
+            // osu! is a 32-bit process, its memory lives below 4GB
+            if end > u32::MAX as usize {
+                return None;
+            }
+            Some(MemoryRegion { start, size: end - start })
+        })
+        .collect();
+    Ok(regions)
+}
+
+/// Parses a signature such as "F8 01 ?? 04" into bytes, `None` being a wildcard
+fn parse_signature(signature: &str) -> eyre::Result<Vec<Option<u8>>> {
+    signature
+        .split_whitespace()
+        .map(|byte| match byte {
+            "??" => Ok(None),
+            _ => u8::from_str_radix(byte, 16)
+                .map(Some)
+                .map_err(|e| eyre::eyre!("Invalid signature byte {}: {}", byte, e)),
+        })
+        .collect()
+}
+
+fn find_pattern(bytes: &[u8], pattern: &[Option<u8>]) -> Option<usize> {
+    if pattern.is_empty() || bytes.len() < pattern.len() {
+        return None;
+    }
+    bytes.windows(pattern.len()).position(|window| {
+        window
+            .iter()
+            .zip(pattern)
+            .all(|(byte, expected)| expected.map_or(true, |e| e == *byte))
+    })
+}
@@ -0,0 +1,326 @@
+use std::cell::{Cell, RefCell};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use rosu_mem::process::{Process, ProcessTraits};
+use rosu_mem::signature::Signature;
+use serde::{Deserialize, Serialize};
+use crate::reader::error::ReaderError;
+use crate::reader::structs::StaticAddresses;
+
+/// Source of osu! memory used by every reader
+///
+/// Only `read_bytes` has to be implemented, typed reads are decoded from it
+/// the same way rosu-mem does (little endian, C# strings as UTF-16).
+pub trait MemorySource {
+    /// Reads `len` bytes starting at `addr`
+    fn read_bytes(&self, addr: i32, len: usize) -> eyre::Result<Vec<u8>>;
+
+    /// Directory of the osu! executable, if known
+    fn executable_dir(&self) -> Option<PathBuf> {
+        None
+    }
+
+    /// Converts a path read from osu! memory into a path usable by Cobra
+    fn translate_path(&self, path: &str) -> String {
+        path.to_string()
+    }
+
+    /// Id of the osu! process, None when the memory does not come from a live process
+    fn process_id(&self) -> Option<u32> {
+        None
+    }
+
+    /// Scans the memory for a signature such as "F8 01 74 04 83 65" ("??" being a wildcard)
+    fn read_signature(&self, signature: &str) -> eyre::Result<i32> {
+        Err(eyre::eyre!("Signature scanning is not supported by this memory source: {}", signature))
+    }
+
+    fn read_u8(&self, addr: i32) -> eyre::Result<u8> {
+        Ok(self.read_bytes(addr, 1)?[0])
+    }
+
+    fn read_i16(&self, addr: i32) -> eyre::Result<i16> {
+        let bytes = self.read_bytes(addr, 2)?;
+        Ok(i16::from_le_bytes([bytes[0], bytes[1]]))
+    }
+
+    fn read_i32(&self, addr: i32) -> eyre::Result<i32> {
+        let bytes = self.read_bytes(addr, 4)?;
+        Ok(i32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    }
+
+    fn read_u32(&self, addr: i32) -> eyre::Result<u32> {
+        Ok(self.read_i32(addr)? as u32)
+    }
+
+    fn read_u64(&self, addr: i32) -> eyre::Result<u64> {
+        let bytes = self.read_bytes(addr, 8)?;
+        let mut buff = [0u8; 8];
+        buff.copy_from_slice(&bytes[..8]);
+        Ok(u64::from_le_bytes(buff))
+    }
+
+    fn read_f64(&self, addr: i32) -> eyre::Result<f64> {
+        Ok(f64::from_bits(self.read_u64(addr)?))
+    }
+
+    /// Reads a C# string whose pointer is stored at `addr`
+    fn read_string(&self, addr: i32) -> eyre::Result<String> {
+        let string_addr = self.read_i32(addr)?;
+        if string_addr == 0 {
+            return Err(ReaderError::NullPointer { addr }.into());
+        }
+        let len = self.read_u32(string_addr + 0x4)? as usize;
+        if len == 0 {
+            return Ok(String::new());
+        }
+        let bytes = self.read_bytes(string_addr + 0x8, len * 2)?;
+        let chars: Vec<u16> = bytes
+            .chunks_exact(2)
+            .map(|c| u16::from_le_bytes([c[0], c[1]]))
+            .collect();
+        Ok(String::from_utf16_lossy(&chars))
+    }
+}
+
+impl MemorySource for Process {
+    fn read_bytes(&self, addr: i32, len: usize) -> eyre::Result<Vec<u8>> {
+        let mut buff = vec![0u8; len];
+        ProcessTraits::read(self, addr, len, &mut buff)?;
+        Ok(buff)
+    }
+
+    fn executable_dir(&self) -> Option<PathBuf> {
+        self.executable_dir.clone()
+    }
+
+    fn process_id(&self) -> Option<u32> {
+        Some(self.pid as u32)
+    }
+
+    fn read_signature(&self, signature: &str) -> eyre::Result<i32> {
+        let signature = Signature::from_str(signature)?;
+        Ok(ProcessTraits::read_signature(self, &signature)?)
+    }
+
+    fn read_i16(&self, addr: i32) -> eyre::Result<i16> {
+        Ok(ProcessTraits::read_i16(self, addr)?)
+    }
+
+    fn read_i32(&self, addr: i32) -> eyre::Result<i32> {
+        Ok(ProcessTraits::read_i32(self, addr)?)
+    }
+
+    fn read_u32(&self, addr: i32) -> eyre::Result<u32> {
+        Ok(ProcessTraits::read_u32(self, addr)?)
+    }
+
+    fn read_u64(&self, addr: i32) -> eyre::Result<u64> {
+        Ok(ProcessTraits::read_u64(self, addr)?)
+    }
+
+    fn read_f64(&self, addr: i32) -> eyre::Result<f64> {
+        Ok(ProcessTraits::read_f64(self, addr)?)
+    }
+
+    fn read_string(&self, addr: i32) -> eyre::Result<String> {
+        Ok(ProcessTraits::read_string(self, addr)?)
+    }
+}
+
+/// Memory region saved in a snapshot
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct SnapshotRegion {
+    addr: i32,
+    /// Bytes of the region as an hexadecimal string
+    bytes: String,
+}
+
+/// Snapshot file content: static addresses and every memory region read
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct Snapshot {
+    addresses: StaticAddresses,
+    #[serde(default)]
+    executable_dir: Option<PathBuf>,
+    regions: Vec<SnapshotRegion>,
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn from_hex(hex: &str) -> eyre::Result<Vec<u8>> {
+    if hex.len() % 2 != 0 {
+        return Err(eyre::eyre!("Invalid hex string length: {}", hex.len()));
+    }
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).map_err(|e| eyre::eyre!("Invalid hex string: {}", e)))
+        .collect()
+}
+
+/// Memory source backed by a snapshot (address -> bytes), used for tests
+#[derive(Debug, Default, Clone)]
+pub struct MockProcess {
+    pub addresses: StaticAddresses,
+    pub executable_dir: Option<PathBuf>,
+    regions: BTreeMap<i32, Vec<u8>>,
+}
+
+impl MockProcess {
+    pub fn new(addresses: StaticAddresses) -> Self {
+        Self {
+            addresses,
+            ..Default::default()
+        }
+    }
+
+    /// Loads a snapshot file written by `RecordingSource::dump`
+    pub fn from_snapshot(path: &Path) -> eyre::Result<Self> {
+        let content = std::fs::read_to_string(path)?;
+        let snapshot: Snapshot = serde_json::from_str(&content)?;
+        let mut mock = MockProcess::new(snapshot.addresses);
+        mock.executable_dir = snapshot.executable_dir;
+        for region in snapshot.regions {
+            mock.regions.insert(region.addr, from_hex(&region.bytes)?);
+        }
+        Ok(mock)
+    }
+
+    /// Writes the regions of the mock as a snapshot file
+    pub fn save_snapshot(&self, path: &Path) -> eyre::Result<()> {
+        let snapshot = Snapshot {
+            addresses: self.addresses.clone(),
+            executable_dir: self.executable_dir.clone(),
+            regions: self.regions
+                .iter()
+                .map(|(&addr, bytes)| SnapshotRegion { addr, bytes: to_hex(bytes) })
+                .collect(),
+        };
+        std::fs::write(path, serde_json::to_string_pretty(&snapshot)?)?;
+        Ok(())
+    }
+
+    pub fn with_bytes(mut self, addr: i32, bytes: &[u8]) -> Self {
+        self.regions.insert(addr, bytes.to_vec());
+        self
+    }
+
+    pub fn with_i16(self, addr: i32, value: i16) -> Self {
+        self.with_bytes(addr, &value.to_le_bytes())
+    }
+
+    pub fn with_i32(self, addr: i32, value: i32) -> Self {
+        self.with_bytes(addr, &value.to_le_bytes())
+    }
+
+    pub fn with_u64(self, addr: i32, value: u64) -> Self {
+        self.with_bytes(addr, &value.to_le_bytes())
+    }
+
+    pub fn with_f64(self, addr: i32, value: f64) -> Self {
+        self.with_bytes(addr, &value.to_le_bytes())
+    }
+
+    /// Writes a C# string object at `string_addr` and its pointer at `addr`
+    pub fn with_string(self, addr: i32, string_addr: i32, value: &str) -> Self {
+        let chars: Vec<u8> = value.encode_utf16().flat_map(|c| c.to_le_bytes()).collect();
+        self.with_i32(addr, string_addr)
+            .with_i32(string_addr + 0x4, value.encode_utf16().count() as i32)
+            .with_bytes(string_addr + 0x8, &chars)
+    }
+}
+
+impl MemorySource for MockProcess {
+    fn read_bytes(&self, addr: i32, len: usize) -> eyre::Result<Vec<u8>> {
+        let (&start, bytes) = self.regions
+            .range(..=addr)
+            .next_back()
+            .ok_or_else(|| eyre::eyre!("Address 0x{:X} is not in the snapshot", addr))?;
+        let offset = (addr - start) as usize;
+        if offset + len > bytes.len() {
+            return Err(eyre::eyre!("Address 0x{:X} (+{}) is not in the snapshot", addr, len));
+        }
+        Ok(bytes[offset..offset + len].to_vec())
+    }
+
+    fn executable_dir(&self) -> Option<PathBuf> {
+        self.executable_dir.clone()
+    }
+}
+
+/// Memory kept by default by a `RecordingSource`, enough for hours of reads of the same objects
+const MAX_RECORDED_BYTES: usize = 16 * 1024 * 1024;
+
+/// Memory source recording every read of another source, to build snapshots
+/// from real sessions
+pub struct RecordingSource<'a, M: MemorySource> {
+    inner: &'a M,
+    reads: RefCell<BTreeMap<i32, Vec<u8>>>,
+    /// Bytes kept in `reads`, new addresses are no longer recorded past `max_bytes`
+    recorded_bytes: Cell<usize>,
+    max_bytes: usize,
+}
+
+impl<'a, M: MemorySource> RecordingSource<'a, M> {
+    pub fn new(inner: &'a M) -> Self {
+        Self::with_limit(inner, MAX_RECORDED_BYTES)
+    }
+
+    /// Records at most `max_bytes` of memory, the addresses already recorded keep being updated
+    pub fn with_limit(inner: &'a M, max_bytes: usize) -> Self {
+        Self {
+            inner,
+            reads: RefCell::new(BTreeMap::new()),
+            recorded_bytes: Cell::new(0),
+            max_bytes,
+        }
+    }
+
+    /// Bytes of memory recorded so far
+    pub fn recorded_bytes(&self) -> usize {
+        self.recorded_bytes.get()
+    }
+
+    /// Turns the recorded reads into a mock process
+    pub fn to_mock(&self, addresses: &StaticAddresses) -> MockProcess {
+        let mut mock = MockProcess::new(addresses.clone());
+        mock.executable_dir = self.inner.executable_dir();
+        for (&addr, bytes) in self.reads.borrow().iter() {
+            mock = mock.with_bytes(addr, bytes);
+        }
+        mock
+    }
+
+    /// Writes the recorded reads as a snapshot file
+    pub fn dump(&self, path: &Path, addresses: &StaticAddresses) -> eyre::Result<()> {
+        self.to_mock(addresses).save_snapshot(path)
+    }
+}
+
+impl<'a, M: MemorySource> MemorySource for RecordingSource<'a, M> {
+    fn read_bytes(&self, addr: i32, len: usize) -> eyre::Result<Vec<u8>> {
+        let bytes = self.inner.read_bytes(addr, len)?;
+        let mut reads = self.reads.borrow_mut();
+        let recorded = reads.get(&addr).map_or(0, Vec::len);
+        let total = self.recorded_bytes.get() - recorded + len;
+        if recorded <= len && total <= self.max_bytes {
+            reads.insert(addr, bytes.clone());
+            self.recorded_bytes.set(total);
+        }
+        Ok(bytes)
+    }
+
+    fn executable_dir(&self) -> Option<PathBuf> {
+        self.inner.executable_dir()
+    }
+
+    fn translate_path(&self, path: &str) -> String {
+        self.inner.translate_path(path)
+    }
+
+    fn process_id(&self) -> Option<u32> {
+        self.inner.process_id()
+    }
+}
@@ -1,275 +1,400 @@
-use crate::reader::reader_beatmap::{get_audio_path, get_beatmap, get_beatmap_md5, get_beatmap_path, get_cover_path};
-use crate::reader::reader_common::get_status;
-use crate::reader::reader_gameplay::{get_mods, get_retries};
-use crate::reader::structs::{GameState, State, StaticAddresses};
+use crate::library::view::refresh_install_status;
+use crate::library::{load_index, InstallStatus};
+use crate::reader::error::{recovery_for, Recovery};
+use crate::reader::events::{listen, EventBus, GameEvent};
+use crate::reader::memory::{MemorySource, RecordingSource};
+use crate::reader::state_machine::GameStateMachine;
+use crate::reader::structs::{BeatmapInfo, GameplayValues, HitErrors, InnerValues, State, StaticAddresses};
+use crate::utils::difficulty::{calc_difficulty_matrix, DifficultyMatrix, DEFAULT_ACCURACIES};
+use crate::utils::msd::MSD_RATES;
+use crate::utils::mods::{mods_to_string, without_speed_mods};
 use crate::utils::nps::get_nps;
-use crate::utils::pattern_detector::get_patterns;
-use crate::{AppState, LoginPage, MapData, MapSetState};
+use crate::utils::pattern_detector::{dominant_patterns, get_patterns};
+use crate::{AppState, LoginPage, MapData, MapSetState, RateDifficultyData};
 use eyre::{Report, Result};
 use rosu_mem::error::ProcessError;
+#[cfg(not(target_os = "linux"))]
 use rosu_mem::process::{Process, ProcessTraits};
 use serde_json::Value;
 use slint::{ComponentHandle, Image, ModelRc, SharedString, VecModel, Weak};
 use std::env::Args;
-use std::os::windows::raw::HANDLE;
 use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
 use std::thread;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 use futures_util::task::SpawnExt;
 use tokio::sync::Mutex;
 use rayon::prelude::*;
 
+mod error;
+pub mod events;
+pub mod input;
+#[cfg(target_os = "linux")]
+mod linux;
+pub(crate) mod memory;
 mod reader_beatmap;
 mod reader_common;
 mod reader_gameplay;
+mod reader_keys;
+mod reader_pp;
 mod reader_resultscreen;
-mod structs;
+mod state_machine;
+pub mod structs;
+#[cfg(test)]
+mod tests;
+
+/// Hit errors shown in the distribution graph, in ms around 0
+const HIT_DISTRIBUTION_RANGE: i32 = 150;
+/// Width of a bar of the distribution graph, in ms
+const HIT_DISTRIBUTION_BUCKET: i32 = 5;
+/// Whether `controlla` is attached to a running osu!
+static ATTACHED: AtomicBool = AtomicBool::new(false);
+/// Delay between two writes of the snapshot recorded with `COBRA_RECORD`
+const RECORD_DUMP_INTERVAL: Duration = Duration::from_secs(30);
 
 fn analyze_patterns(json_value: &Value) -> Vec<SharedString> {
-    let mut patterns = Vec::new();
-
-    if let Some(tertiary) = json_value
-        .get("TertiaryPattern")
-        .and_then(|v| v.as_object())
-    {
-        let mut values: Vec<(String, f64)> = tertiary
-            .iter()
-            .filter_map(|(key, val)| val.as_f64().map(|v| (key.clone(), v)))
-            .filter(|(_k, v)| *v > 0.0)
-            .collect();
-
-        values.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal));
+    dominant_patterns(json_value).into_iter().map(SharedString::from).collect()
+}
 
-        if !values.is_empty() {
-            let max_value = values[0].1;
-            patterns = values
-                .into_iter()
-                .filter(|(_k, v)| *v >= (max_value * 0.8))
-                .map(|(k, v)| format!("{}", k).into())
-                .collect();
-        }
-    }
+/// Updates the UI from the reader events, on a dedicated thread
+///
+/// # Arguments
+/// * `events` - Event bus of the reader
+/// * `weak` - Handle of the main window
+pub fn subscribe_ui(events: &EventBus, weak: Weak<LoginPage>) {
+    let receiver = events.subscribe();
+    thread::spawn(move || {
+        listen(receiver, |event| match event {
+            GameEvent::MapChanged { map, mods } => {
+                println!("New map: {}", map.path);
+                show_map(map, mods, weak.clone());
+            }
+            GameEvent::ModsChanged { map, mods } => {
+                println!("Mods changed: {}", mods_to_string(mods));
+                let handle = weak.clone();
+                std::thread::spawn(move || {
+                    let pp_values = calc_mods_pp(&map.path, mods);
+                    let _ = handle.upgrade_in_event_loop(move |handle| {
+                        update_pp_ui(&handle, pp_values);
+                    });
+                });
+            }
+            GameEvent::PlayFinished { hit_errors, .. } | GameEvent::PlayQuit { hit_errors } => {
+                push_hit_distribution(&hit_errors, weak.clone());
+            }
+            GameEvent::FoldersFound { game, songs } => {
+                // Only filled when the user did not choose a folder
+                let _ = weak.upgrade_in_event_loop(move |handle| {
+                    let state = handle.global::<MapSetState>();
+                    if state.get_osu_path().is_empty() {
+                        state.set_osu_path(SharedString::from(songs));
+                    }
+                });
+                if let Some(game) = game {
+                    let handle = weak.clone();
+                    thread::spawn(move || match load_index(Path::new(&game)) {
+                        Ok(_) => refresh_install_status(handle),
+                        Err(e) => eprintln!("Erreur lors de la lecture d'osu!.db: {}", e),
+                    });
+                }
+            }
+            _ => {}
+        });
+    });
+}
 
-    patterns
+/// Shows the live pp of the current play, computed by the state machine
+///
+/// # Arguments
+/// * `events` - Event bus of the reader
+/// * `weak` - Handle of the main window
+pub fn subscribe_pp_counter(events: &EventBus, weak: Weak<LoginPage>) {
+    let receiver = events.subscribe();
+    thread::spawn(move || {
+        listen(receiver, |event| {
+            if let GameEvent::GameplayUpdated(values) = event {
+                if let Some((current, fc)) = values.live_pp {
+                    push_live_values(values, current, fc, weak.clone());
+                }
+            }
+        });
+    });
 }
 
-pub fn waiting_for_play(p: &Process, state: &mut State, weak: Weak<LoginPage>) -> eyre::Result<()> {
-    let mut last_map: MapData = MapData {
-        song: "".into(),
-        author: "".into(),
-        creator: "".into(),
-        cover: slint::Image::default(),
-        link: "".into(),
-        difficulties: "".into(),
-        download_progress: 0.0,
-        md5: "".into(),
-    };
+/// Loads the analysis of a newly selected map and shows it
+fn show_map(map: BeatmapInfo, mods: u32, handle: Weak<LoginPage>) {
+    std::thread::spawn(move || {
+        let path = map.path.clone();
+        let (pp_values, (b, patterns)) = rayon::join(
+            || calc_mods_pp(&path, mods),
+            || rayon::join(
+                || get_nps(&path, 1.0).unwrap_or_default(),
+                || {
+                    let patterns = get_patterns(&path).unwrap_or_default();
+                    analyze_patterns(&patterns)
+                }
+            )
+        );
 
-    loop {
-        if GameState::from(get_status(p, state)) == GameState::Playing {
-            return Ok(());
-        }
 
-        if SharedString::from(get_beatmap_md5(p, state)?) != last_map.md5 {
-            println!("New map");
-            let map = get_beatmap(p, state)?;
-            let map_to_move = map.clone();
-            last_map = map.clone();
-            let handle = weak.clone();
-            let path = get_beatmap_path(
-                p,
-                state
-            )?;
-            println!("{}", path);
-            let img = get_cover_path(p, state)?;
-            let audio = get_audio_path(p,state)?;
+        let values : Vec<f32> = b.iter().map(|kv| kv.value as f32).collect();
+        let (avg, max) = {
+            let values: Vec<f32> = b.par_iter()
+                .map(|kv| kv.value as f32)
+                .collect();
 
-            std::thread::spawn(move || {
-                let song = map_to_move.song.clone();
-                let author = map_to_move.author.clone();
-                let creator = map_to_move.creator.clone();
-                let link = map_to_move.link.clone();
-                let difficulties = map_to_move.difficulties.clone();
-                let progress = map_to_move.download_progress;
-                let md5 = map_to_move.md5.clone();
-                let (calc_pp, (b, patterns)) = rayon::join(
-                    || calc_pp(&path),
-                    || rayon::join(
-                        || get_nps(&path, 1.0).unwrap(),
-                        || {
-                            let patterns = get_patterns(&path).unwrap();
-                            analyze_patterns(&patterns)
-                        }
-                    )
+            let (sum, max) = values.par_iter()
+                .fold(
+                    || (0.0f32, f32::NEG_INFINITY),
+                    |(sum, max), &value| (sum + value, max.max(value))
+                )
+                .reduce(
+                    || (0.0f32, f32::NEG_INFINITY),
+                    |(sum1, max1), (sum2, max2)| (sum1 + sum2, max1.max(max2))
                 );
 
+            (sum / values.len() as f32, max)
+        };
 
-                let values : Vec<f32> = b.iter().map(|kv| kv.value as f32).collect();
-                let (avg, max) = {
-                    let values: Vec<f32> = b.par_iter()
-                        .map(|kv| kv.value as f32)
-                        .collect();
-
-                    let (sum, max) = values.par_iter()
-                        .fold(
-                            || (0.0f32, f32::NEG_INFINITY),
-                            |(sum, max), &value| (sum + value, max.max(value))
-                        )
-                        .reduce(
-                            || (0.0f32, f32::NEG_INFINITY),
-                            |(sum1, max1), (sum2, max2)| (sum1 + sum2, max1.max(max2))
-                        );
-
-                    (sum / values.len() as f32, max)
-                };
-
-                handle.upgrade_in_event_loop(move |handle| {
-                    let img = Image::load_from_path(Path::new(&img)).unwrap_or_else(|_| {
-                        Image::default()
-                    });
-                    let map_data = MapData {
-                        song,
-                        author,
-                        creator,
-                        cover: img,
-                        link,
-                        difficulties,
-                        download_progress: progress,
-                        md5,
-                    };
-                    handle.global::<AppState>().set_map(map_data);
-                    let model_data = ModelRc::new(VecModel::from(values));
-                    handle.global::<AppState>().set_graph_data(model_data);
-                    handle.global::<AppState>().set_avg_nps(avg);
-                    handle.global::<AppState>().set_max_value(max);
-                    handle.global::<AppState>().set_pp_text1(SharedString::from(format!("95%: {}", calc_pp[0])));
-                    handle.global::<AppState>().set_pp_text2(SharedString::from(format!("98%: {}", calc_pp[1])));
-                    handle.global::<AppState>().set_pp_text3(SharedString::from(format!("99%: {}", calc_pp[2])));
-                    handle.global::<AppState>().set_pp_text4(SharedString::from(format!("100%: {}", calc_pp[3])));
-                    let patterns = ModelRc::new(VecModel::from(patterns));
-                    handle.global::<AppState>().set_patterns(patterns);
-                    handle.global::<AppState>().set_audio_path(SharedString::from(audio));
-                    handle.global::<AppState>().set_osu_path(SharedString::from(path));
-                })
-                    .expect("Échec de la mise à jour de l'interface");
+        handle.upgrade_in_event_loop(move |handle| {
+            let img = Image::load_from_path(Path::new(&map.cover_path)).unwrap_or_else(|_| {
+                Image::default()
             });
+            let map_data = MapData {
+                song: SharedString::from(map.song),
+                author: SharedString::from(map.author),
+                creator: SharedString::from(map.creator),
+                cover: img,
+                link: SharedString::new(),
+                difficulties: SharedString::from(map.difficulty),
+                download_progress: 0.0,
+                install_status: InstallStatus::Installed.as_ui(),
+                md5: SharedString::from(map.md5),
+            };
+            handle.global::<AppState>().set_map(map_data);
+            let model_data = ModelRc::new(VecModel::from(values));
+            handle.global::<AppState>().set_graph_data(model_data);
+            handle.global::<AppState>().set_avg_nps(avg);
+            handle.global::<AppState>().set_max_value(max);
+            update_pp_ui(&handle, pp_values);
+            let patterns = ModelRc::new(VecModel::from(patterns));
+            handle.global::<AppState>().set_patterns(patterns);
+            handle.global::<AppState>().set_audio_path(SharedString::from(map.audio_path));
+            handle.global::<AppState>().set_osu_path(SharedString::from(map.path));
+        })
+            .expect("Échec de la mise à jour de l'interface");
+    });
+}
 
+/// Pre-play difficulty values of a map for the selected mods
+struct ModsPP {
+    mods: u32,
+    stars: f64,
+    pp: Vec<i32>,
+    matrix: DifficultyMatrix,
+}
 
+/// Computes stars, pp and the rate table of a map for the given mods
+///
+/// # Arguments
+/// * `path` - Path of the .osu file
+/// * `mods` - Mods bitmask selected in song select
+fn calc_mods_pp(path: &String, mods: u32) -> ModsPP {
+    let ((stars, pp), matrix) = rayon::join(
+        || calc_pp(path, mods),
+        || calc_difficulty_matrix(path, without_speed_mods(mods), &MSD_RATES, &DEFAULT_ACCURACIES)
+            .unwrap_or_else(|e| {
+                eprintln!("Erreur lors du calcul de la table de rates: {}", e);
+                DifficultyMatrix::default()
+            })
+    );
+    ModsPP { mods, stars, pp, matrix }
+}
 
-        }
+fn update_pp_ui(handle: &LoginPage, values: ModsPP) {
+    let app_state = handle.global::<AppState>();
+    app_state.set_mods(SharedString::from(mods_to_string(values.mods)));
+    app_state.set_stars(values.stars as f32);
+    app_state.set_pp_text1(SharedString::from(format!("95%: {}", values.pp[0])));
+    app_state.set_pp_text2(SharedString::from(format!("98%: {}", values.pp[1])));
+    app_state.set_pp_text3(SharedString::from(format!("99%: {}", values.pp[2])));
+    app_state.set_pp_text4(SharedString::from(format!("100%: {}", values.pp[3])));
+    let accuracies: Vec<f32> = values.matrix.accuracies.iter().map(|&a| a as f32).collect();
+    app_state.set_rate_table_accuracies(ModelRc::new(VecModel::from(accuracies)));
+    app_state.set_rate_table(rate_table_model(&values.matrix));
+    app_state.invoke_update_pp_window(SharedString::from(values.pp[3].to_string()));
+}
 
-        thread::sleep(Duration::from_millis(300));
-    }
+fn rate_table_model(matrix: &DifficultyMatrix) -> ModelRc<RateDifficultyData> {
+    let rows: Vec<RateDifficultyData> = matrix
+        .rates
+        .iter()
+        .map(|row| RateDifficultyData {
+            rate: row.rate as f32,
+            stars: row.stars as f32,
+            msd: row.msd.unwrap_or(0.0) as f32,
+            pp: ModelRc::new(VecModel::from(
+                row.pp.iter().map(|(_, pp)| *pp as i32).collect::<Vec<i32>>()
+            )),
+        })
+        .collect();
+    ModelRc::new(VecModel::from(rows))
 }
 
-fn calc_pp(path: &String) -> Vec<i32> {
+fn calc_pp(path: &String, mods: u32) -> (f64, Vec<i32>) {
     let mut vec: Vec<i32> = vec![];
-    let map = rosu_pp::Beatmap::from_path(path).unwrap();
-    let diff_attrs = rosu_pp::Difficulty::new().mods(0).calculate(&map);
+    let map = match rosu_pp::Beatmap::from_path(path) {
+        Ok(map) => map,
+        Err(e) => {
+            eprintln!("Impossible de lire la map {}: {}", path, e);
+            return (0.0, vec![0; 4]);
+        }
+    };
+    let diff_attrs = rosu_pp::Difficulty::new().mods(mods).calculate(&map);
     let stars = diff_attrs.stars();
 
     let perf_attrs = rosu_pp::Performance::new(diff_attrs)
+        .mods(mods)
         .accuracy(95.0)
         .calculate();
     vec.push(perf_attrs.pp() as i32);
 
-    let perf_attrs = perf_attrs.performance().accuracy(98.0).calculate();
+    let perf_attrs = perf_attrs.performance().mods(mods).accuracy(98.0).calculate();
     vec.push(perf_attrs.pp() as i32);
 
-    let perf_attrs = perf_attrs.performance().accuracy(99.0).calculate();
+    let perf_attrs = perf_attrs.performance().mods(mods).accuracy(99.0).calculate();
     vec.push(perf_attrs.pp() as i32);
 
-    vec.push(perf_attrs.performance().accuracy(100.0).calculate().pp() as i32);
+    vec.push(perf_attrs.performance().mods(mods).accuracy(100.0).calculate().pp() as i32);
+
+    (stars, vec)
+}
 
-    vec
+/// Whether osu! is running, followed by the reader or found among the processes
+///
+/// osu! rewrites its databases such as collection.db when it closes, files
+/// must not be modified while this returns true.
+pub fn osu_running() -> bool {
+    if ATTACHED.load(Ordering::Relaxed) {
+        return true;
+    }
+    #[cfg(not(target_os = "linux"))]
+    let process = Process::initialize("osu!.exe");
+    #[cfg(target_os = "linux")]
+    let process = linux::WineProcess::initialize("osu!.exe");
+    process.is_ok()
 }
 
-pub fn controlla(weak: Weak<LoginPage>) -> Result<()> {
-    let interval: Duration = Duration::from_millis(300);
+/// Reads osu! and publishes what happens in game on the event bus
+///
+/// # Arguments
+/// * `events` - Event bus shared with the subscribers
+pub fn controlla(events: EventBus) -> Result<()> {
     let mut state = State {
         addresses: StaticAddresses::default(),
+        inner: InnerValues::default(),
     };
 
-    if interval != Duration::from_millis(300) {
-        println!("Using non default interval: {}", interval.as_millis());
-    }
-
     'init_loop: loop {
-        let p = match Process::initialize("osu!.exe") {
+        #[cfg(not(target_os = "linux"))]
+        let process = Process::initialize("osu!.exe");
+        #[cfg(target_os = "linux")]
+        let process = linux::WineProcess::initialize("osu!.exe");
+        let p = match process {
             Ok(p) => p,
             Err(e) => {
+                ATTACHED.store(false, Ordering::Relaxed);
+                std::thread::sleep(Duration::from_millis(1000));
                 continue 'init_loop;
             }
         };
+        ATTACHED.store(true, Ordering::Relaxed);
 
         println!("Reading static signatures...");
         match StaticAddresses::new(&p) {
             Ok(v) => state.addresses = v,
-            Err(e) => match e.downcast_ref::<ProcessError>() {
-                Some(&ProcessError::ProcessNotFound) => {
-                    continue 'init_loop;
-                }
-                #[cfg(target_os = "windows")]
-                Some(&ProcessError::OsError { .. }) => {
-                    println!("{:?}", e);
-                    continue 'init_loop;
-                }
-                Some(_) | None => {
-                    println!("{:?}", e);
-                    continue 'init_loop;
-                }
-            },
+            Err(e) => {
+                println!("{:?}", e);
+                std::thread::sleep(Duration::from_millis(1000));
+                continue 'init_loop;
+            }
         };
 
         println!("Starting reading loop");
-        'main_loop: loop {
-            std::thread::sleep(Duration::from_millis(1000));
-            println!("Waiting For Play");
-            let weak_clone = weak.clone();
-            if let Err(e) = waiting_for_play(&p, &mut state, weak_clone) {
-                match e.downcast_ref::<ProcessError>() {
-                    Some(&ProcessError::ProcessNotFound) => {
-                        continue 'init_loop;
+        let record_path = std::env::var("COBRA_RECORD").ok();
+        let recorder = record_path.as_ref().map(|_| RecordingSource::new(&p));
+        let mut last_dump = Instant::now();
+        let mut machine = GameStateMachine::default();
+        loop {
+            let result = match &recorder {
+                Some(recorder) => machine.tick(recorder, &mut state),
+                None => machine.tick(&p, &mut state),
+            };
+
+            match result {
+                Ok(new_events) => {
+                    if let (Some(recorder), Some(path)) = (&recorder, &record_path) {
+                        if last_dump.elapsed() >= RECORD_DUMP_INTERVAL {
+                            dump_recording(recorder, path, &state.addresses);
+                            last_dump = Instant::now();
+                        }
                     }
-                    #[cfg(target_os = "windows")]
-                    Some(&ProcessError::OsError { .. }) => {
-                        println!("{:?}", e);
-                        continue 'init_loop;
+                    for event in new_events {
+                        events.emit(event);
                     }
-                    Some(_) | None => {
-                        println!("{:?}", e);
-                        continue 'main_loop;
+                }
+                Err(e) => {
+                    if recovery_for(&e) == Recovery::Reinitialize {
+                        if let (Some(recorder), Some(path)) = (&recorder, &record_path) {
+                            dump_recording(recorder, path, &state.addresses);
+                        }
+                        continue 'init_loop;
                     }
                 }
             }
-            println!("Playing");
-            std::thread::sleep(Duration::from_millis(700));
-            let a = playing(&p, &mut state);
-
-            // let a = wait_result_screen(&p, &mut state) && a;
+            thread::sleep(machine.poll_interval());
         }
     }
 }
 
-pub(crate) fn playing(p: &Process, state: &mut State) -> bool {
-    println!("Playing");
-    let mut cur_time = 0;
-    let mut last_time = 0;
-    let mut last_paused = 0;
-    let mut last_retries = 0;
-    let mode_list = get_mods(p, state);
-    while (GameState::from(get_status(p, state)) == GameState::Playing) {
-        cur_time = reader_gameplay::get_ig_time(p, state);
-        if (cur_time - last_time < 20 && cur_time > 0 && last_time > 0 && last_paused != cur_time) {
-            last_paused = cur_time;
-        }
-        last_time = cur_time;
-        let status = get_status(p, state);
-        let md5 = get_beatmap_md5(p, state);
-        if (last_retries < get_retries(p, state)) {
-            return false;
-        }
+/// Writes the reads recorded with `COBRA_RECORD` to the snapshot file
+fn dump_recording<M: MemorySource>(recorder: &RecordingSource<M>, path: &str, addresses: &StaticAddresses) {
+    if let Err(e) = recorder.dump(Path::new(path), addresses) {
+        eprintln!("Impossible d'enregistrer la session: {}", e);
     }
-    true
+}
+
+fn push_live_values(values: GameplayValues, current: f64, fc: f64, weak: Weak<LoginPage>) {
+    let text = format!("{:.0} / {:.0}", current, fc);
+    let _ = weak.upgrade_in_event_loop(move |handle| {
+        let app_state = handle.global::<AppState>();
+        app_state.set_live_accuracy((values.accuracy * 100.0) as f32);
+        app_state.set_live_hits(SharedString::from(format!(
+            "{} / {} / {} / {} / {} / {}",
+            values.hit._geki, values.hit._300, values.hit._katu,
+            values.hit._100, values.hit._50, values.hit._miss
+        )));
+        app_state.set_unstable_rate(values.unstable_rate as f32);
+        app_state.set_mean_offset(values.mean_offset as f32);
+        app_state.invoke_update_pp_window(SharedString::from(text));
+    });
+}
+
+pub(crate) fn push_hit_distribution(hit_errors: &HitErrors, weak: Weak<LoginPage>) {
+    let distribution: Vec<f32> = hit_errors
+        .distribution(HIT_DISTRIBUTION_RANGE, HIT_DISTRIBUTION_BUCKET)
+        .into_iter()
+        .map(|count| count as f32)
+        .collect();
+    let max = distribution.iter().cloned().fold(1.0f32, f32::max);
+    let unstable_rate = hit_errors.unstable_rate() as f32;
+    let mean = hit_errors.mean() as f32;
+    let _ = weak.upgrade_in_event_loop(move |handle| {
+        let app_state = handle.global::<AppState>();
+        app_state.set_hit_distribution(ModelRc::new(VecModel::from(distribution)));
+        app_state.set_hit_distribution_max(max);
+        app_state.set_unstable_rate(unstable_rate);
+        app_state.set_mean_offset(mean);
+    });
 }
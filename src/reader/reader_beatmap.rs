@@ -1,23 +1,26 @@
 use std::path::{Path, PathBuf};
 use image::ImageReader;
 use reqwest::get;
-use rosu_mem::process::{Process, ProcessTraits};
+use crate::reader::memory::MemorySource;
 use slint::{Image, SharedPixelBuffer, SharedString};
 use crate::MapData;
-use crate::reader::structs::State;
+use crate::library::InstallStatus;
+use crate::reader::error::ReaderError;
+use crate::reader::reader_common::read_ptr;
+use crate::reader::structs::{BeatmapInfo, State};
 
 
-pub(crate) fn read_from_beatmap_ptr_string(p: &Process, state: &mut State, offset: i32) -> eyre::Result<String>
+pub(crate) fn read_from_beatmap_ptr_string(p: &impl MemorySource, state: &mut State, offset: i32) -> eyre::Result<String>
 {
-    let beatmap_ptr = p.read_i32(state.addresses.base - 0xC)?;
-    let beatmap_addr = p.read_i32(beatmap_ptr)?;
+    let beatmap_ptr = read_ptr(p, state.addresses.base - 0xC)?;
+    let beatmap_addr = read_ptr(p, beatmap_ptr)?;
     Ok(p.read_string(beatmap_addr + offset)?)
 }
 
-pub(crate) fn read_multiple_from_beatmap_ptr_string(p: &Process, state: &mut State, offset: Vec<i32>) -> eyre::Result<Vec<String>>
+pub(crate) fn read_multiple_from_beatmap_ptr_string(p: &impl MemorySource, state: &mut State, offset: Vec<i32>) -> eyre::Result<Vec<String>>
 {
-    let beatmap_ptr = p.read_i32(state.addresses.base - 0xC)?;
-    let beatmap_addr = p.read_i32(beatmap_ptr)?;
+    let beatmap_ptr = read_ptr(p, state.addresses.base - 0xC)?;
+    let beatmap_addr = read_ptr(p, beatmap_ptr)?;
     let mut result = Vec::new();
     for offset in offset {
         result.push(p.read_string(beatmap_addr + offset)?);
@@ -25,36 +28,36 @@ pub(crate) fn read_multiple_from_beatmap_ptr_string(p: &Process, state: &mut Sta
     Ok(result)
 }
 
-pub(crate) fn get_beatmap_md5(p: &Process, state: &mut State) -> eyre::Result<String>
+pub(crate) fn get_beatmap_md5(p: &impl MemorySource, state: &mut State) -> eyre::Result<String>
 {
     Ok(read_from_beatmap_ptr_string(p,state,0x6c)?)
 }
 
 
-pub(crate) fn get_author(p: &Process, state: &mut State) -> eyre::Result<String>
+pub(crate) fn get_author(p: &impl MemorySource, state: &mut State) -> eyre::Result<String>
 {
     Ok(read_from_beatmap_ptr_string(p,state,0x18)?)
 }
 
-pub(crate) fn get_creator(p: &Process, state: &mut State) -> eyre::Result<String>
+pub(crate) fn get_creator(p: &impl MemorySource, state: &mut State) -> eyre::Result<String>
 {
     Ok(read_from_beatmap_ptr_string(p,state,0x7C)?)
 
 }
 
-pub(crate) fn get_title(p: &Process, state: &mut State) -> eyre::Result<String>
+pub(crate) fn get_title(p: &impl MemorySource, state: &mut State) -> eyre::Result<String>
 {
     Ok(read_from_beatmap_ptr_string(p,state,0x24)?)
 
 }
 
-pub(crate) fn get_difficulty(p: &Process, state: &mut State) -> eyre::Result<String>
+pub(crate) fn get_difficulty(p: &impl MemorySource, state: &mut State) -> eyre::Result<String>
 {
     Ok(read_from_beatmap_ptr_string(p,state,0xAC)?)
 
 }
 
-pub(crate) fn get_beatmap(p: &Process, state: &mut State) -> eyre::Result<MapData>
+pub(crate) fn get_beatmap(p: &impl MemorySource, state: &mut State) -> eyre::Result<MapData>
 {
     let mut vec = Vec::new();
     vec.push(0x18); // author
@@ -72,6 +75,7 @@ pub(crate) fn get_beatmap(p: &Process, state: &mut State) -> eyre::Result<MapDat
         song : SharedString::from(vec[3].clone()),
         md5 : SharedString::from(vec[4].clone()),
         download_progress: 0.0,
+        install_status: InstallStatus::Installed.as_ui(),
         link : SharedString::from(String::new()),
     })
 
@@ -79,31 +83,56 @@ pub(crate) fn get_beatmap(p: &Process, state: &mut State) -> eyre::Result<MapDat
 
 
 
-pub(crate) fn get_path_folder(p: &Process, state: &mut State) -> eyre::Result<String> {
+/// Reads the selected beatmap with its file paths
+///
+/// # Arguments
+/// * `p` - Process handle for osu!
+/// * `state` - Current state containing memory addresses
+///
+/// # Returns
+/// Metadata and paths of the beatmap
+pub(crate) fn get_beatmap_info(p: &impl MemorySource, state: &mut State) -> eyre::Result<BeatmapInfo>
+{
+    let values = read_multiple_from_beatmap_ptr_string(p, state, vec![0x6c, 0x24, 0x18, 0x7C, 0xAC])?;
+    Ok(BeatmapInfo {
+        md5: values[0].clone(),
+        song: values[1].clone(),
+        author: values[2].clone(),
+        creator: values[3].clone(),
+        difficulty: values[4].clone(),
+        path: get_beatmap_path(p, state)?,
+        cover_path: get_cover_path(p, state)?,
+        audio_path: get_audio_path(p, state)?,
+    })
+}
+
+pub(crate) fn get_path_folder(p: &impl MemorySource, state: &mut State) -> eyre::Result<String> {
 
-    let settings_ptr = p.read_i32(state.addresses.settings+0x8)?;
-    let settings_addr = p.read_i32(settings_ptr+0xb8)?;
+    let settings_ptr = read_ptr(p, state.addresses.settings+0x8)?;
+    let settings_addr = read_ptr(p, settings_ptr+0xb8)?;
     let path = (p.read_string(settings_addr+0x4)?);
     println!("{:?}", path.clone());
     if path == "Songs" {
-        return Ok(format!("{}/Songs", p.executable_dir.clone().unwrap().display()));
+        let executable_dir = p.executable_dir()
+            .ok_or_else(|| ReaderError::StaleState("osu! executable directory is unknown".to_string()))?;
+        return Ok(format!("{}/Songs", executable_dir.display()));
     }
-    Ok(path)
+    Ok(p.translate_path(&path))
 }
 
-pub(crate) fn get_folder(p: &Process, state: &mut State) -> eyre::Result<String> {
-    let beatmap_ptr = p.read_i32(state.addresses.base - 0xC)?;
-    let beatmap_addr = p.read_i32(beatmap_ptr)?;
+pub(crate) fn get_folder(p: &impl MemorySource, state: &mut State) -> eyre::Result<String> {
+    let beatmap_ptr = read_ptr(p, state.addresses.base - 0xC)?;
+    let beatmap_addr = read_ptr(p, beatmap_ptr)?;
     Ok(p.read_string(beatmap_addr + 0x78)?)
 
 
 }
-pub(crate) fn get_filename(p: &Process, state: &mut State) -> eyre::Result<String> {
-    let beatmap_ptr = p.read_i32(state.addresses.base - 0xC)?;
-    let beatmap_addr = p.read_i32(beatmap_ptr)?;
+pub(crate) fn get_filename(p: &impl MemorySource, state: &mut State) -> eyre::Result<String> {
+    let beatmap_ptr = read_ptr(p, state.addresses.base - 0xC)?;
+    let beatmap_addr = read_ptr(p, beatmap_ptr)?;
     Ok(p.read_string(beatmap_addr + 0x90)?)
 }
-pub(crate) fn get_beatmap_path(p: &Process, state: &mut State) -> eyre::Result<String>
+pub(crate) fn get_beatmap_path(p: &impl MemorySource, state: &mut State) -> eyre::Result<String>
 {
     let path = get_path_folder(p,state)?;
     let song_path = Path::new(&path);
@@ -112,7 +141,7 @@ pub(crate) fn get_beatmap_path(p: &Process, state: &mut State) -> eyre::Result<S
     Ok(song_path.display().to_string())
 }
 
-pub(crate) fn get_audio_path(p: &Process, state: &mut State) -> eyre::Result<String>
+pub(crate) fn get_audio_path(p: &impl MemorySource, state: &mut State) -> eyre::Result<String>
 {
     let path = get_path_folder(p, state)?;
     let song_path = Path::new(&path);
@@ -121,12 +150,12 @@ pub(crate) fn get_audio_path(p: &Process, state: &mut State) -> eyre::Result<Str
     Ok(song_path.display().to_string())
 }
 
-pub(crate) fn get_beatmap_cover(p: &Process, state: &mut State) -> eyre::Result<String> {
-    let beatmap_ptr = p.read_i32(state.addresses.base - 0xC)?;
-    let beatmap_addr = p.read_i32(beatmap_ptr)?;
+pub(crate) fn get_beatmap_cover(p: &impl MemorySource, state: &mut State) -> eyre::Result<String> {
+    let beatmap_ptr = read_ptr(p, state.addresses.base - 0xC)?;
+    let beatmap_addr = read_ptr(p, beatmap_ptr)?;
     Ok(p.read_string(beatmap_addr + 0x68)?)
 }
-pub(crate) fn get_cover_path(p: &Process, state: &mut State) -> eyre::Result<String> {
+pub(crate) fn get_cover_path(p: &impl MemorySource, state: &mut State) -> eyre::Result<String> {
     let path = get_path_folder(p, state)?;
     let song_path = Path::new(&path);
     let song_path = song_path.join(get_folder(p, state)?);
@@ -1,7 +1,45 @@
-use rosu_mem::process::{Process, ProcessTraits};
+use crate::reader::memory::MemorySource;
+use crate::reader::error::ReaderError;
 use crate::reader::structs::State;
 
-pub(crate) fn get_status(p: &Process, state: &mut State) -> u32 {
-    let status_ptr = p.read_i32(state.addresses.status - 0x4).unwrap();
-    p.read_u32(status_ptr).unwrap()
+/// Reads a pointer from memory, failing on null pointers instead of reading garbage
+///
+/// # Arguments
+/// * `p` - Process handle for osu!
+/// * `addr` - Address of the pointer
+pub(crate) fn read_ptr(p: &impl MemorySource, addr: i32) -> eyre::Result<i32> {
+    let ptr = p.read_i32(addr)?;
+    if ptr == 0 {
+        return Err(ReaderError::NullPointer { addr }.into());
+    }
+    Ok(ptr)
+}
+
+/// Resolves the address of the current ruleset object
+///
+/// # Arguments
+/// * `p` - Process handle for osu!
+/// * `state` - Current state containing memory addresses
+pub(crate) fn get_ruleset_addr(p: &impl MemorySource, state: &mut State) -> eyre::Result<i32> {
+    let ruleset_addr = read_ptr(p, state.addresses.rulesets - 0xb)?;
+    read_ptr(p, ruleset_addr + 0x4)
+}
+
+pub(crate) fn get_status(p: &impl MemorySource, state: &mut State) -> eyre::Result<u32> {
+    let status_ptr = read_ptr(p, state.addresses.status - 0x4)?;
+    Ok(p.read_u32(status_ptr)?)
+}
+
+
+/// Reads the mods selected in song select from memory
+///
+/// # Arguments
+/// * `p` - Process handle for osu!
+/// * `state` - Current state containing memory addresses
+///
+/// # Returns
+/// Selected mods as u32 bitmask
+pub(crate) fn get_menu_mods(p: &impl MemorySource, state: &mut State) -> eyre::Result<u32> {
+    let menu_mods_ptr = read_ptr(p, state.addresses.menu_mods + 0x9)?;
+    Ok(p.read_u32(menu_mods_ptr)?)
 }
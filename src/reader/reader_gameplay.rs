@@ -1,5 +1,7 @@
-use rosu_mem::process::{Process, ProcessTraits};
-use crate::reader::structs::State;
+use crate::reader::memory::MemorySource;
+use crate::reader::reader_common::{get_ruleset_addr, read_ptr};
+use crate::reader::structs::{GameplayValues, Hit, State};
+use crate::utils::scoring::accuracy_v1;
 
 /// Reads the current score during gameplay from memory
 ///
@@ -9,12 +11,11 @@ use crate::reader::structs::State;
 ///
 /// # Returns
 /// Current score as i32
-pub fn get_score_gameplay(p: &Process, state: &mut State) -> i32 {
-    let ruleset_addr = p.read_i32(state.addresses.rulesets - 0xb).unwrap();
-    let ruleset_addr = p.read_i32(ruleset_addr + 0x4).unwrap();
-    let gameplay_base = p.read_i32(ruleset_addr + 0x68).unwrap();
-    let score_base = p.read_i32(gameplay_base + 0x38).unwrap();
-    p.read_i32(score_base + 0x78).unwrap()
+pub fn get_score_gameplay(p: &impl MemorySource, state: &mut State) -> eyre::Result<i32> {
+    let ruleset_addr = get_ruleset_addr(p, state)?;
+    let gameplay_base = read_ptr(p, ruleset_addr + 0x68)?;
+    let score_base = read_ptr(p, gameplay_base + 0x38)?;
+    Ok(p.read_i32(score_base + 0x78)?)
 }
 
 /// Reads currently active mods from memory
@@ -25,14 +26,13 @@ pub fn get_score_gameplay(p: &Process, state: &mut State) -> i32 {
 ///
 /// # Returns
 /// Active mods as u32 bitmask
-pub fn get_mods(p: &Process, state: &mut State) -> u32 {
-    let ruleset_addr = p.read_i32(state.addresses.rulesets - 0xb).unwrap();
-    let ruleset_addr = p.read_i32(ruleset_addr + 0x4).unwrap();
-    let resultscreen_base = p.read_i32(ruleset_addr + 0x38).unwrap();
-    let mods_xor_base = p.read_i32(resultscreen_base + 0x1C).unwrap();
-    let mods_xor1 = p.read_u64(mods_xor_base + 0xc).unwrap();
-    let mods_xor2 =  p.read_u64(mods_xor_base + 0x8).unwrap();
-    (mods_xor1 ^ mods_xor2) as u32
+pub fn get_mods(p: &impl MemorySource, state: &mut State) -> eyre::Result<u32> {
+    let ruleset_addr = get_ruleset_addr(p, state)?;
+    let resultscreen_base = read_ptr(p, ruleset_addr + 0x38)?;
+    let mods_xor_base = read_ptr(p, resultscreen_base + 0x1C)?;
+    let mods_xor1 = p.read_u64(mods_xor_base + 0xc)?;
+    let mods_xor2 =  p.read_u64(mods_xor_base + 0x8)?;
+    Ok((mods_xor1 ^ mods_xor2) as u32)
 }
 
 /// Reads current combo from memory
@@ -43,12 +43,11 @@ pub fn get_mods(p: &Process, state: &mut State) -> u32 {
 ///
 /// # Returns
 /// Current combo as i16
-pub fn get_combo(p: &Process, state: &mut State) -> i16 {
-    let ruleset_addr = p.read_i32(state.addresses.rulesets - 0xb).unwrap();
-    let ruleset_addr = p.read_i32(ruleset_addr + 0x4).unwrap();
-    let gameplay_base = p.read_i32(ruleset_addr + 0x68).unwrap();
-    let score_base = p.read_i32(gameplay_base + 0x38).unwrap();
-    p.read_i16(score_base + 0x94).unwrap()
+pub fn get_combo(p: &impl MemorySource, state: &mut State) -> eyre::Result<i16> {
+    let ruleset_addr = get_ruleset_addr(p, state)?;
+    let gameplay_base = read_ptr(p, ruleset_addr + 0x68)?;
+    let score_base = read_ptr(p, gameplay_base + 0x38)?;
+    Ok(p.read_i16(score_base + 0x94)?)
 }
 
 /// Reads maximum combo achieved in current play from memory
@@ -59,14 +58,61 @@ pub fn get_combo(p: &Process, state: &mut State) -> i16 {
 ///
 /// # Returns
 /// Maximum combo as i16
-pub fn get_max_combo(p: &Process, state: &mut State) -> i16 {
-    let ruleset_addr = p.read_i32(state.addresses.rulesets - 0xb).unwrap();
-    let ruleset_addr = p.read_i32(ruleset_addr + 0x4).unwrap();
-    let gameplay_base = p.read_i32(ruleset_addr + 0x68).unwrap();
-    let score_base = p.read_i32(gameplay_base + 0x38).unwrap();
-    p.read_i16(score_base + 0x68).unwrap()
+pub fn get_max_combo(p: &impl MemorySource, state: &mut State) -> eyre::Result<i16> {
+    let ruleset_addr = get_ruleset_addr(p, state)?;
+    let gameplay_base = read_ptr(p, ruleset_addr + 0x68)?;
+    let score_base = read_ptr(p, gameplay_base + 0x38)?;
+    Ok(p.read_i16(score_base + 0x68)?)
 }
 
+/// Reads the judgements of the current play from memory
+///
+/// # Arguments
+/// * `p` - Process handle for osu!
+/// * `state` - Current state containing memory addresses
+///
+/// # Returns
+/// Hit counts of the current play
+pub fn get_hits(p: &impl MemorySource, state: &mut State) -> eyre::Result<Hit> {
+    let ruleset_addr = get_ruleset_addr(p, state)?;
+    let gameplay_base = read_ptr(p, ruleset_addr + 0x68)?;
+    let score_base = read_ptr(p, gameplay_base + 0x38)?;
+    Ok(Hit {
+        _300: p.read_i16(score_base + 0x8A)?,
+        _100: p.read_i16(score_base + 0x88)?,
+        _50: p.read_i16(score_base + 0x8C)?,
+        _miss: p.read_i16(score_base + 0x92)?,
+        _geki: p.read_i16(score_base + 0x8E)?,
+        _katu: p.read_i16(score_base + 0x90)?,
+    })
+}
+
+/// Reads the hit errors recorded in the score object, starting at a given index
+///
+/// osu! appends an offset (in ms, negative when early) for every judged note,
+/// reading from the last known index avoids copying the whole list every tick.
+///
+/// # Arguments
+/// * `p` - Process handle for osu!
+/// * `state` - Current state containing memory addresses
+/// * `from` - Index of the first hit error to read
+///
+/// # Returns
+/// Hit errors from `from` to the end of the list
+pub fn get_hit_errors(p: &impl MemorySource, state: &mut State, from: usize) -> eyre::Result<Vec<i32>> {
+    let ruleset_addr = get_ruleset_addr(p, state)?;
+    let gameplay_base = read_ptr(p, ruleset_addr + 0x68)?;
+    let score_base = read_ptr(p, gameplay_base + 0x38)?;
+    let hit_errors_base = read_ptr(p, score_base + 0x38)?;
+    let items_addr = read_ptr(p, hit_errors_base + 0x4)?;
+    let size = p.read_i32(hit_errors_base + 0xC)?.max(0) as usize;
+
+    let mut hit_errors = Vec::with_capacity(size.saturating_sub(from));
+    for i in from..size {
+        hit_errors.push(p.read_i32(items_addr + 0x8 + 0x4 * i as i32)?);
+    }
+    Ok(hit_errors)
+}
 
 /// Reads current HP value from memory
 ///
@@ -76,12 +122,11 @@ pub fn get_max_combo(p: &Process, state: &mut State) -> i16 {
 ///
 /// # Returns
 /// Current HP as f64 (0.0 to 1.0)
-pub fn get_current_hp(p: &Process, state: &mut State) -> f64 {
-    let ruleset_addr = p.read_i32(state.addresses.rulesets - 0xb).unwrap();
-    let ruleset_addr = p.read_i32(ruleset_addr + 0x4).unwrap();
-    let gameplay_base = p.read_i32(ruleset_addr + 0x68).unwrap();
-    let hp_base = p.read_i32(gameplay_base + 0x40).unwrap();
-    p.read_f64(hp_base + 0x1C).unwrap()
+pub fn get_current_hp(p: &impl MemorySource, state: &mut State) -> eyre::Result<f64> {
+    let ruleset_addr = get_ruleset_addr(p, state)?;
+    let gameplay_base = read_ptr(p, ruleset_addr + 0x68)?;
+    let hp_base = read_ptr(p, gameplay_base + 0x40)?;
+    Ok(p.read_f64(hp_base + 0x1C)?)
 }
 
 /// Reads current player username from memory
@@ -92,15 +137,13 @@ pub fn get_current_hp(p: &Process, state: &mut State) -> f64 {
 ///
 /// # Returns
 /// Username as String
-pub fn get_username(p: &Process, state: &mut State) -> String {
-    let ruleset_addr = p.read_i32(state.addresses.rulesets - 0xb).unwrap();
-    let ruleset_addr = p.read_i32(ruleset_addr + 0x4).unwrap();
-    let gameplay_base = p.read_i32(ruleset_addr + 0x68).unwrap();
-    let score_base = p.read_i32(gameplay_base + 0x38).unwrap();
-    p.read_string(score_base + 0x28).unwrap()
+pub fn get_username(p: &impl MemorySource, state: &mut State) -> eyre::Result<String> {
+    let ruleset_addr = get_ruleset_addr(p, state)?;
+    let gameplay_base = read_ptr(p, ruleset_addr + 0x68)?;
+    let score_base = read_ptr(p, gameplay_base + 0x38)?;
+    Ok(p.read_string(score_base + 0x28)?)
 }
 
-
 /// Reads in-game time (play time) from memory
 ///
 /// # Arguments
@@ -109,9 +152,9 @@ pub fn get_username(p: &Process, state: &mut State) -> String {
 ///
 /// # Returns
 /// In-game time in milliseconds as i32
-pub fn get_ig_time(p: &Process, state: &mut State) -> i32 {
-    let playtime_ptr = p.read_i32(state.addresses.playtime + 0x5).unwrap();
-    p.read_i32(playtime_ptr).unwrap()
+pub fn get_ig_time(p: &impl MemorySource, state: &mut State) -> eyre::Result<i32> {
+    let playtime_ptr = read_ptr(p, state.addresses.playtime + 0x5)?;
+    Ok(p.read_i32(playtime_ptr)?)
 }
 
 /// Reads number of retries in current session from memory
@@ -122,7 +165,36 @@ pub fn get_ig_time(p: &Process, state: &mut State) -> i32 {
 ///
 /// # Returns
 /// Number of retries as i32
-pub fn get_retries(p: &Process, state: &mut State) -> i32 {
-    let igt_addr = p.read_i32(state.addresses.base - 0x33).unwrap();
-    p.read_i32(igt_addr+0x8).unwrap()
-}
\ No newline at end of file
+pub fn get_retries(p: &impl MemorySource, state: &mut State) -> eyre::Result<i32> {
+    let igt_addr = read_ptr(p, state.addresses.base - 0x33)?;
+    Ok(p.read_i32(igt_addr+0x8)?)
+}
+
+/// Reads every gameplay value of the current play at once
+///
+/// # Arguments
+/// * `p` - Process handle for osu!
+/// * `state` - Current state containing memory addresses
+pub fn get_gameplay(p: &impl MemorySource, state: &mut State) -> eyre::Result<GameplayValues> {
+    let ruleset_addr = get_ruleset_addr(p, state)?;
+    let gameplay_base = read_ptr(p, ruleset_addr + 0x68)?;
+    let score_base = read_ptr(p, gameplay_base + 0x38)?;
+    let hp_base = read_ptr(p, gameplay_base + 0x40)?;
+    let hit_errors_base = read_ptr(p, score_base + 0x38)?;
+    let mode = p.read_i32(score_base + 0x64)? as u8;
+    let hit = get_hits(p, state)?;
+
+    Ok(GameplayValues {
+        username: p.read_string(score_base + 0x28)?,
+        mode,
+        score: p.read_i32(score_base + 0x78)?,
+        combo: p.read_i16(score_base + 0x94)?,
+        max_combo: p.read_i16(score_base + 0x68)?,
+        hp: p.read_f64(hp_base + 0x1C)?,
+        mods: get_mods(p, state)?,
+        accuracy: accuracy_v1(mode, &hit)?,
+        hit,
+        hit_errors_count: p.read_i32(hit_errors_base + 0xC)?,
+        ..Default::default()
+    })
+}
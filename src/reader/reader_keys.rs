@@ -0,0 +1,102 @@
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+use crate::reader::memory::MemorySource;
+use crate::reader::reader_common::{get_ruleset_addr, read_ptr};
+use crate::reader::structs::{KeyState, KeyStats, State};
+
+/// Names of the buttons of the osu! key overlay, in memory order
+const KEY_OVERLAY_NAMES: [&str; 4] = ["K1", "K2", "M1", "M2"];
+/// Window used to compute the keys per second
+const KPS_WINDOW: Duration = Duration::from_secs(1);
+
+/// Reads the buttons of the key overlay (K1, K2, M1, M2)
+///
+/// osu! only allocates the key overlay in osu!, taiko and catch, reading it
+/// in mania returns a null pointer error.
+///
+/// # Arguments
+/// * `p` - Process handle for osu!
+/// * `state` - Current state containing memory addresses
+///
+/// # Returns
+/// State and press count of each button
+pub fn get_key_overlay(p: &impl MemorySource, state: &mut State) -> eyre::Result<Vec<KeyState>> {
+    let ruleset_addr = get_ruleset_addr(p, state)?;
+    let key_overlay_base = read_ptr(p, ruleset_addr + 0xB0)?;
+    let buttons_list = read_ptr(p, key_overlay_base + 0x10)?;
+    let buttons_addr = read_ptr(p, buttons_list + 0x4)?;
+
+    let mut keys = Vec::with_capacity(KEY_OVERLAY_NAMES.len());
+    for (i, name) in KEY_OVERLAY_NAMES.iter().enumerate() {
+        let button_addr = read_ptr(p, buttons_addr + 0x8 + 0x4 * i as i32)?;
+        keys.push(KeyState {
+            name: name.to_string(),
+            pressed: p.read_u8(button_addr + 0x1C)? != 0,
+            count: p.read_i32(button_addr + 0x14)?.max(0) as u32,
+        });
+    }
+    Ok(keys)
+}
+
+/// Counts the presses of each key and the keys per second of a play
+#[derive(Debug, Clone, Default)]
+pub struct KeyTracker {
+    keys: Vec<KeyState>,
+    presses: VecDeque<Instant>,
+}
+
+impl KeyTracker {
+    pub fn new(names: &[String]) -> Self {
+        Self {
+            keys: names
+                .iter()
+                .map(|name| KeyState { name: name.clone(), ..Default::default() })
+                .collect(),
+            presses: VecDeque::new(),
+        }
+    }
+
+    /// Updates the keys from their current pressed state, counting new presses
+    ///
+    /// # Arguments
+    /// * `pressed` - Whether each key is held, in the order of the names
+    /// * `now` - Time of the snapshot
+    pub fn update_pressed(&mut self, pressed: &[bool], now: Instant) {
+        for (key, &down) in self.keys.iter_mut().zip(pressed) {
+            if down && !key.pressed {
+                key.count += 1;
+                self.presses.push_back(now);
+            }
+            key.pressed = down;
+        }
+    }
+
+    /// Updates the keys from counters kept by osu!, such as the key overlay
+    ///
+    /// # Arguments
+    /// * `keys` - Keys read from memory, replacing the tracked ones
+    /// * `now` - Time of the snapshot
+    pub fn update_counts(&mut self, keys: &[KeyState], now: Instant) {
+        let previous: u32 = self.keys.iter().map(|key| key.count).sum();
+        let current: u32 = keys.iter().map(|key| key.count).sum();
+        // The counters go back to 0 on retry
+        for _ in 0..current.saturating_sub(previous) {
+            self.presses.push_back(now);
+        }
+        self.keys = keys.to_vec();
+    }
+
+    /// Current keys and keys per second
+    pub fn stats(&mut self, now: Instant) -> KeyStats {
+        while let Some(&first) = self.presses.front() {
+            if now.duration_since(first) <= KPS_WINDOW {
+                break;
+            }
+            self.presses.pop_front();
+        }
+        KeyStats {
+            keys: self.keys.clone(),
+            kps: self.presses.len() as f64 / KPS_WINDOW.as_secs_f64(),
+        }
+    }
+}
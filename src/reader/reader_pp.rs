@@ -0,0 +1,111 @@
+use rosu_pp::any::ScoreState;
+use crate::utils::modes::MANIA_MODE;
+use crate::reader::structs::{Hit, InnerValues};
+
+fn score_state(hit: &Hit, max_combo: i16) -> ScoreState {
+    ScoreState {
+        max_combo: max_combo.max(0) as u32,
+        n_geki: hit._geki.max(0) as u32,
+        n_katu: hit._katu.max(0) as u32,
+        n300: hit._300.max(0) as u32,
+        n100: hit._100.max(0) as u32,
+        n50: hit._50.max(0) as u32,
+        misses: hit._miss.max(0) as u32,
+        ..ScoreState::default()
+    }
+}
+
+/// Number of objects judged so far
+///
+/// Gekis and katus are judgements of their own in mania only, in the other modes
+/// they are counted again in the 300s and 100s.
+pub(super) fn judged_objects(hit: &Hit, mode: u8) -> u32 {
+    let judgements: &[i16] = if mode == MANIA_MODE {
+        &[hit._geki, hit._300, hit._katu, hit._100, hit._50, hit._miss]
+    } else {
+        &[hit._300, hit._100, hit._50, hit._miss]
+    };
+    judgements.iter().map(|&n| n.max(0) as u32).sum()
+}
+
+/// Judgements of the play if every miss and remaining object were perfect
+///
+/// # Arguments
+/// * `state` - Judgements so far
+/// * `mode` - Mode of the map
+/// * `remaining` - Objects not judged yet
+pub(super) fn full_combo_state(state: &ScoreState, mode: u8, remaining: u32) -> ScoreState {
+    let mut fc = ScoreState { misses: 0, ..state.clone() };
+    if mode == MANIA_MODE {
+        fc.n_geki += state.misses + remaining;
+    } else {
+        fc.n300 += state.misses + remaining;
+    }
+    fc
+}
+
+impl InnerValues {
+    /// Prepares the gradual pp calculation for a new play
+    ///
+    /// # Arguments
+    /// * `path` - Path of the .osu file being played
+    /// * `mods` - Mods bitmask of the play
+    pub fn init_pp(&mut self, path: &str, mods: u32) -> eyre::Result<()> {
+        self.reset();
+        let map = rosu_pp::Beatmap::from_path(path)
+            .map_err(|e| eyre::eyre!("Impossible de lire la map {}: {}", path, e))?;
+
+        let difficulty = rosu_pp::Difficulty::new().mods(mods);
+        let diff_attrs = difficulty.calculate(&map);
+
+        self.current_beatmap_perf = Some(
+            rosu_pp::Performance::new(diff_attrs)
+                .mods(mods)
+                .calculate()
+        );
+        self.gradual_performance_current = Some(difficulty.gradual_performance(&map));
+        self.mods = mods;
+        self.mode = map.mode as u8;
+        self.n_objects = map.hit_objects.len() as u32;
+        Ok(())
+    }
+
+    /// Feeds the judgements of the current play and returns (current pp, if-FC pp)
+    ///
+    /// Returns `None` if nothing was judged since the last call, or if no map
+    /// has been prepared with `init_pp`.
+    ///
+    /// # Arguments
+    /// * `hit` - Judgements read during gameplay
+    /// * `max_combo` - Highest combo reached so far
+    pub fn update_pp(&mut self, hit: &Hit, max_combo: i16) -> Option<(f64, f64)> {
+        let judged = judged_objects(hit, self.mode);
+        if judged <= self.passed_objects {
+            return None;
+        }
+
+        let state = score_state(hit, max_combo);
+        let gradual = self.gradual_performance_current.as_mut()?;
+        let current = gradual
+            .nth(state.clone(), (judged - self.passed_objects - 1) as usize)
+            .map(|attrs| attrs.pp())
+            .unwrap_or(0.0);
+        self.passed_objects = judged;
+
+        let full = self.current_beatmap_perf.as_ref()?;
+        let fc_state = full_combo_state(&state, self.mode, self.n_objects.saturating_sub(judged));
+        let fc = full
+            .performance()
+            .mods(self.mods)
+            .n_geki(fc_state.n_geki)
+            .n_katu(fc_state.n_katu)
+            .n300(fc_state.n300)
+            .n100(fc_state.n100)
+            .n50(fc_state.n50)
+            .misses(0)
+            .calculate()
+            .pp();
+
+        Some((current, fc))
+    }
+}
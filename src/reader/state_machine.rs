@@ -0,0 +1,231 @@
+use std::time::{Duration, Instant};
+use crate::reader::error::{recovery_for, Recovery};
+use crate::reader::events::GameEvent;
+use crate::reader::input::ManiaInput;
+use crate::reader::memory::MemorySource;
+use crate::reader::reader_beatmap::{get_beatmap_info, get_beatmap_md5, get_path_folder};
+use crate::reader::reader_common::{get_menu_mods, get_status};
+use crate::reader::reader_gameplay::{get_gameplay, get_hit_errors, get_ig_time, get_mods, get_retries};
+use crate::reader::reader_keys::{get_key_overlay, KeyTracker};
+use crate::reader::reader_resultscreen::get_result_screen;
+use crate::reader::structs::{BeatmapInfo, GameState, GameplayValues, KeyStats, State};
+use crate::utils::modes::MANIA_MODE;
+use crate::utils::mods::NO_FAIL;
+
+/// Delay between two ticks outside of gameplay
+const MENU_INTERVAL: Duration = Duration::from_millis(300);
+/// Delay between two ticks during gameplay
+const PLAYING_INTERVAL: Duration = Duration::from_millis(50);
+
+/// Follows the osu! screens and turns memory reads into `GameEvent`s
+#[derive(Debug, Default)]
+pub struct GameStateMachine {
+    status: GameState,
+    map: Option<BeatmapInfo>,
+    mods: u32,
+    retries: i32,
+    last_time: i32,
+    paused: bool,
+    failed: bool,
+    last_values: Option<GameplayValues>,
+    key_tracker: KeyTracker,
+    mania_input: Option<ManiaInput>,
+    /// Whether the keyboard was already set up for this play, even if it failed
+    mania_input_started: bool,
+    songs_folder: Option<String>,
+}
+
+impl GameStateMachine {
+    /// Current screen as seen by the last successful tick
+    pub fn status(&self) -> GameState {
+        self.status
+    }
+
+    /// Delay to wait before the next tick, shorter during gameplay
+    pub fn poll_interval(&self) -> Duration {
+        match self.status {
+            GameState::Playing => PLAYING_INTERVAL,
+            _ => MENU_INTERVAL,
+        }
+    }
+
+    /// Reads osu! once and returns the events since the previous tick
+    ///
+    /// When the transition fails the machine is left unchanged and an error is returned,
+    /// so the same transition is attempted again on the next tick. Once the transition is
+    /// applied, a failed read of the new screen only delays its events to the next tick.
+    ///
+    /// # Arguments
+    /// * `p` - Process handle for osu!
+    /// * `state` - Current state containing memory addresses
+    pub fn tick(&mut self, p: &impl MemorySource, state: &mut State) -> eyre::Result<Vec<GameEvent>> {
+        let status = GameState::from(get_status(p, state)?);
+        let mut events = Vec::new();
+        let changed = status != self.status;
+
+        if changed {
+            let from = self.status;
+            self.on_transition(p, state, from, status, &mut events)?;
+            events.insert(0, GameEvent::StateChanged { from, to: status });
+            self.status = status;
+        }
+
+        let read = match status {
+            GameState::Playing => self.tick_playing(p, state, &mut events),
+            GameState::ResultScreen | GameState::MultiplayerResultScreen => Ok(()),
+            _ => self.tick_menu(p, state, &mut events),
+        };
+        match read {
+            // The transition is already applied, its events must not be lost with the read
+            Err(e) if !changed || recovery_for(&e) == Recovery::Reinitialize => return Err(e),
+            _ => {}
+        }
+
+        if self.songs_folder.is_none() {
+            if let Ok(songs) = get_path_folder(p, state) {
+                self.songs_folder = Some(songs.clone());
+                let game = p.executable_dir().map(|dir| dir.display().to_string());
+                events.push(GameEvent::FoldersFound { game, songs });
+            }
+        }
+        Ok(events)
+    }
+
+    fn on_transition(
+        &mut self,
+        p: &impl MemorySource,
+        state: &mut State,
+        from: GameState,
+        to: GameState,
+        events: &mut Vec<GameEvent>,
+    ) -> eyre::Result<()> {
+        if to == GameState::Playing {
+            self.start_play(p, state, events)?;
+        } else if from == GameState::Playing {
+            let hit_errors = state.inner.hit_errors.clone();
+            if to == GameState::ResultScreen || to == GameState::MultiplayerResultScreen {
+                let mut result = get_result_screen(p, state)?;
+                let values = self.last_values.take().unwrap_or_default();
+                result.key_counts = values.keys.keys.iter().map(|key| key.count).collect();
+                events.push(GameEvent::PlayFinished { values, hit_errors });
+                let map = self.map.clone().unwrap_or_default();
+                events.push(GameEvent::ResultScreenShown { map, result });
+            } else {
+                self.last_values = None;
+                events.push(GameEvent::PlayQuit { hit_errors });
+            }
+            self.mania_input = None;
+        }
+        Ok(())
+    }
+
+    fn start_play(&mut self, p: &impl MemorySource, state: &mut State, events: &mut Vec<GameEvent>) -> eyre::Result<()> {
+        let map = get_beatmap_info(p, state)?;
+        let mods = get_mods(p, state)?;
+        self.retries = get_retries(p, state)?;
+        self.last_time = 0;
+        self.paused = false;
+        self.failed = false;
+        self.last_values = None;
+        self.key_tracker = KeyTracker::default();
+        self.mania_input = None;
+        self.mania_input_started = false;
+        // The pp are computed here once for every subscriber, a play without them is still followed
+        if let Err(e) = state.inner.init_pp(&map.path, mods) {
+            eprintln!("Erreur lors de l'initialisation du pp: {}", e);
+        }
+
+        self.map = Some(map.clone());
+        self.mods = mods;
+        events.push(GameEvent::PlayStarted { map, mods });
+        Ok(())
+    }
+
+    fn tick_menu(&mut self, p: &impl MemorySource, state: &mut State, events: &mut Vec<GameEvent>) -> eyre::Result<()> {
+        let mods = get_menu_mods(p, state)?;
+        let md5 = get_beatmap_md5(p, state)?;
+
+        match &self.map {
+            Some(map) if map.md5 == md5 => {
+                if mods != self.mods {
+                    self.mods = mods;
+                    events.push(GameEvent::ModsChanged { map: map.clone(), mods });
+                }
+            }
+            _ => {
+                let map = get_beatmap_info(p, state)?;
+                self.map = Some(map.clone());
+                self.mods = mods;
+                events.push(GameEvent::MapChanged { map, mods });
+            }
+        }
+        Ok(())
+    }
+
+    fn tick_playing(&mut self, p: &impl MemorySource, state: &mut State, events: &mut Vec<GameEvent>) -> eyre::Result<()> {
+        let retries = get_retries(p, state)?;
+        if retries > self.retries {
+            events.push(GameEvent::Retry);
+            return self.start_play(p, state, events);
+        }
+
+        let mut values = match get_gameplay(p, state) {
+            Ok(values) => values,
+            // The score object is not allocated yet while the map is loading
+            Err(e) if recovery_for(&e) == Recovery::Retry => return Ok(()),
+            Err(e) => return Err(e),
+        };
+
+        if (values.hit_errors_count as usize) < state.inner.hit_errors.errors.len() {
+            state.inner.hit_errors.clear();
+        }
+        let known_errors = state.inner.hit_errors.errors.len();
+        let new_errors = get_hit_errors(p, state, known_errors).unwrap_or_default();
+        state.inner.hit_errors.errors.extend_from_slice(&new_errors);
+        values.new_hit_errors = new_errors;
+        values.unstable_rate = state.inner.hit_errors.unstable_rate();
+        values.mean_offset = state.inner.hit_errors.mean();
+        values.live_pp = state.inner.update_pp(&values.hit, values.max_combo);
+
+        // The time also stops on the fail screen, which is not a pause
+        let time = get_ig_time(p, state)?;
+        if time > 0 && time == self.last_time && !self.paused && !self.failed {
+            self.paused = true;
+            events.push(GameEvent::Paused { time });
+        } else if time != self.last_time && self.paused {
+            self.paused = false;
+            events.push(GameEvent::Resumed { time });
+        }
+        self.last_time = time;
+        values.keys = self.read_keys(p, state, values.mode);
+
+        let judged = values.hit._geki as i32 + values.hit._300 as i32 + values.hit._katu as i32
+            + values.hit._100 as i32 + values.hit._50 as i32 + values.hit._miss as i32;
+        if !self.failed && judged > 0 && values.hp <= 0.0 && values.mods & NO_FAIL == 0 {
+            self.failed = true;
+            events.push(GameEvent::Failed(values.clone()));
+        }
+
+        self.last_values = Some(values.clone());
+        events.push(GameEvent::GameplayUpdated(values));
+        Ok(())
+    }
+
+    /// Reads the keys of the play, from the keyboard in mania when enabled and from the key overlay otherwise
+    fn read_keys(&mut self, p: &impl MemorySource, state: &mut State, mode: u8) -> KeyStats {
+        let now = Instant::now();
+        if mode == MANIA_MODE {
+            if !self.mania_input_started {
+                let path = self.map.as_ref().map(|map| map.path.as_str()).unwrap_or_default();
+                self.mania_input = p.process_id().and_then(|pid| ManiaInput::start(path, pid));
+                self.mania_input_started = true;
+            }
+            return self.mania_input.as_ref().map(ManiaInput::stats).unwrap_or_default();
+        }
+        // The key overlay is missing when it is hidden in the osu! settings
+        if let Ok(keys) = get_key_overlay(p, state) {
+            self.key_tracker.update_counts(&keys, now);
+        }
+        self.key_tracker.stats(now)
+    }
+}
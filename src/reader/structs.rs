@@ -1,11 +1,10 @@
-use std::str::FromStr;
-use rosu_mem::{
-    process::{Process, ProcessTraits},
-    signature::Signature,
-};
+use serde::{Deserialize, Serialize};
+use rosu_pp::any::PerformanceAttributes;
+use rosu_pp::GradualPerformance;
+use crate::reader::memory::MemorySource;
 
 
-#[derive(Default, Clone)]
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
 pub struct StaticAddresses {
     pub base: i32,
     pub status: i32,
@@ -19,7 +18,7 @@ pub struct StaticAddresses {
     pub settings : i32,
 }
 
-#[derive(Debug, Default)]
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Hit{
     pub _geki:i16,
     pub _300:i16,
@@ -30,46 +29,32 @@ pub struct Hit{
 }
 
 impl StaticAddresses {
-    pub fn new(p: &Process) -> Result<Self,Box<dyn std::error::Error>> {
-
-        let base_sign = Signature::from_str("F8 01 74 04 83 65")?;
-        let status_sign = Signature::from_str("48 83 F8 04 73 1E")?;
-        let menu_mods_sign =
-            Signature::from_str("C8 FF ?? ?? ?? ?? ?? 81 0D ?? ?? ?? ?? 00 08 00 00")?;
-
-        let rulesets_sign = Signature::from_str("7D 15 A1 ?? ?? ?? ?? 85 C0")?;
-
-        let playtime_sign = Signature::from_str("5E 5F 5D C3 A1 ?? ?? ?? ?? 89 ?? 04")?;
-
-        let skin_sign = Signature::from_str("75 21 8B 1D")?;
-
-        let chat_checker = Signature::from_str("0A D7 23 3C 00 00 ?? 01")?;
-
-        let audio_time_base = Signature::from_str("DB 5C 24 34 8B 44 24 34")?;
-
-        let ig_time_base = Signature::from_str("EB 0A A1 ?? ?? ?? ?? A3")?;
-
-        let settings_base = Signature::from_str("83 E0 20 85 C0 7E 2F")?;
+    pub fn new(p: &impl MemorySource) -> eyre::Result<Self> {
         Ok(Self {
-            base: p.read_signature(&base_sign)?,
-            status: p.read_signature(&status_sign)?,
-            menu_mods: p.read_signature(&menu_mods_sign)?,
-            rulesets: p.read_signature(&rulesets_sign)?,
-            playtime: p.read_signature(&playtime_sign)?,
-            skin: p.read_signature(&skin_sign)?,
-            chat_checker: p.read_signature(&chat_checker)?,
-            audio_time_base: p.read_signature(&audio_time_base)?,
-            ig_time_base: p.read_signature(&ig_time_base)?,
-            settings: p.read_signature(&settings_base)?,
+            base: p.read_signature("F8 01 74 04 83 65")?,
+            status: p.read_signature("48 83 F8 04 73 1E")?,
+            menu_mods: p.read_signature("C8 FF ?? ?? ?? ?? ?? 81 0D ?? ?? ?? ?? 00 08 00 00")?,
+            rulesets: p.read_signature("7D 15 A1 ?? ?? ?? ?? 85 C0")?,
+            playtime: p.read_signature("5E 5F 5D C3 A1 ?? ?? ?? ?? 89 ?? 04")?,
+            skin: p.read_signature("75 21 8B 1D")?,
+            chat_checker: p.read_signature("0A D7 23 3C 00 00 ?? 01")?,
+            audio_time_base: p.read_signature("DB 5C 24 34 8B 44 24 34")?,
+            ig_time_base: p.read_signature("EB 0A A1 ?? ?? ?? ?? A3")?,
+            settings: p.read_signature("83 E0 20 85 C0 7E 2F")?,
         })
     }
 }
 
-/* for pp counter for later
 #[derive(Default)]
 pub struct InnerValues {
-    pub gradual_performance_current: Option<GradualPerformance<'static>>,
+    pub gradual_performance_current: Option<GradualPerformance>,
     pub current_beatmap_perf: Option<PerformanceAttributes>,
+    pub mods: u32,
+    /// Mode of the map, 3 for mania
+    pub mode: u8,
+    pub n_objects: u32,
+    pub passed_objects: u32,
+    pub hit_errors: HitErrors,
 }
 
 
@@ -77,12 +62,62 @@ impl InnerValues {
     pub fn reset(&mut self) {
         self.current_beatmap_perf = None;
         self.gradual_performance_current = None;
+        self.mods = 0;
+        self.mode = 0;
+        self.n_objects = 0;
+        self.passed_objects = 0;
+        self.hit_errors.clear();
     }
 }
-*/
-#[derive(Default,Clone)]
+
+/// Hit errors of a play, in ms (negative when early)
+#[derive(Debug, Default, Clone)]
+pub struct HitErrors {
+    pub errors: Vec<i32>,
+}
+
+impl HitErrors {
+    pub fn clear(&mut self) {
+        self.errors.clear();
+    }
+
+    /// Mean offset of the play in ms
+    pub fn mean(&self) -> f64 {
+        if self.errors.is_empty() {
+            return 0.0;
+        }
+        self.errors.iter().map(|&e| e as f64).sum::<f64>() / self.errors.len() as f64
+    }
+
+    /// Unstable rate as displayed by osu! (standard deviation of the hit errors * 10)
+    pub fn unstable_rate(&self) -> f64 {
+        if self.errors.is_empty() {
+            return 0.0;
+        }
+        let mean = self.mean();
+        let variance = self.errors
+            .iter()
+            .map(|&e| (e as f64 - mean).powi(2))
+            .sum::<f64>() / self.errors.len() as f64;
+        variance.sqrt() * 10.0
+    }
+
+    /// Counts the hit errors per bucket of `bucket_ms`, from `-range` to `range` ms
+    pub fn distribution(&self, range: i32, bucket_ms: i32) -> Vec<u32> {
+        let buckets = ((2 * range) / bucket_ms + 1) as usize;
+        let mut result = vec![0; buckets];
+        for &error in &self.errors {
+            let index = (error.clamp(-range, range) + range) / bucket_ms;
+            result[index as usize] += 1;
+        }
+        result
+    }
+}
+
+#[derive(Default)]
 pub struct State {
     pub addresses: StaticAddresses,
+    pub inner: InnerValues,
 }
 
 #[derive( Debug, Default, PartialEq, Eq, Clone, Copy)]
@@ -148,12 +183,70 @@ impl From<i16> for BeatmapStatus {
 }
 
 
-#[derive(Debug, Default)]
+#[derive(Debug, Default, Clone)]
 pub struct ResultScreenValues {
     pub username: String,
     pub mode: u8,
+    pub mods: u32,
     pub max_combo: i16,
     pub score: i32,
     pub hit : Hit,
     pub accuracy: f64,
+    /// Hit errors of the play, kept from gameplay since osu! clears them on the result screen
+    pub hit_errors: Vec<i32>,
+    /// Presses of each key during the play, empty when the keys were not read
+    pub key_counts: Vec<u32>,
+}
+
+#[derive(Debug, Default, Clone)]
+pub struct GameplayValues {
+    pub username: String,
+    pub mode: u8,
+    pub score: i32,
+    pub combo: i16,
+    pub max_combo: i16,
+    pub hp: f64,
+    pub mods: u32,
+    pub hit : Hit,
+    pub accuracy: f64,
+    /// Number of hit errors recorded so far, used for the unstable rate
+    pub hit_errors_count: i32,
+    /// Hit errors recorded since the previous tick
+    pub new_hit_errors: Vec<i32>,
+    pub unstable_rate: f64,
+    pub mean_offset: f64,
+    pub keys: KeyStats,
+    /// Current and if-FC pp, only set on the ticks where new objects were judged
+    pub live_pp: Option<(f64, f64)>,
+}
+
+/// Key of the key overlay, or mania column
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct KeyState {
+    pub name: String,
+    pub pressed: bool,
+    /// Presses since the start of the play
+    pub count: u32,
+}
+
+/// Keys of the current play
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct KeyStats {
+    pub keys: Vec<KeyState>,
+    /// Presses over the last second, all keys combined
+    pub kps: f64,
+}
+
+/// Beatmap selected in osu!, with the paths needed to analyse it
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct BeatmapInfo {
+    pub md5: String,
+    pub song: String,
+    pub author: String,
+    pub creator: String,
+    pub difficulty: String,
+    /// Path of the .osu file
+    pub path: String,
+    pub cover_path: String,
+    pub audio_path: String,
 }
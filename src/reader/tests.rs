@@ -0,0 +1,357 @@
+use std::time::{Duration, Instant};
+use crate::reader::error::ReaderError;
+use crate::reader::events::GameEvent;
+use crate::reader::memory::{MemorySource, MockProcess, RecordingSource};
+use crate::reader::reader_beatmap::{get_beatmap_md5, get_beatmap_path, get_title};
+use crate::reader::reader_common::get_status;
+use crate::reader::reader_gameplay::{get_gameplay, get_hit_errors, get_hits};
+use crate::reader::reader_keys::{get_key_overlay, KeyTracker};
+use crate::reader::reader_pp::{full_combo_state, judged_objects};
+use crate::reader::reader_resultscreen::get_result_screen;
+use crate::reader::state_machine::GameStateMachine;
+use crate::reader::structs::{GameState, Hit, State, StaticAddresses};
+
+const BASE: i32 = 0x1000;
+const STATUS: i32 = 0x1100;
+const RULESETS: i32 = 0x1200;
+const SETTINGS: i32 = 0x1300;
+const MENU_MODS: i32 = 0x1400;
+const PLAYTIME: i32 = 0x1500;
+
+fn addresses() -> StaticAddresses {
+    StaticAddresses {
+        base: BASE,
+        status: STATUS,
+        rulesets: RULESETS,
+        settings: SETTINGS,
+        menu_mods: MENU_MODS,
+        playtime: PLAYTIME,
+        ..Default::default()
+    }
+}
+
+fn state() -> State {
+    State {
+        addresses: addresses(),
+        ..Default::default()
+    }
+}
+
+/// Selected beatmap and songs folder
+fn with_beatmap(mock: MockProcess) -> MockProcess {
+    mock
+        .with_i32(BASE - 0xC, 0x3000)
+        .with_i32(0x3000, 0x3100)
+        .with_string(0x3100 + 0x6c, 0x4000, "0123456789abcdef0123456789abcdef")
+        .with_string(0x3100 + 0x24, 0x4100, "Triumph & Regret")
+        .with_string(0x3100 + 0x78, 0x4200, "123 Artist - Title")
+        .with_string(0x3100 + 0x90, 0x4300, "map.osu")
+        .with_string(0x3100 + 0x18, 0x4400, "Artist")
+        .with_string(0x3100 + 0x7C, 0x4500, "Mapper")
+        .with_string(0x3100 + 0xAC, 0x4600, "Insane")
+        .with_string(0x3100 + 0x64, 0x4700, "audio.mp3")
+        .with_string(0x3100 + 0x68, 0x4800, "bg.jpg")
+        // settings
+        .with_i32(SETTINGS + 0x8, 0x5000)
+        .with_i32(0x5000 + 0xb8, 0x5100)
+        .with_string(0x5100 + 0x4, 0x5200, "C:/osu!/Songs")
+}
+
+/// Song select with a selected map
+fn song_select() -> MockProcess {
+    with_beatmap(MockProcess::new(addresses()))
+        .with_i32(STATUS - 0x4, 0x2000)
+        .with_i32(0x2000, GameState::SongSelect as i32)
+        .with_i32(MENU_MODS + 0x9, 0x7000)
+        .with_i32(0x7000, 0)
+}
+
+/// Gameplay with a score object and hit errors
+fn gameplay() -> MockProcess {
+    let ruleset = 0x6100;
+    let gameplay_base = 0x6200;
+    let score_base = 0x6300;
+    let hp_base = 0x6400;
+    let hit_errors_base = 0x6500;
+    let hit_errors_items = 0x6600;
+    let mods_xor_base = 0x6700;
+
+    with_beatmap(MockProcess::new(addresses()))
+        .with_i32(STATUS - 0x4, 0x2000)
+        .with_i32(0x2000, GameState::Playing as i32)
+        // retries and in-game time
+        .with_i32(BASE - 0x33, 0x7100)
+        .with_i32(0x7100 + 0x8, 0)
+        .with_i32(PLAYTIME + 0x5, 0x7200)
+        .with_i32(0x7200, 1000)
+        .with_i32(RULESETS - 0xb, 0x6000)
+        .with_i32(0x6000 + 0x4, ruleset)
+        .with_i32(ruleset + 0x68, gameplay_base)
+        .with_i32(ruleset + 0x38, score_base)
+        .with_i32(gameplay_base + 0x38, score_base)
+        .with_i32(gameplay_base + 0x40, hp_base)
+        .with_f64(hp_base + 0x1C, 150.0)
+        .with_string(score_base + 0x28, 0x6800, "Cobra")
+        .with_i32(score_base + 0x1C, mods_xor_base)
+        .with_u64(mods_xor_base + 0xc, 64 ^ 0xFF)
+        .with_u64(mods_xor_base + 0x8, 0xFF)
+        .with_i32(score_base + 0x38, hit_errors_base)
+        .with_i32(score_base + 0x64, 3)
+        .with_i16(score_base + 0x68, 120)
+        .with_i32(score_base + 0x78, 456789)
+        .with_i16(score_base + 0x88, 4)
+        .with_i16(score_base + 0x8A, 50)
+        .with_i16(score_base + 0x8C, 1)
+        .with_i16(score_base + 0x8E, 100)
+        .with_i16(score_base + 0x90, 10)
+        .with_i16(score_base + 0x92, 2)
+        .with_i16(score_base + 0x94, 80)
+        .with_i32(hit_errors_base + 0x4, hit_errors_items)
+        .with_i32(hit_errors_base + 0xC, 3)
+        .with_i32(hit_errors_items + 0x8, -10)
+        .with_i32(hit_errors_items + 0xC, 4)
+        .with_i32(hit_errors_items + 0x10, 12)
+}
+
+#[test]
+fn reads_status() {
+    let p = song_select();
+    assert_eq!(GameState::from(get_status(&p, &mut state()).unwrap()), GameState::SongSelect);
+}
+
+#[test]
+fn reads_beatmap_chain() {
+    let p = song_select();
+    let mut state = state();
+    assert_eq!(get_beatmap_md5(&p, &mut state).unwrap(), "0123456789abcdef0123456789abcdef");
+    assert_eq!(get_title(&p, &mut state).unwrap(), "Triumph & Regret");
+    let path = std::path::Path::new("C:/osu!/Songs").join("123 Artist - Title").join("map.osu");
+    assert_eq!(get_beatmap_path(&p, &mut state).unwrap(), path.display().to_string());
+}
+
+#[test]
+fn reads_gameplay_chain() {
+    let p = gameplay();
+    let mut state = state();
+    let values = get_gameplay(&p, &mut state).unwrap();
+    assert_eq!(values.username, "Cobra");
+    assert_eq!(values.mode, 3);
+    assert_eq!(values.score, 456789);
+    assert_eq!(values.combo, 80);
+    assert_eq!(values.max_combo, 120);
+    assert_eq!(values.mods, 64);
+    assert_eq!(values.hit._geki, 100);
+    assert_eq!(values.hit._miss, 2);
+    assert_eq!(values.hit_errors_count, 3);
+    assert_eq!(get_hit_errors(&p, &mut state, 1).unwrap(), vec![4, 12]);
+}
+
+#[test]
+fn reads_result_screen_chain() {
+    let p = gameplay();
+    let mut state = state();
+    let result = get_result_screen(&p, &mut state).unwrap();
+    assert_eq!(result.score, 456789);
+    assert_eq!(result.hit._300, 50);
+    assert!(result.accuracy > 0.9 && result.accuracy < 1.0);
+}
+
+#[test]
+fn null_pointer_is_an_error() {
+    let p = song_select().with_i32(BASE - 0xC, 0);
+    let e = get_beatmap_md5(&p, &mut state()).unwrap_err();
+    assert!(matches!(e.downcast_ref::<ReaderError>(), Some(ReaderError::NullPointer { .. })));
+}
+
+#[test]
+fn missing_memory_is_an_error() {
+    let p = MockProcess::new(addresses());
+    assert!(get_hits(&p, &mut state()).is_err());
+}
+
+#[test]
+fn recorded_snapshot_replays_reads() {
+    let source = gameplay();
+    let recorder = RecordingSource::new(&source);
+    let expected = get_gameplay(&recorder, &mut state()).unwrap();
+
+    let path = std::env::temp_dir().join(format!("cobra_snapshot_{}.json", std::process::id()));
+    recorder.dump(&path, &addresses()).unwrap();
+    let replay = MockProcess::from_snapshot(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+
+    let values = get_gameplay(&replay, &mut state()).unwrap();
+    assert_eq!(values.score, expected.score);
+    assert_eq!(values.hit._geki, expected.hit._geki);
+    assert_eq!(replay.read_i32(RULESETS - 0xb).unwrap(), 0x6000);
+}
+
+#[test]
+fn recording_stops_at_the_limit() {
+    let source = gameplay();
+    let recorder = RecordingSource::with_limit(&source, 12);
+    recorder.read_i32(RULESETS - 0xb).unwrap();
+    recorder.read_u64(0x6700 + 0xc).unwrap();
+    assert_eq!(recorder.recorded_bytes(), 12);
+
+    // Past the limit the reads still succeed but new addresses are not kept
+    assert_eq!(recorder.read_i32(0x6300 + 0x78).unwrap(), 456789);
+    recorder.read_i32(RULESETS - 0xb).unwrap();
+    assert_eq!(recorder.recorded_bytes(), 12);
+    let replay = recorder.to_mock(&addresses());
+    assert_eq!(replay.read_i32(RULESETS - 0xb).unwrap(), 0x6000);
+    assert!(replay.read_i32(0x6300 + 0x78).is_err());
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn translates_wine_paths() {
+    use crate::reader::linux::translate_wine_path;
+    let prefix = std::path::Path::new("/nonexistent/.wine");
+    assert_eq!(translate_wine_path(prefix, "Z:\\home\\cobra\\osu!\\Songs"), std::path::Path::new("/home/cobra/osu!/Songs"));
+    assert_eq!(translate_wine_path(prefix, "C:\\osu!\\Songs"), prefix.join("drive_c/osu!/Songs"));
+    assert_eq!(translate_wine_path(prefix, "Songs"), std::path::Path::new("Songs"));
+}
+
+#[test]
+fn machine_emits_map_changed_once() {
+    let p = song_select();
+    let mut state = state();
+    let mut machine = GameStateMachine::default();
+    let events = machine.tick(&p, &mut state).unwrap();
+    assert!(matches!(events[0], GameEvent::StateChanged { to: GameState::SongSelect, .. }));
+    assert!(matches!(&events[1], GameEvent::MapChanged { map, mods: 0 } if map.song == "Triumph & Regret"));
+    assert!(matches!(&events[2], GameEvent::FoldersFound { songs, .. } if songs == "C:/osu!/Songs"));
+    assert!(machine.tick(&p, &mut state).unwrap().is_empty());
+}
+
+#[test]
+fn machine_emits_mods_changed() {
+    let mut state = state();
+    let mut machine = GameStateMachine::default();
+    machine.tick(&song_select(), &mut state).unwrap();
+    let events = machine.tick(&song_select().with_i32(0x7000, 64), &mut state).unwrap();
+    assert!(matches!(events.as_slice(), [GameEvent::ModsChanged { mods: 64, .. }]));
+}
+
+#[test]
+fn machine_follows_a_play() {
+    let mut state = state();
+    let mut machine = GameStateMachine::default();
+    machine.tick(&song_select(), &mut state).unwrap();
+
+    let events = machine.tick(&gameplay(), &mut state).unwrap();
+    assert!(matches!(events[0], GameEvent::StateChanged { from: GameState::SongSelect, to: GameState::Playing }));
+    assert!(matches!(&events[1], GameEvent::PlayStarted { mods: 64, .. }));
+    assert!(matches!(&events[2], GameEvent::GameplayUpdated(values) if values.score == 456789));
+    assert_eq!(state.inner.hit_errors.errors, vec![-10, 4, 12]);
+
+    // The in-game time did not move since the previous tick
+    let events = machine.tick(&gameplay(), &mut state).unwrap();
+    assert!(matches!(events[0], GameEvent::Paused { time: 1000 }));
+
+    let events = machine.tick(&gameplay().with_i32(0x2000, GameState::ResultScreen as i32), &mut state).unwrap();
+    assert!(matches!(&events[1], GameEvent::PlayFinished { hit_errors, .. } if hit_errors.errors.len() == 3));
+    assert!(matches!(&events[2], GameEvent::ResultScreenShown { map, result }
+        if map.md5 == "0123456789abcdef0123456789abcdef" && result.mods == 64 && result.hit_errors.len() == 3));
+}
+
+#[test]
+fn machine_does_not_pause_after_a_fail() {
+    let failing = || gameplay().with_f64(0x6400 + 0x1C, 0.0);
+    let mut state = state();
+    let mut machine = GameStateMachine::default();
+    machine.tick(&song_select(), &mut state).unwrap();
+
+    let events = machine.tick(&failing(), &mut state).unwrap();
+    assert!(matches!(&events[2], GameEvent::Failed(values) if values.hp == 0.0));
+
+    // The in-game time stops on the fail screen
+    let events = machine.tick(&failing(), &mut state).unwrap();
+    assert!(!events.iter().any(|event| matches!(event, GameEvent::Paused { .. })));
+
+    let events = machine.tick(&song_select(), &mut state).unwrap();
+    assert!(matches!(&events[1], GameEvent::PlayQuit { .. }));
+}
+
+#[test]
+fn machine_keeps_the_transition_when_the_new_screen_is_unreadable() {
+    let mut state = state();
+    let mut machine = GameStateMachine::default();
+    machine.tick(&song_select(), &mut state).unwrap();
+
+    // The in-game time is not allocated yet on the first tick of the play
+    let events = machine.tick(&gameplay().with_i32(PLAYTIME + 0x5, 0), &mut state).unwrap();
+    assert!(matches!(events.as_slice(), [GameEvent::StateChanged { to: GameState::Playing, .. }, GameEvent::PlayStarted { .. }]));
+    let events = machine.tick(&gameplay(), &mut state).unwrap();
+    assert!(matches!(events.as_slice(), [GameEvent::GameplayUpdated(_)]));
+
+    // Nor are the mods of song select right after quitting
+    let events = machine.tick(&song_select().with_i32(MENU_MODS + 0x9, 0), &mut state).unwrap();
+    assert!(matches!(events.as_slice(), [GameEvent::StateChanged { to: GameState::SongSelect, .. }, GameEvent::PlayQuit { .. }]));
+    assert_eq!(machine.status(), GameState::SongSelect);
+    assert!(machine.tick(&song_select().with_i32(MENU_MODS + 0x9, 0), &mut state).is_err());
+}
+
+#[test]
+fn machine_detects_retries() {
+    let mut state = state();
+    let mut machine = GameStateMachine::default();
+    machine.tick(&gameplay(), &mut state).unwrap();
+    let events = machine.tick(&gameplay().with_i32(0x7100 + 0x8, 1), &mut state).unwrap();
+    assert!(matches!(events.as_slice(), [GameEvent::Retry, GameEvent::PlayStarted { .. }]));
+    assert!(state.inner.hit_errors.errors.is_empty());
+}
+
+#[test]
+fn reads_key_overlay() {
+    let ruleset = 0x6100;
+    let key_overlay = 0x8000;
+    let buttons = 0x8200;
+    let mut p = gameplay()
+        .with_i32(ruleset + 0xB0, key_overlay)
+        .with_i32(key_overlay + 0x10, 0x8100)
+        .with_i32(0x8100 + 0x4, buttons);
+    for (i, count) in [42, 40, 0, 3].into_iter().enumerate() {
+        let button = 0x8300 + 0x100 * i as i32;
+        p = p
+            .with_i32(buttons + 0x8 + 0x4 * i as i32, button)
+            .with_bytes(button + 0x1C, &[(i == 1) as u8])
+            .with_i32(button + 0x14, count);
+    }
+
+    let keys = get_key_overlay(&p, &mut state()).unwrap();
+    assert_eq!(keys.iter().map(|k| k.name.as_str()).collect::<Vec<_>>(), vec!["K1", "K2", "M1", "M2"]);
+    assert_eq!(keys.iter().map(|k| k.count).collect::<Vec<_>>(), vec![42, 40, 0, 3]);
+    assert!(keys[1].pressed && !keys[0].pressed);
+}
+
+#[test]
+fn key_tracker_counts_presses_and_kps() {
+    let names: Vec<String> = ["D", "F"].iter().map(|n| n.to_string()).collect();
+    let mut tracker = KeyTracker::new(&names);
+    let start = Instant::now();
+    tracker.update_pressed(&[true, false], start);
+    tracker.update_pressed(&[true, true], start + Duration::from_millis(10));
+    tracker.update_pressed(&[false, false], start + Duration::from_millis(20));
+    tracker.update_pressed(&[true, false], start + Duration::from_millis(30));
+
+    let stats = tracker.stats(start + Duration::from_millis(500));
+    assert_eq!(stats.keys.iter().map(|k| k.count).collect::<Vec<_>>(), vec![2, 1]);
+    assert_eq!(stats.kps, 3.0);
+    assert_eq!(tracker.stats(start + Duration::from_secs(2)).kps, 0.0);
+}
+
+#[test]
+fn counts_judged_objects_per_mode() {
+    // In osu!, gekis and katus are 300s and 100s finishing a combo
+    let hit = Hit { _geki: 3, _300: 10, _katu: 1, _100: 2, _50: 1, _miss: 1 };
+    assert_eq!(judged_objects(&hit, 0), 14);
+    assert_eq!(judged_objects(&hit, 3), 18);
+
+    let state = rosu_pp::any::ScoreState { n_geki: 3, n300: 10, n_katu: 1, n100: 2, n50: 1, misses: 1, ..Default::default() };
+    let fc = full_combo_state(&state, 0, 5);
+    assert_eq!((fc.n_geki, fc.n300, fc.misses), (3, 16, 0));
+    let fc = full_combo_state(&state, 3, 5);
+    assert_eq!((fc.n_geki, fc.n300, fc.misses), (9, 10, 0));
+}
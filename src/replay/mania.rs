@@ -0,0 +1,210 @@
+use rosu_map::section::hit_objects::HitObjectKind;
+use rosu_map::Beatmap;
+use crate::reader::structs::HitErrors;
+use crate::replay::ReplayFrame;
+use crate::utils::mods::{clock_rate, EASY, HARD_ROCK, MIRROR, RANDOM};
+
+/// Number of parts the map is split into to count the misses
+pub const SECTION_COUNT: usize = 20;
+
+/// Note of a mania map, holds being judged on their head
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ManiaNote {
+    pub column: usize,
+    /// Time of the note in ms
+    pub time: f64,
+}
+
+/// Press of a column in a replay
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KeyPress {
+    pub column: usize,
+    pub time: i64,
+}
+
+/// Judgement of a note of the map
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct NoteResult {
+    pub column: usize,
+    pub time: f64,
+    /// Hit error in ms of real time (negative when early), `None` for a miss
+    pub offset: Option<f64>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ColumnStats {
+    pub hits: usize,
+    pub misses: usize,
+    /// Mean hit error in ms
+    pub mean: f64,
+    pub unstable_rate: f64,
+}
+
+/// Hit errors of a mania replay, note by note
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ManiaReplayAnalysis {
+    pub notes: Vec<NoteResult>,
+    pub columns: Vec<ColumnStats>,
+    /// Notes hit before and after their time
+    pub early: usize,
+    pub late: usize,
+    /// Misses in each of the `SECTION_COUNT` parts of the map
+    pub misses_by_section: Vec<usize>,
+}
+
+impl ManiaReplayAnalysis {
+    /// Hit errors of the notes hit, for the distribution graph
+    pub fn hit_errors(&self) -> HitErrors {
+        HitErrors { errors: self.notes.iter().filter_map(|note| note.offset).map(|offset| offset.round() as i32).collect() }
+    }
+}
+
+/// Notes of a mania map sorted by time
+///
+/// # Returns
+/// The number of columns and the notes
+pub fn mania_notes(map: &Beatmap) -> (usize, Vec<ManiaNote>) {
+    let keys = (map.circle_size.round() as usize).max(1);
+    let mut notes: Vec<ManiaNote> = map
+        .hit_objects
+        .iter()
+        .filter_map(|hit_object| {
+            let pos_x = match &hit_object.kind {
+                HitObjectKind::Circle(circle) => circle.pos.x,
+                HitObjectKind::Slider(slider) => slider.pos.x,
+                HitObjectKind::Hold(hold) => hold.pos_x,
+                _ => return None,
+            };
+            let column = ((pos_x * keys as f32 / 512.0).floor().max(0.0) as usize).min(keys - 1);
+            Some(ManiaNote { column, time: hit_object.start_time })
+        })
+        .collect();
+    notes.sort_by(|a, b| a.time.total_cmp(&b.time));
+    (keys, notes)
+}
+
+/// Moments each column starts being held
+///
+/// # Arguments
+/// * `frames` - Frames of a mania replay
+/// * `keys` - Number of columns
+pub fn key_presses(frames: &[ReplayFrame], keys: usize) -> Vec<KeyPress> {
+    let mut presses = Vec::new();
+    let mut held = 0;
+    for frame in frames {
+        let pressed = frame.mania_keys();
+        for column in 0..keys.min(32) {
+            let bit = 1 << column;
+            if pressed & bit != 0 && held & bit == 0 {
+                presses.push(KeyPress { column, time: frame.time });
+            }
+        }
+        held = pressed;
+    }
+    presses
+}
+
+/// Checks that the columns pressed in a replay can be matched with the notes of the map
+///
+/// Random shuffles the columns with a seed the replay does not keep.
+///
+/// # Arguments
+/// * `mods` - Mods of the replay
+pub fn check_replay_mods(mods: u32) -> eyre::Result<()> {
+    if mods & RANDOM != 0 {
+        return Err(eyre::eyre!("les colonnes d'un replay en Random ne peuvent pas être retrouvées"));
+    }
+    Ok(())
+}
+
+/// Windows of the 50 judgement and of a miss in ms, as in osu!stable
+///
+/// # Arguments
+/// * `od` - Overall difficulty of the map
+/// * `mods` - Mods of the play, HR and EZ changing the windows
+pub fn hit_windows(od: f64, mods: u32) -> (f64, f64) {
+    let scale = if mods & HARD_ROCK != 0 {
+        1.0 / 1.4
+    } else if mods & EASY != 0 {
+        1.4
+    } else {
+        1.0
+    };
+    ((151.0 - 3.0 * od) * scale, (188.0 - 3.0 * od) * scale)
+}
+
+fn column_stats(notes: &[NoteResult], column: usize) -> ColumnStats {
+    let notes: Vec<&NoteResult> = notes.iter().filter(|note| note.column == column).collect();
+    let errors: Vec<f64> = notes.iter().filter_map(|note| note.offset).collect();
+    let hits = errors.len();
+    if hits == 0 {
+        return ColumnStats { misses: notes.len(), ..ColumnStats::default() };
+    }
+    let mean = errors.iter().sum::<f64>() / hits as f64;
+    let variance = errors.iter().map(|error| (error - mean).powi(2)).sum::<f64>() / hits as f64;
+    ColumnStats { hits, misses: notes.len() - hits, mean, unstable_rate: variance.sqrt() * 10.0 }
+}
+
+/// Matches the presses of a replay with the notes of the map
+///
+/// Each press judges the oldest note of its column still in its window, presses before
+/// the miss window are ignored and notes left unpressed are misses.
+///
+/// # Arguments
+/// * `notes` - Notes of the map, from `mania_notes`
+/// * `keys` - Number of columns
+/// * `frames` - Frames of the replay
+/// * `od` - Overall difficulty of the map
+/// * `mods` - Mods of the replay, Mirror flipping the columns pressed
+pub fn analyze_mania_replay(notes: &[ManiaNote], keys: usize, frames: &[ReplayFrame], od: f64, mods: u32) -> ManiaReplayAnalysis {
+    let (meh, miss) = hit_windows(od, mods);
+    let rate = clock_rate(mods);
+    let mut presses = key_presses(frames, keys);
+    if mods & MIRROR != 0 {
+        presses.iter_mut().for_each(|press| press.column = keys - 1 - press.column);
+    }
+
+    let mut results = Vec::with_capacity(notes.len());
+    for column in 0..keys {
+        let column_notes: Vec<&ManiaNote> = notes.iter().filter(|note| note.column == column).collect();
+        let mut next = 0;
+        for press in presses.iter().filter(|press| press.column == column) {
+            let time = press.time as f64;
+            while next < column_notes.len() && time > column_notes[next].time + meh {
+                results.push(NoteResult { column, time: column_notes[next].time, offset: None });
+                next += 1;
+            }
+            let Some(note) = column_notes.get(next) else {
+                break;
+            };
+            let error = time - note.time;
+            if error < -miss {
+                continue;
+            }
+            let offset = (error >= -meh).then_some(error / rate);
+            results.push(NoteResult { column, time: note.time, offset });
+            next += 1;
+        }
+        for note in &column_notes[next.min(column_notes.len())..] {
+            results.push(NoteResult { column, time: note.time, offset: None });
+        }
+    }
+    results.sort_by(|a, b| a.time.total_cmp(&b.time).then(a.column.cmp(&b.column)));
+
+    let mut misses_by_section = vec![0; SECTION_COUNT];
+    if let (Some(first), Some(last)) = (results.first(), results.last()) {
+        let span = (last.time - first.time).max(1.0);
+        for note in results.iter().filter(|note| note.offset.is_none()) {
+            let section = ((note.time - first.time) / span * SECTION_COUNT as f64) as usize;
+            misses_by_section[section.min(SECTION_COUNT - 1)] += 1;
+        }
+    }
+
+    ManiaReplayAnalysis {
+        columns: (0..keys).map(|column| column_stats(&results, column)).collect(),
+        early: results.iter().filter(|note| note.offset.is_some_and(|offset| offset < 0.0)).count(),
+        late: results.iter().filter(|note| note.offset.is_some_and(|offset| offset > 0.0)).count(),
+        misses_by_section,
+        notes: results,
+    }
+}
@@ -0,0 +1,97 @@
+use std::fs::File;
+use std::io::{BufReader, Read};
+use std::path::Path;
+use crate::library::binary::OsuReader;
+use crate::library::scores_db::{read_score, DbScore};
+
+pub mod mania;
+pub mod view;
+#[cfg(test)]
+mod tests;
+
+/// Time of the last frame of replays set since 2013, its keys holding the RNG seed
+const SEED_FRAME_TIME: i64 = -12345;
+
+/// Input state of a replay frame
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct ReplayFrame {
+    /// Time since the start of the map, in ms
+    pub time: i64,
+    /// Cursor position, or key bits as a float in osu!mania
+    pub x: f32,
+    pub y: f32,
+    /// Mouse and keyboard buttons pressed
+    pub keys: u32,
+}
+
+impl ReplayFrame {
+    /// Columns held in osu!mania, one bit per column from the left
+    pub fn mania_keys(&self) -> u32 {
+        self.x.max(0.0) as u32
+    }
+}
+
+/// Content of a .osr file
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Replay {
+    /// Header of the replay, stored like a play of scores.db
+    pub score: DbScore,
+    pub frames: Vec<ReplayFrame>,
+    pub seed: Option<i32>,
+}
+
+/// Reads a .osr file
+///
+/// # Arguments
+/// * `path` - Path of the replay
+pub fn read_replay(path: &Path) -> eyre::Result<Replay> {
+    parse_replay(BufReader::new(File::open(path)?))
+}
+
+/// Parses the content of a .osr file and decompresses its frames
+///
+/// # Arguments
+/// * `reader` - Content of the file
+pub fn parse_replay(reader: impl Read) -> eyre::Result<Replay> {
+    let mut reader = OsuReader::new(reader);
+    let (score, compressed) = read_score(&mut reader)?;
+    let mut data = Vec::new();
+    lzma_rs::lzma_decompress(&mut compressed.as_slice(), &mut data)
+        .map_err(|e| eyre::eyre!("données du replay illisibles : {}", e))?;
+    let (frames, seed) = parse_frames(&String::from_utf8(data)?)?;
+    Ok(Replay { score, frames, seed })
+}
+
+/// Parses the decompressed frames, "w|x|y|z" separated by commas
+///
+/// Each frame gives the time elapsed since the previous one, the times are made absolute.
+///
+/// # Arguments
+/// * `data` - Decompressed replay data
+///
+/// # Returns
+/// The frames, and the RNG seed when the replay has one
+pub fn parse_frames(data: &str) -> eyre::Result<(Vec<ReplayFrame>, Option<i32>)> {
+    let mut frames = Vec::new();
+    let mut seed = None;
+    let mut time = 0;
+    for frame in data.split(',').filter(|frame| !frame.trim().is_empty()) {
+        let values: Vec<&str> = frame.trim().split('|').collect();
+        let [delta, x, y, keys] = values[..] else {
+            return Err(eyre::eyre!("frame invalide : {}", frame));
+        };
+        let delta: i64 = delta.parse()?;
+        if delta == SEED_FRAME_TIME {
+            seed = Some(keys.parse()?);
+            continue;
+        }
+        time += delta;
+        frames.push(ReplayFrame {
+            time,
+            x: x.parse()?,
+            y: y.parse()?,
+            keys: keys.parse::<f64>()? as u32,
+        });
+    }
+    Ok((frames, seed))
+}
@@ -0,0 +1,132 @@
+use std::io::Cursor;
+use crate::library::scores_db::DbScore;
+use crate::library::tests::DbWriter;
+use crate::reader::structs::Hit;
+use crate::replay::mania::{analyze_mania_replay, check_replay_mods, hit_windows, key_presses, KeyPress, ManiaNote, SECTION_COUNT};
+use crate::replay::{parse_frames, parse_replay, ReplayFrame};
+use crate::utils::mods::{DOUBLE_TIME, HARD_ROCK, MIRROR, RANDOM};
+
+fn frame(time: i64, keys: u32) -> ReplayFrame {
+    ReplayFrame { time, x: keys as f32, y: 0.0, keys: 0 }
+}
+
+/// Presses `column` at each time, releasing it 20 ms later
+fn taps(column: usize, times: &[i64]) -> Vec<ReplayFrame> {
+    times
+        .iter()
+        .flat_map(|&time| [frame(time, 1 << column), frame(time + 20, 0)])
+        .collect()
+}
+
+fn notes(column: usize, times: &[f64]) -> Vec<ManiaNote> {
+    times.iter().map(|&time| ManiaNote { column, time }).collect()
+}
+
+#[test]
+fn parses_frames_and_seed() {
+    let (frames, seed) = parse_frames("0|256|-500|0,-1|256|-500|0,100|5|0|0,50|1|0|0,-12345|0|0|1234,").unwrap();
+    assert_eq!(seed, Some(1234));
+    let times: Vec<i64> = frames.iter().map(|frame| frame.time).collect();
+    assert_eq!(times, [0, -1, 99, 149]);
+    assert_eq!(frames[2].mania_keys(), 5);
+    assert!(parse_frames("10|1|0").is_err());
+}
+
+#[test]
+fn decodes_osr_files() {
+    let header = DbScore {
+        mode: 3,
+        version: 20250107,
+        beatmap_md5: "aaaa".to_string(),
+        player: "Cobra".to_string(),
+        replay_md5: "r1".to_string(),
+        hit: Hit { _geki: 10, _300: 5, _katu: 1, _100: 1, _50: 0, _miss: 1 },
+        score: 500_000,
+        max_combo: 12,
+        mods: 64,
+        date: 638_000_000_000_000_000,
+        online_id: 42,
+        ..DbScore::default()
+    };
+    let mut compressed = Vec::new();
+    lzma_rs::lzma_compress(&mut "0|0|0|0,10|1|0|0,20|0|0|0,-12345|0|0|7".as_bytes(), &mut compressed).unwrap();
+
+    let mut writer = DbWriter::default();
+    writer.score_header(&header).string("0|1,").i64(header.date).i32(compressed.len() as i32);
+    writer.bytes.extend_from_slice(&compressed);
+    writer.i64(header.online_id);
+    let bytes = writer.bytes;
+
+    let replay = parse_replay(Cursor::new(bytes.as_slice())).unwrap();
+    assert_eq!(replay.score, header);
+    assert_eq!(replay.seed, Some(7));
+    assert_eq!(replay.frames, [frame(0, 0), frame(10, 1), frame(30, 0)]);
+    assert!(parse_replay(Cursor::new(&bytes[..bytes.len() - 20])).is_err());
+}
+
+#[test]
+fn finds_key_presses() {
+    let frames = [frame(0, 0b01), frame(10, 0b11), frame(20, 0b10), frame(30, 0b01), frame(40, 0)];
+    let presses = key_presses(&frames, 2);
+    assert_eq!(
+        presses,
+        [
+            KeyPress { column: 0, time: 0 },
+            KeyPress { column: 1, time: 10 },
+            KeyPress { column: 0, time: 30 },
+        ]
+    );
+    assert_eq!(hit_windows(8.0, 0), (127.0, 164.0));
+    assert!((hit_windows(8.0, HARD_ROCK).0 - 127.0 / 1.4).abs() < 1e-9);
+}
+
+#[test]
+fn judges_mania_replays() {
+    // OD 8: hits within 127 ms, presses earlier than 164 ms ignored
+    let mut map = notes(0, &[1000.0, 2000.0, 3000.0, 4000.0]);
+    map.extend(notes(1, &[1500.0, 2500.0]));
+    map.sort_by(|a, b| a.time.total_cmp(&b.time));
+    // Taps of 20 ms, never overlapping between the columns
+    let mut frames = taps(0, &[990, 1700, 1850, 3020, 3500]);
+    frames.extend(taps(1, &[1510, 2480]));
+    frames.sort_by_key(|frame| frame.time);
+
+    let analysis = analyze_mania_replay(&map, 2, &frames, 8.0, 0);
+    let offsets: Vec<Option<f64>> = analysis.notes.iter().map(|note| note.offset).collect();
+    // 1700 is ignored, 1850 is an early miss of 2000 and 4000 is never pressed
+    assert_eq!(offsets, [Some(-10.0), Some(10.0), None, Some(-20.0), Some(20.0), None]);
+    assert_eq!((analysis.early, analysis.late), (2, 2));
+    assert_eq!((analysis.columns[0].hits, analysis.columns[0].misses), (2, 2));
+    assert_eq!(analysis.columns[1].mean, -5.0);
+    assert_eq!(analysis.columns[1].unstable_rate, 150.0);
+    assert_eq!(analysis.misses_by_section.len(), SECTION_COUNT);
+    assert_eq!(analysis.misses_by_section[6], 1);
+    assert_eq!(analysis.misses_by_section[SECTION_COUNT - 1], 1);
+    assert_eq!(analysis.hit_errors().errors, [-10, 10, -20, 20]);
+
+    // Hit errors are given in real time with speed mods
+    let analysis = analyze_mania_replay(&notes(0, &[1000.0]), 1, &taps(0, &[1030]), 8.0, DOUBLE_TIME);
+    assert_eq!(analysis.notes[0].offset, Some(20.0));
+}
+
+#[test]
+fn flips_the_columns_of_mirror_replays() {
+    let map = [notes(0, &[1000.0]), notes(3, &[2000.0])].concat();
+    // With Mirror the first column of the map is pressed with the last key
+    let mut frames = taps(3, &[1010]);
+    frames.extend(taps(0, &[1990]));
+
+    let analysis = analyze_mania_replay(&map, 4, &frames, 8.0, MIRROR);
+    let offsets: Vec<Option<f64>> = analysis.notes.iter().map(|note| note.offset).collect();
+    assert_eq!(offsets, [Some(10.0), Some(-10.0)]);
+    assert!(analysis.columns.iter().all(|column| column.misses == 0));
+
+    let analysis = analyze_mania_replay(&map, 4, &frames, 8.0, 0);
+    assert_eq!(analysis.notes.iter().filter(|note| note.offset.is_none()).count(), 2);
+}
+
+#[test]
+fn refuses_random_replays() {
+    assert!(check_replay_mods(MIRROR | DOUBLE_TIME).is_ok());
+    assert!(check_replay_mods(RANDOM).is_err());
+}
@@ -0,0 +1,61 @@
+use std::path::Path;
+use slint::{ComponentHandle, SharedString, Weak};
+use crate::library::view::library_index;
+use crate::reader::push_hit_distribution;
+use crate::replay::mania::{analyze_mania_replay, check_replay_mods, mania_notes, ManiaReplayAnalysis};
+use crate::replay::read_replay;
+use crate::utils::modes::MANIA_MODE;
+use crate::{AppState, LoginPage};
+
+/// Summary of a replay analysis shown under the distribution graph
+pub fn replay_summary(analysis: &ManiaReplayAnalysis) -> String {
+    let columns: Vec<String> = analysis
+        .columns
+        .iter()
+        .enumerate()
+        .map(|(column, stats)| format!("{}: UR {:.1} / {:+.1}ms / {} miss", column + 1, stats.unstable_rate, stats.mean, stats.misses))
+        .collect();
+    let sections: Vec<String> = analysis.misses_by_section.iter().map(usize::to_string).collect();
+    format!(
+        "{}\nTôt : {} · Tard : {}\nMiss par section : {}",
+        columns.join(" · "),
+        analysis.early,
+        analysis.late,
+        sections.join(" ")
+    )
+}
+
+fn analyze(path: &Path, songs_dir: &Path) -> eyre::Result<ManiaReplayAnalysis> {
+    let replay = read_replay(path)?;
+    if replay.score.mode != MANIA_MODE {
+        return Err(eyre::eyre!("seuls les replays osu!mania sont analysés"));
+    }
+    check_replay_mods(replay.score.mods)?;
+    let index = library_index(songs_dir)?;
+    let beatmap = index
+        .by_md5(&replay.score.beatmap_md5)
+        .ok_or_else(|| eyre::eyre!("map du replay absente de osu!.db"))?;
+    let map = rosu_map::from_path::<rosu_map::Beatmap>(beatmap.path(songs_dir))
+        .map_err(|e| eyre::eyre!("Impossible de lire la map {}: {}", beatmap.file_name, e))?;
+    let (keys, notes) = mania_notes(&map);
+    Ok(analyze_mania_replay(&notes, keys, &replay.frames, f64::from(map.overall_difficulty), replay.score.mods))
+}
+
+/// Analyzes a mania replay and shows its hit errors in the distribution graph
+///
+/// # Arguments
+/// * `path` - Path of the .osr file
+/// * `songs_dir` - Songs folder of osu!
+/// * `weak` - Handle of the main window
+pub fn analyze_replay_file(path: &Path, songs_dir: &Path, weak: Weak<LoginPage>) {
+    let summary = match analyze(path, songs_dir) {
+        Ok(analysis) => {
+            push_hit_distribution(&analysis.hit_errors(), weak.clone());
+            replay_summary(&analysis)
+        }
+        Err(e) => format!("Analyse du replay impossible : {}", e),
+    };
+    let _ = weak.upgrade_in_event_loop(move |handle| {
+        handle.global::<AppState>().set_replay_summary(SharedString::from(summary));
+    });
+}
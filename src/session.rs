@@ -0,0 +1,349 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+use slint::{ComponentHandle, ModelRc, SharedString, VecModel, Weak};
+use crate::history::query::{best_per_map, weighted_total};
+use crate::history::view::format_timestamp;
+use crate::history::{now, ScoreRecord};
+use crate::reader::events::{listen, EventBus, GameEvent};
+use crate::utils::data_dir::data_dir;
+use crate::{LoginPage, SessionMapData, SessionState};
+
+/// Statistics of one beatmap played during the session
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct MapSession {
+    pub md5: String,
+    pub title: String,
+    pub difficulty: String,
+    /// Plays started, retries included
+    pub attempts: u32,
+    pub retries: u32,
+    pub passes: u32,
+    pub fails: u32,
+    pub best_pp: f64,
+}
+
+/// Everything played since Cobra was launched
+#[derive(Debug)]
+pub struct Session {
+    /// Unix timestamp of the start of the session, in seconds
+    pub started_at: u64,
+    /// Beatmaps in the order they were first played
+    pub maps: Vec<MapSession>,
+    pub gameplay_time: Duration,
+    pub pauses: u32,
+    pub quits: u32,
+    /// Best pp of every beatmap, from the history and the plays of the session
+    bests: HashMap<String, f64>,
+    initial_total: f64,
+    current_map: Option<usize>,
+    current_failed: bool,
+    playing_since: Option<Instant>,
+}
+
+impl Session {
+    /// Starts a session, `history` being the plays recorded before launch
+    pub fn new(history: &[ScoreRecord]) -> Self {
+        let bests: HashMap<String, f64> = best_per_map(history)
+            .into_iter()
+            .map(|record| (record.beatmap_md5, record.pp))
+            .collect();
+        Self {
+            started_at: now(),
+            maps: Vec::new(),
+            gameplay_time: Duration::ZERO,
+            pauses: 0,
+            quits: 0,
+            initial_total: weighted_total(bests.values().copied()),
+            bests,
+            current_map: None,
+            current_failed: false,
+            playing_since: None,
+        }
+    }
+
+    pub fn retries(&self) -> u32 {
+        self.maps.iter().map(|map| map.retries).sum()
+    }
+
+    pub fn passes(&self) -> u32 {
+        self.maps.iter().map(|map| map.passes).sum()
+    }
+
+    pub fn fails(&self) -> u32 {
+        self.maps.iter().map(|map| map.fails).sum()
+    }
+
+    pub fn attempts(&self) -> u32 {
+        self.maps.iter().map(|map| map.attempts).sum()
+    }
+
+    /// Weighted pp total gained since the start of the session
+    pub fn pp_gained(&self) -> f64 {
+        weighted_total(self.bests.values().copied()) - self.initial_total
+    }
+
+    /// Time spent in gameplay, pauses excluded, the current play included
+    pub fn total_gameplay_time(&self) -> Duration {
+        self.gameplay_time + self.playing_since.map_or(Duration::ZERO, |since| since.elapsed())
+    }
+
+    fn start_timer(&mut self) {
+        if self.playing_since.is_none() {
+            self.playing_since = Some(Instant::now());
+        }
+    }
+
+    fn stop_timer(&mut self) {
+        if let Some(since) = self.playing_since.take() {
+            self.gameplay_time += since.elapsed();
+        }
+    }
+
+    fn current(&mut self) -> Option<&mut MapSession> {
+        self.current_map.and_then(|i| self.maps.get_mut(i))
+    }
+
+    /// Updates the session from a reader event
+    pub fn on_event(&mut self, event: &GameEvent) {
+        match event {
+            GameEvent::PlayStarted { map, .. } => {
+                let index = match self.maps.iter().position(|m| m.md5 == map.md5) {
+                    Some(index) => index,
+                    None => {
+                        self.maps.push(MapSession {
+                            md5: map.md5.clone(),
+                            title: map.song.clone(),
+                            difficulty: map.difficulty.clone(),
+                            ..Default::default()
+                        });
+                        self.maps.len() - 1
+                    }
+                };
+                self.maps[index].attempts += 1;
+                self.current_map = Some(index);
+                self.current_failed = false;
+                self.stop_timer();
+                self.start_timer();
+            }
+            GameEvent::Retry => {
+                self.stop_timer();
+                if let Some(map) = self.current() {
+                    map.retries += 1;
+                }
+            }
+            GameEvent::Paused { .. } => {
+                self.pauses += 1;
+                self.stop_timer();
+            }
+            GameEvent::Resumed { .. } => self.start_timer(),
+            GameEvent::Failed(_) => {
+                self.current_failed = true;
+                self.stop_timer();
+                if let Some(map) = self.current() {
+                    map.fails += 1;
+                }
+            }
+            GameEvent::PlayQuit { .. } => {
+                self.stop_timer();
+                if !self.current_failed {
+                    self.quits += 1;
+                }
+            }
+            GameEvent::PlayFinished { .. } => {
+                self.stop_timer();
+                if let Some(map) = self.current() {
+                    map.passes += 1;
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Takes a play written to the history into account for the pp gained
+    pub fn on_record(&mut self, record: &ScoreRecord) {
+        let best = self.bests.entry(record.beatmap_md5.clone()).or_insert(0.0);
+        *best = best.max(record.pp);
+        if let Some(map) = self.maps.iter_mut().find(|map| map.md5 == record.beatmap_md5) {
+            map.best_pp = map.best_pp.max(record.pp);
+        }
+    }
+
+    /// Text summary of the session
+    pub fn summary(&self) -> String {
+        let mut summary = format!(
+            "Session Cobra du {} (UTC)\n\n\
+            Temps en jeu: {}\n\
+            Parties: {} ({} réussies, {} échouées, {} abandonnées)\n\
+            Retries: {}\n\
+            Pauses: {}\n\
+            PP gagnés: {:+.2}\n\n\
+            Maps jouées:\n",
+            format_timestamp(self.started_at),
+            format_duration(self.total_gameplay_time()),
+            self.attempts(),
+            self.passes(),
+            self.fails(),
+            self.quits,
+            self.retries(),
+            self.pauses,
+            self.pp_gained(),
+        );
+        for map in &self.maps {
+            summary.push_str(&format!(
+                "- {} [{}]: {} essais, {} retries, {} réussies, {} échouées, meilleur score {:.2}pp\n",
+                map.title, map.difficulty, map.attempts, map.retries, map.passes, map.fails, map.best_pp
+            ));
+        }
+        summary
+    }
+
+    /// Writes the summary in the `sessions` folder of the data folder
+    ///
+    /// # Returns
+    /// Path of the written file
+    pub fn export_summary(&self) -> eyre::Result<PathBuf> {
+        let dir = data_dir()?.join("sessions");
+        std::fs::create_dir_all(&dir)?;
+        let name = format_timestamp(self.started_at).replace([' ', ':'], "-");
+        let path = dir.join(format!("session_{}.txt", name));
+        std::fs::write(&path, self.summary())?;
+        Ok(path)
+    }
+}
+
+/// Formats a duration as "1h05m" or "12m30s"
+fn format_duration(duration: Duration) -> String {
+    let seconds = duration.as_secs();
+    if seconds >= 3_600 {
+        format!("{}h{:02}m", seconds / 3_600, seconds % 3_600 / 60)
+    } else {
+        format!("{}m{:02}s", seconds / 60, seconds % 60)
+    }
+}
+
+/// Updates the session panel
+pub fn refresh_session_ui(session: &Session, weak: Weak<LoginPage>) {
+    let maps: Vec<SessionMapData> = session
+        .maps
+        .iter()
+        .map(|map| SessionMapData {
+            title: SharedString::from(format!("{} [{}]", map.title, map.difficulty)),
+            attempts: map.attempts as i32,
+            retries: map.retries as i32,
+            passes: map.passes as i32,
+            fails: map.fails as i32,
+            best_pp: map.best_pp as f32,
+        })
+        .collect();
+    let play_time = format_duration(session.total_gameplay_time());
+    let attempts = session.attempts() as i32;
+    let retries = session.retries() as i32;
+    let passes = session.passes() as i32;
+    let fails = session.fails() as i32;
+    let pauses = session.pauses as i32;
+    let pp_gained = session.pp_gained() as f32;
+
+    let _ = weak.upgrade_in_event_loop(move |handle| {
+        let session_state = handle.global::<SessionState>();
+        session_state.set_play_time(SharedString::from(play_time));
+        session_state.set_attempts(attempts);
+        session_state.set_retries(retries);
+        session_state.set_passes(passes);
+        session_state.set_fails(fails);
+        session_state.set_pauses(pauses);
+        session_state.set_pp_gained(pp_gained);
+        session_state.set_maps(ModelRc::new(VecModel::from(maps)));
+    });
+}
+
+/// Follows the reader events to keep the session up to date
+///
+/// # Arguments
+/// * `events` - Event bus of the reader
+/// * `session` - Session shared with the UI
+/// * `weak` - Handle of the main window
+pub fn subscribe_session(events: &EventBus, session: Arc<Mutex<Session>>, weak: Weak<LoginPage>) {
+    let receiver = events.subscribe();
+    thread::spawn(move || {
+        listen(receiver, |event| {
+            if matches!(event, GameEvent::GameplayUpdated(_) | GameEvent::StateChanged { .. }) {
+                return;
+            }
+            let mut session = session.lock().unwrap();
+            session.on_event(&event);
+            refresh_session_ui(&session, weak.clone());
+        });
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::reader::structs::{BeatmapInfo, GameplayValues, HitErrors};
+
+    fn started(md5: &str) -> GameEvent {
+        let map = BeatmapInfo {
+            md5: md5.to_string(),
+            song: "Triumph & Regret".to_string(),
+            difficulty: "Regret".to_string(),
+            ..Default::default()
+        };
+        GameEvent::PlayStarted { map, mods: 0 }
+    }
+
+    fn play(session: &mut Session, events: &[GameEvent]) {
+        for event in events {
+            session.on_event(event);
+        }
+    }
+
+    #[test]
+    fn counts_a_failed_play() {
+        let mut session = Session::new(&[]);
+        play(&mut session, &[
+            started("a"),
+            GameEvent::Paused { time: 1000 },
+            GameEvent::Resumed { time: 1000 },
+            GameEvent::Failed(GameplayValues::default()),
+            GameEvent::PlayQuit { hit_errors: HitErrors::default() },
+        ]);
+        assert_eq!((session.attempts(), session.fails(), session.passes()), (1, 1, 0));
+        assert_eq!(session.pauses, 1);
+        // Leaving the fail screen is not a quit
+        assert_eq!(session.quits, 0);
+        assert!(session.playing_since.is_none());
+    }
+
+    #[test]
+    fn counts_retries_and_passes_per_map() {
+        let mut session = Session::new(&[]);
+        play(&mut session, &[
+            started("a"),
+            GameEvent::Retry,
+            started("a"),
+            GameEvent::PlayFinished { values: GameplayValues::default(), hit_errors: HitErrors::default() },
+            started("b"),
+            GameEvent::PlayQuit { hit_errors: HitErrors::default() },
+        ]);
+        assert_eq!(session.maps.len(), 2);
+        assert_eq!((session.maps[0].attempts, session.maps[0].retries, session.maps[0].passes), (2, 1, 1));
+        assert_eq!((session.maps[1].attempts, session.maps[1].passes), (1, 0));
+        assert_eq!(session.quits, 1);
+        assert_eq!(session.attempts(), 3);
+    }
+
+    #[test]
+    fn stops_the_timer_while_paused() {
+        let mut session = Session::new(&[]);
+        play(&mut session, &[started("a"), GameEvent::Paused { time: 500 }]);
+        assert!(session.playing_since.is_none());
+        session.on_event(&GameEvent::Resumed { time: 500 });
+        assert!(session.playing_since.is_some());
+        session.on_event(&GameEvent::PlayFinished { values: GameplayValues::default(), hit_errors: HitErrors::default() });
+        assert!(session.playing_since.is_none());
+        assert!(session.summary().contains("- Triumph & Regret [Regret]: 1 essais, 0 retries, 1 réussies"));
+    }
+}
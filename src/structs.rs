@@ -14,4 +14,6 @@ pub struct MapSet {
 pub struct Map {
     pub difficulty: f64,
     pub pattern: String,
+    #[serde(default)]
+    pub md5: String,
 }
\ No newline at end of file
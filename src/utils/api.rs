@@ -1,8 +1,7 @@
 use std::error::Error;
-use futures_util::StreamExt;
 use reqwest::{Client, Response, Method, StatusCode};
-use tokio::io::AsyncWriteExt;
-use serde::de::DeserializeOwned;
+use crate::structs::MapSet;
+use crate::utils::pattern_detector::pattern_in_family;
 use crate::MapSetResponse;
 
 /// Custom result type for API operations that can return any error type
@@ -10,6 +9,119 @@ type ApiResult<T> = Result<T, Box<dyn Error>>;
 /// Custom result type specifically for operations returning strings
 type ApiStringResult = Result<String, String>;
 
+/// Order of the mapsets returned by a search
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum MapsetSort {
+    #[default]
+    Recent,
+    StarsAsc,
+    StarsDesc,
+    Length,
+    Bpm,
+    Title,
+}
+
+impl MapsetSort {
+    fn as_param(self) -> &'static str {
+        match self {
+            Self::Recent => "recent",
+            Self::StarsAsc => "stars_asc",
+            Self::StarsDesc => "stars_desc",
+            Self::Length => "length",
+            Self::Bpm => "bpm",
+            Self::Title => "title",
+        }
+    }
+}
+
+/// Ranked status of a mapset
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MapsetStatus {
+    Ranked,
+    Approved,
+    Qualified,
+    Loved,
+    Pending,
+    Graveyard,
+}
+
+impl MapsetStatus {
+    fn as_param(self) -> &'static str {
+        match self {
+            Self::Ranked => "ranked",
+            Self::Approved => "approved",
+            Self::Qualified => "qualified",
+            Self::Loved => "loved",
+            Self::Pending => "pending",
+            Self::Graveyard => "graveyard",
+        }
+    }
+}
+
+/// Search filters for `Api::fetch_mapsets`, `None` fields are not filtered on
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct MapsetQuery {
+    /// Searched in the title, artist, creator and difficulty names
+    pub text: Option<String>,
+    pub key_count: Option<u32>,
+    pub star_min: Option<f64>,
+    pub star_max: Option<f64>,
+    pub msd_min: Option<f64>,
+    pub msd_max: Option<f64>,
+    /// Pattern family of one of the difficulties (ex: "Jumpstream"), checked
+    /// on the returned mapsets with `MapsetQuery::keeps`
+    pub pattern: Option<String>,
+    pub status: Option<MapsetStatus>,
+    /// Length range in seconds
+    pub length_min: Option<u32>,
+    pub length_max: Option<u32>,
+    pub bpm_min: Option<f64>,
+    pub bpm_max: Option<f64>,
+    pub sort: MapsetSort,
+}
+
+impl MapsetQuery {
+    /// Query parameters of `/mapset` for a page of this search
+    ///
+    /// The API ignores the parameters it does not know, the page is then
+    /// returned unfiltered. The pattern is never sent since the families of the
+    /// filters are not the pattern names of the API.
+    ///
+    /// # Arguments
+    /// * `page` - Page number to fetch
+    pub fn params(&self, page: i32) -> Vec<(&'static str, String)> {
+        let mut params = vec![("page", page.to_string())];
+        let mut push = |name: &'static str, value: Option<String>| {
+            if let Some(value) = value {
+                params.push((name, value));
+            }
+        };
+        push("q", self.text.clone().filter(|text| !text.trim().is_empty()));
+        push("keys", self.key_count.map(|keys| keys.to_string()));
+        push("star_min", self.star_min.map(|v| v.to_string()));
+        push("star_max", self.star_max.map(|v| v.to_string()));
+        push("msd_min", self.msd_min.map(|v| v.to_string()));
+        push("msd_max", self.msd_max.map(|v| v.to_string()));
+        push("status", self.status.map(|status| status.as_param().to_string()));
+        push("length_min", self.length_min.map(|v| v.to_string()));
+        push("length_max", self.length_max.map(|v| v.to_string()));
+        push("bpm_min", self.bpm_min.map(|v| v.to_string()));
+        push("bpm_max", self.bpm_max.map(|v| v.to_string()));
+        params.push(("sort", self.sort.as_param().to_string()));
+        params
+    }
+
+    /// Whether a mapset returned by the API matches the pattern of the search
+    ///
+    /// # Arguments
+    /// * `map_set` - Mapset of a `/mapset` page
+    pub fn keeps(&self, map_set: &MapSet) -> bool {
+        self.pattern
+            .as_ref()
+            .is_none_or(|family| map_set.maps.iter().any(|map| pattern_in_family(&map.pattern, family)))
+    }
+}
+
 /// Represents an API client for making HTTP requests
 #[derive(Clone)]
 pub struct Api {
@@ -37,18 +149,17 @@ impl Api {
     /// # Arguments
     /// * `method` - HTTP method to use
     /// * `endpoint` - API endpoint to call
+    /// * `query` - Query parameters, encoded in the URL
     /// * `body` - Optional JSON body to send with the request
-    async fn request<T>(
+    async fn request(
         &self,
         method: Method,
         endpoint: &str,
+        query: &[(&str, String)],
         body: Option<serde_json::Value>
-    ) -> ApiResult<Response>
-    where
-        T: DeserializeOwned + Send + 'static
-    {
+    ) -> ApiResult<Response> {
         let url: String = format!("{}{}", self.base_url, endpoint);
-        let mut builder = self.client.request(method, &url);
+        let mut builder = self.client.request(method, &url).query(query);
 
         if let Some(json) = body {
             builder = builder.json(&json);
@@ -68,14 +179,16 @@ impl Api {
         Ok(bytes.to_vec())
     }
 
-    /// Fetches a page of mapsets from the API
+    /// Fetches a page of mapsets matching a search from the API
     ///
     /// # Arguments
     /// * `page` - Page number to fetch
-    pub async fn fetch_mapsets(&self, page: i32) -> ApiResult<MapSetResponse> {
-        let response: Response = self.request::<MapSetResponse>(
+    /// * `query` - Search filters, `MapsetQuery::default()` for every mapset
+    pub async fn fetch_mapsets(&self, page: i32, query: &MapsetQuery) -> ApiResult<MapSetResponse> {
+        let response: Response = self.request(
             Method::GET,
-            &format!("/mapset?page={}", page),
+            "/mapset",
+            &query.params(page),
             None
         ).await?;
 
@@ -94,7 +207,7 @@ impl Api {
             "password": password
         });
 
-        let response: Response = self.request::<String>(Method::POST, "/login", Some(params))
+        let response: Response = self.request(Method::POST, "/login", &[], Some(params))
             .await
             .map_err(|e| format!("Erreur HTTP: {}", e))?;
 
@@ -108,38 +221,63 @@ impl Api {
             Err(format!("Identifiants incorrects (status: {})", status))
         }
     }
+}
 
-    /// Downloads a file from a URL to a local path with progress tracking
-    ///
-    /// # Arguments
-    /// * `url` - URL of the file to download
-    /// * `path` - Local path where the file should be saved
-    /// * `progress_callback` - Callback function that receives download progress updates
-    pub async fn download_files(
-        &self,
-        url: &str,
-        path: &str,
-        progress_callback: impl Fn(f32) + Send + 'static,
-    ) -> ApiResult<()> {
-        let response: Response = self.client.get(url).send().await?;
-        let total_size: u64 = response.content_length().unwrap_or(0);
-
-        let mut file = tokio::fs::File::create(path).await?;
-        let mut downloaded: u64 = 0;
-        let mut stream = response.bytes_stream();
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::structs::Map;
 
-        while let Some(chunk_result) = stream.next().await {
-            let chunk: bytes::Bytes = chunk_result?;
-            downloaded += chunk.len() as u64;
+    #[test]
+    fn default_query_only_pages() {
+        let params = MapsetQuery::default().params(3);
+        assert_eq!(params, vec![("page", "3".to_string()), ("sort", "recent".to_string())]);
+    }
 
-            if total_size > 0 {
-                let progress: f32 = downloaded as f32 / total_size as f32;
-                progress_callback(progress);
-            }
+    #[test]
+    fn query_sends_set_filters() {
+        let query = MapsetQuery {
+            text: Some("camellia".to_string()),
+            key_count: Some(4),
+            star_min: Some(4.5),
+            pattern: Some("Jumpstream".to_string()),
+            status: Some(MapsetStatus::Ranked),
+            bpm_max: Some(200.0),
+            sort: MapsetSort::StarsDesc,
+            ..Default::default()
+        };
+        let params = query.params(0);
+        assert!(params.contains(&("q", "camellia".to_string())));
+        assert!(params.contains(&("keys", "4".to_string())));
+        assert!(params.contains(&("star_min", "4.5".to_string())));
+        assert!(!params.iter().any(|(name, _)| *name == "pattern"));
+        assert!(params.contains(&("status", "ranked".to_string())));
+        assert!(params.contains(&("bpm_max", "200".to_string())));
+        assert!(params.contains(&("sort", "stars_desc".to_string())));
+        assert!(!params.iter().any(|(name, _)| *name == "msd_min"));
+    }
 
-            file.write_all(&chunk).await?;
-        }
+    #[test]
+    fn pattern_is_checked_on_the_mapsets() {
+        let map = |pattern: &str| Map { difficulty: 4.0, pattern: pattern.to_string(), md5: String::new() };
+        let map_set = MapSet {
+            maps: vec![map("Light JS"), map("Chordjack")],
+            cover: String::new(),
+            song: "Title".to_string(),
+            author: "Artist".to_string(),
+            creator: "Mapper".to_string(),
+            link: String::new(),
+        };
+        let query = |pattern: Option<&str>| MapsetQuery { pattern: pattern.map(str::to_string), ..Default::default() };
+        assert!(query(None).keeps(&map_set));
+        assert!(query(Some("Jumpstream")).keeps(&map_set));
+        assert!(query(Some("Jack")).keeps(&map_set));
+        assert!(!query(Some("Handstream")).keeps(&map_set));
+    }
 
-        Ok(())
+    #[test]
+    fn blank_text_is_not_sent() {
+        let query = MapsetQuery { text: Some("  ".to_string()), ..Default::default() };
+        assert!(!query.params(0).iter().any(|(name, _)| *name == "q"));
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,19 @@
+use std::path::PathBuf;
+
+/// Folder holding the files written by Cobra, next to the executable
+const DATA_DIR_NAME: &str = "cobra_data";
+
+/// Returns the folder where Cobra stores its data, creating it if needed
+///
+/// The `COBRA_DATA_DIR` environment variable overrides the default location.
+pub fn data_dir() -> eyre::Result<PathBuf> {
+    let dir = match std::env::var_os("COBRA_DATA_DIR") {
+        Some(dir) => PathBuf::from(dir),
+        None => std::env::current_exe()?
+            .parent()
+            .ok_or_else(|| eyre::eyre!("Impossible de trouver le dossier de l'exécutable"))?
+            .join(DATA_DIR_NAME),
+    };
+    std::fs::create_dir_all(&dir)?;
+    Ok(dir)
+}
@@ -0,0 +1,85 @@
+use crate::utils::msd::{calc_msd, msd_for_rate};
+use rayon::prelude::*;
+
+/// Accuracies shown by default in the difficulty table
+pub const DEFAULT_ACCURACIES: [f64; 4] = [95.0, 98.0, 99.0, 100.0];
+
+/// Difficulty values of a map at a given rate
+#[derive(Debug, Clone)]
+pub struct RateDifficulty {
+    pub rate: f64,
+    pub stars: f64,
+    /// Overall MSD, only available for 4K mania maps
+    pub msd: Option<f64>,
+    /// Performance points for each requested accuracy, as (accuracy, pp)
+    pub pp: Vec<(f64, f64)>,
+}
+
+/// Difficulty values of a map for a set of rates and accuracies
+#[derive(Debug, Clone, Default)]
+pub struct DifficultyMatrix {
+    pub accuracies: Vec<f64>,
+    pub rates: Vec<RateDifficulty>,
+}
+
+impl DifficultyMatrix {
+    /// Returns the row of the matrix matching the given rate
+    pub fn at_rate(&self, rate: f64) -> Option<&RateDifficulty> {
+        self.rates.iter().find(|r| (r.rate - rate).abs() < 0.001)
+    }
+}
+
+/// Computes stars, MSD and pp of a map for every rate and accuracy given
+///
+/// # Arguments
+/// * `path` - Path of the .osu file
+/// * `mods` - Mods bitmask applied on top of the rate
+/// * `rates` - Clock rates to compute (e.g. 1.2 for 1.2x)
+/// * `accuracies` - Accuracies to compute pp for, in percent
+pub fn calc_difficulty_matrix(
+    path: &str,
+    mods: u32,
+    rates: &[f64],
+    accuracies: &[f64],
+) -> eyre::Result<DifficultyMatrix> {
+    let map = rosu_pp::Beatmap::from_path(path)
+        .map_err(|e| eyre::eyre!("Impossible de lire la map {}: {}", path, e))?;
+    let msds = rosu_map::from_path::<rosu_map::Beatmap>(path)
+        .ok()
+        .and_then(|parsed| calc_msd(&parsed).ok());
+
+    let rates = rates
+        .par_iter()
+        .map(|&rate| {
+            let diff_attrs = rosu_pp::Difficulty::new()
+                .mods(mods)
+                .clock_rate(rate)
+                .calculate(&map);
+            let stars = diff_attrs.stars();
+
+            let pp = accuracies
+                .iter()
+                .map(|&accuracy| {
+                    let perf_attrs = rosu_pp::Performance::new(diff_attrs.clone())
+                        .mods(mods)
+                        .clock_rate(rate)
+                        .accuracy(accuracy)
+                        .calculate();
+                    (accuracy, perf_attrs.pp())
+                })
+                .collect();
+
+            RateDifficulty {
+                rate,
+                stars,
+                msd: msds.as_ref().map(|msds| msd_for_rate(msds, rate)),
+                pp,
+            }
+        })
+        .collect();
+
+    Ok(DifficultyMatrix {
+        accuracies: accuracies.to_vec(),
+        rates,
+    })
+}
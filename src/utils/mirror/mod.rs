@@ -0,0 +1,234 @@
+use std::path::{Path, PathBuf};
+use futures_util::future::BoxFuture;
+use reqwest::{header, Client, RequestBuilder, Response};
+use serde::{Deserialize, Serialize};
+use crate::utils::data_dir::data_dir;
+
+mod sources;
+#[cfg(test)]
+mod tests;
+
+pub use sources::{CommunityMirror, OsefMirror, OsuApiMirror};
+
+/// Source of .osz files, tried in the order chosen in the settings
+pub trait Mirror: Send + Sync {
+    /// Name shown in the settings and in the logs
+    fn name(&self) -> &str;
+
+    /// Starts the download of a beatmapset
+    ///
+    /// # Arguments
+    /// * `client` - HTTP client shared by the mirrors
+    /// * `set_id` - osu! id of the beatmapset
+    /// * `offset` - Bytes already downloaded, asked with a `Range` header when not 0
+    ///
+    /// # Returns
+    /// The response, already checked to hold a file. Its status is 206 only
+    /// when the mirror honoured the range.
+    fn download<'a>(&'a self, client: &'a Client, set_id: u32, offset: u64) -> BoxFuture<'a, eyre::Result<Response>>;
+}
+
+/// Asks for the end of a file when part of it is already downloaded
+pub(crate) fn with_range(builder: RequestBuilder, offset: u64) -> RequestBuilder {
+    if offset == 0 {
+        builder
+    } else {
+        builder.header(header::RANGE, format!("bytes={}-", offset))
+    }
+}
+
+/// Fails on error statuses and on the HTML pages some mirrors send instead of a 404
+pub(crate) fn check_response(response: Response) -> eyre::Result<Response> {
+    let status = response.status();
+    if !status.is_success() {
+        return Err(eyre::eyre!("statut HTTP {}", status));
+    }
+    let is_html = response
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| value.contains("text/html"));
+    if is_html {
+        return Err(eyre::eyre!("page HTML reçue à la place du fichier"));
+    }
+    Ok(response)
+}
+
+/// Mirrors known by Cobra
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum MirrorKind {
+    Osef,
+    OsuApi,
+    Catboy,
+    Nerinyan,
+    OsuDirect,
+}
+
+impl MirrorKind {
+    pub const ALL: [MirrorKind; 5] = [Self::Osef, Self::Catboy, Self::Nerinyan, Self::OsuDirect, Self::OsuApi];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Osef => "osef.me",
+            Self::OsuApi => "osu! API v2",
+            Self::Catboy => "catboy.best",
+            Self::Nerinyan => "Nerinyan",
+            Self::OsuDirect => "osu.direct",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MirrorConfig {
+    pub kind: MirrorKind,
+    pub enabled: bool,
+}
+
+/// Mirrors chosen in the settings, saved in the data folder
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MirrorSettings {
+    /// Every mirror, in the order they are tried
+    pub mirrors: Vec<MirrorConfig>,
+    /// OAuth client of the osu! API v2, created in the osu! account settings
+    #[serde(default)]
+    pub osu_client_id: String,
+    #[serde(default)]
+    pub osu_client_secret: String,
+}
+
+impl Default for MirrorSettings {
+    fn default() -> Self {
+        Self {
+            mirrors: MirrorKind::ALL
+                .iter()
+                .map(|&kind| MirrorConfig { kind, enabled: kind != MirrorKind::OsuApi })
+                .collect(),
+            osu_client_id: String::new(),
+            osu_client_secret: String::new(),
+        }
+    }
+}
+
+impl MirrorSettings {
+    fn path() -> eyre::Result<PathBuf> {
+        Ok(data_dir()?.join("mirrors.json"))
+    }
+
+    /// Loads the saved settings, or the defaults when there are none
+    ///
+    /// Mirrors added since the file was written are appended, disabled.
+    pub fn load() -> Self {
+        let mut settings: Self = Self::path()
+            .and_then(|path| Ok(std::fs::read_to_string(path)?))
+            .and_then(|text| Ok(serde_json::from_str(&text)?))
+            .unwrap_or_default();
+        for kind in MirrorKind::ALL {
+            if !settings.mirrors.iter().any(|mirror| mirror.kind == kind) {
+                settings.mirrors.push(MirrorConfig { kind, enabled: false });
+            }
+        }
+        settings
+    }
+
+    pub fn save(&self) -> eyre::Result<()> {
+        std::fs::write(Self::path()?, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+
+    /// Moves a mirror up (negative offset) or down in the fallback order
+    ///
+    /// # Arguments
+    /// * `index` - Current position of the mirror
+    /// * `offset` - Number of positions to move it by
+    pub fn move_mirror(&mut self, index: usize, offset: i32) {
+        if index >= self.mirrors.len() {
+            return;
+        }
+        let target = (index as i32 + offset).clamp(0, self.mirrors.len() as i32 - 1) as usize;
+        let mirror = self.mirrors.remove(index);
+        self.mirrors.insert(target, mirror);
+    }
+}
+
+/// Mirrors to download from, each one being tried when the previous fails
+pub struct MirrorList {
+    client: Client,
+    mirrors: Vec<Box<dyn Mirror>>,
+}
+
+impl MirrorList {
+    pub fn new(client: Client, mirrors: Vec<Box<dyn Mirror>>) -> Self {
+        Self { client, mirrors }
+    }
+
+    /// Builds the enabled mirrors, the osu! API is skipped without credentials
+    ///
+    /// # Arguments
+    /// * `client` - HTTP client shared by the mirrors
+    /// * `settings` - Mirrors chosen in the settings
+    pub fn from_settings(client: Client, settings: &MirrorSettings) -> Self {
+        let mirrors = settings
+            .mirrors
+            .iter()
+            .filter(|mirror| mirror.enabled)
+            .filter_map(|mirror| -> Option<Box<dyn Mirror>> {
+                match mirror.kind {
+                    MirrorKind::Osef => Some(Box::new(OsefMirror::default())),
+                    MirrorKind::Catboy => Some(Box::new(CommunityMirror::catboy())),
+                    MirrorKind::Nerinyan => Some(Box::new(CommunityMirror::nerinyan())),
+                    MirrorKind::OsuDirect => Some(Box::new(CommunityMirror::osu_direct())),
+                    MirrorKind::OsuApi => {
+                        if settings.osu_client_id.is_empty() || settings.osu_client_secret.is_empty() {
+                            return None;
+                        }
+                        Some(Box::new(OsuApiMirror::new(&settings.osu_client_id, &settings.osu_client_secret)))
+                    }
+                }
+            })
+            .collect();
+        Self::new(client, mirrors)
+    }
+
+    pub fn names(&self) -> Vec<String> {
+        self.mirrors.iter().map(|mirror| mirror.name().to_string()).collect()
+    }
+
+    /// Starts the download of a beatmapset on the first mirror that has it
+    ///
+    /// # Arguments
+    /// * `set_id` - osu! id of the beatmapset
+    /// * `offset` - Bytes already downloaded, see `Mirror::download`
+    ///
+    /// # Returns
+    /// Name of the mirror and its response
+    pub async fn open(&self, set_id: u32, offset: u64) -> eyre::Result<(String, Response)> {
+        let mut errors = Vec::new();
+        for mirror in &self.mirrors {
+            match mirror.download(&self.client, set_id, offset).await {
+                Ok(response) => return Ok((mirror.name().to_string(), response)),
+                Err(e) => errors.push(format!("{}: {}", mirror.name(), e)),
+            }
+        }
+        if errors.is_empty() {
+            return Err(eyre::eyre!("Aucun miroir activé"));
+        }
+        Err(eyre::eyre!(
+            "Aucun miroir n'a pu télécharger la beatmapset {} ({})",
+            set_id,
+            errors.join(", ")
+        ))
+    }
+}
+
+/// Extracts the beatmapset id from an osu! or mirror link
+///
+/// # Arguments
+/// * `link` - Link such as `https://osu.ppy.sh/beatmapsets/123#mania/456` or `https://catboy.best/d/123`
+pub fn parse_set_id(link: &str) -> Option<u32> {
+    let path = link.split(['#', '?']).next()?;
+    path.split('/')
+        .rev()
+        .map(|segment| segment.trim_end_matches(".osz"))
+        .find(|segment| !segment.is_empty() && segment.chars().all(|c| c.is_ascii_digit()))
+        .and_then(|segment| segment.parse().ok())
+}
@@ -0,0 +1,160 @@
+use std::time::{Duration, Instant};
+use futures_util::future::BoxFuture;
+use reqwest::{Client, Response, StatusCode};
+use serde::Deserialize;
+use tokio::sync::Mutex;
+use crate::utils::mirror::{check_response, with_range, Mirror};
+
+/// Downloads from the osef.me API, the server the maps are browsed on
+pub struct OsefMirror {
+    base_url: String,
+}
+
+impl Default for OsefMirror {
+    fn default() -> Self {
+        Self::new("https://osef.me/api")
+    }
+}
+
+impl OsefMirror {
+    pub fn new(base_url: &str) -> Self {
+        Self { base_url: base_url.trim_end_matches('/').to_string() }
+    }
+}
+
+impl Mirror for OsefMirror {
+    fn name(&self) -> &str {
+        "osef.me"
+    }
+
+    fn download<'a>(&'a self, client: &'a Client, set_id: u32, offset: u64) -> BoxFuture<'a, eyre::Result<Response>> {
+        Box::pin(async move {
+            let url = format!("{}/mapset/{}/download", self.base_url, set_id);
+            check_response(with_range(client.get(url), offset).send().await?)
+        })
+    }
+}
+
+/// Community mirror serving the .osz files on a plain URL
+pub struct CommunityMirror {
+    name: String,
+    /// Download URL, `{id}` being replaced by the beatmapset id
+    url_template: String,
+}
+
+impl CommunityMirror {
+    pub fn new(name: &str, url_template: &str) -> Self {
+        Self { name: name.to_string(), url_template: url_template.to_string() }
+    }
+
+    pub fn catboy() -> Self {
+        Self::new("catboy.best", "https://catboy.best/d/{id}")
+    }
+
+    pub fn nerinyan() -> Self {
+        Self::new("Nerinyan", "https://api.nerinyan.moe/d/{id}")
+    }
+
+    pub fn osu_direct() -> Self {
+        Self::new("osu.direct", "https://osu.direct/api/d/{id}")
+    }
+}
+
+impl Mirror for CommunityMirror {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn download<'a>(&'a self, client: &'a Client, set_id: u32, offset: u64) -> BoxFuture<'a, eyre::Result<Response>> {
+        Box::pin(async move {
+            let url = self.url_template.replace("{id}", &set_id.to_string());
+            check_response(with_range(client.get(url), offset).send().await?)
+        })
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct TokenResponse {
+    access_token: String,
+    expires_in: u64,
+}
+
+#[derive(Debug, Clone)]
+struct Token {
+    value: String,
+    expires_at: Instant,
+}
+
+/// Official osu! API v2, authenticated with OAuth client credentials
+///
+/// The token is requested on the first download and renewed when it expires.
+/// osu! may refuse downloads to client credentials tokens, the next mirror is
+/// then used.
+pub struct OsuApiMirror {
+    base_url: String,
+    client_id: String,
+    client_secret: String,
+    token: Mutex<Option<Token>>,
+}
+
+impl OsuApiMirror {
+    pub fn new(client_id: &str, client_secret: &str) -> Self {
+        Self::with_base_url("https://osu.ppy.sh", client_id, client_secret)
+    }
+
+    pub fn with_base_url(base_url: &str, client_id: &str, client_secret: &str) -> Self {
+        Self {
+            base_url: base_url.trim_end_matches('/').to_string(),
+            client_id: client_id.to_string(),
+            client_secret: client_secret.to_string(),
+            token: Mutex::new(None),
+        }
+    }
+
+    /// Returns the current token, requesting a new one when needed
+    async fn token(&self, client: &Client) -> eyre::Result<String> {
+        let mut token = self.token.lock().await;
+        if let Some(token) = token.as_ref().filter(|token| token.expires_at > Instant::now()) {
+            return Ok(token.value.clone());
+        }
+
+        let response = client
+            .post(format!("{}/oauth/token", self.base_url))
+            .form(&[
+                ("client_id", self.client_id.as_str()),
+                ("client_secret", self.client_secret.as_str()),
+                ("grant_type", "client_credentials"),
+                ("scope", "public"),
+            ])
+            .send()
+            .await?;
+        if !response.status().is_success() {
+            return Err(eyre::eyre!("authentification refusée (statut {})", response.status()));
+        }
+        let response: TokenResponse = response.json().await?;
+        // Renewed a minute early so a download never starts with an expired token
+        let lifetime = Duration::from_secs(response.expires_in.saturating_sub(60));
+        *token = Some(Token { value: response.access_token.clone(), expires_at: Instant::now() + lifetime });
+        Ok(response.access_token)
+    }
+}
+
+impl Mirror for OsuApiMirror {
+    fn name(&self) -> &str {
+        "osu! API v2"
+    }
+
+    fn download<'a>(&'a self, client: &'a Client, set_id: u32, offset: u64) -> BoxFuture<'a, eyre::Result<Response>> {
+        Box::pin(async move {
+            let token = self.token(client).await?;
+            let request = client
+                .get(format!("{}/api/v2/beatmapsets/{}/download", self.base_url, set_id))
+                .bearer_auth(token);
+            let response = with_range(request, offset).send().await?;
+            if response.status() == StatusCode::UNAUTHORIZED {
+                *self.token.lock().await = None;
+            }
+            check_response(response)
+        })
+    }
+}
@@ -0,0 +1,109 @@
+use axum::extract::Path as UrlPath;
+use axum::http::{header, HeaderMap, StatusCode};
+use axum::response::IntoResponse;
+use axum::routing::{get, post};
+use axum::{Json, Router};
+use reqwest::Client;
+use crate::utils::mirror::{
+    parse_set_id, CommunityMirror, Mirror, MirrorKind, MirrorList, MirrorSettings, OsefMirror, OsuApiMirror,
+};
+
+/// Starts a local server imitating the mirrors and returns its base URL
+async fn mock_server() -> String {
+    let app = Router::new()
+        .route("/missing/d/:id", get(|| async { StatusCode::NOT_FOUND }))
+        .route(
+            "/html/d/:id",
+            get(|| async { ([(header::CONTENT_TYPE, "text/html")], "<html>Beatmap introuvable</html>") }),
+        )
+        .route("/good/d/:id", get(|UrlPath(id): UrlPath<u32>| async move { format!("osz-{}", id) }))
+        .route(
+            "/osef/mapset/:id/download",
+            get(|UrlPath(id): UrlPath<u32>| async move { format!("osef-{}", id) }),
+        )
+        .route(
+            "/oauth/token",
+            post(|| async { Json(serde_json::json!({ "access_token": "secret-token", "expires_in": 86400, "token_type": "Bearer" })) }),
+        )
+        .route(
+            "/api/v2/beatmapsets/:id/download",
+            get(|UrlPath(id): UrlPath<u32>, headers: HeaderMap| async move {
+                match headers.get(header::AUTHORIZATION).and_then(|value| value.to_str().ok()) {
+                    Some("Bearer secret-token") => format!("api-{}", id).into_response(),
+                    _ => StatusCode::UNAUTHORIZED.into_response(),
+                }
+            }),
+        );
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let address = listener.local_addr().unwrap();
+    tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+    format!("http://{}", address)
+}
+
+fn community(base: &str, route: &str) -> Box<dyn Mirror> {
+    Box::new(CommunityMirror::new(route, &format!("{}/{}/d/{{id}}", base, route)))
+}
+
+#[tokio::test]
+async fn falls_back_to_the_next_mirror() {
+    let base = mock_server().await;
+    let mirrors = MirrorList::new(
+        Client::new(),
+        vec![community(&base, "missing"), community(&base, "html"), community(&base, "good")],
+    );
+
+    let (name, response) = mirrors.open(42, 0).await.unwrap();
+    assert_eq!(name, "good");
+    assert_eq!(response.text().await.unwrap(), "osz-42");
+}
+
+#[tokio::test]
+async fn fails_when_every_mirror_fails() {
+    let base = mock_server().await;
+    let mirrors = MirrorList::new(Client::new(), vec![community(&base, "missing"), community(&base, "html")]);
+
+    let error = mirrors.open(42, 0).await.unwrap_err().to_string();
+    assert!(error.contains("missing") && error.contains("html"));
+}
+
+#[tokio::test]
+async fn downloads_from_osef_and_osu_api() {
+    let base = mock_server().await;
+    let client = Client::new();
+
+    let osef = OsefMirror::new(&format!("{}/osef", base));
+    assert_eq!(osef.download(&client, 7, 0).await.unwrap().text().await.unwrap(), "osef-7");
+
+    let api = OsuApiMirror::with_base_url(&base, "1234", "client-secret");
+    assert_eq!(api.download(&client, 7, 0).await.unwrap().text().await.unwrap(), "api-7");
+    // The token is kept for the next downloads
+    assert_eq!(api.download(&client, 8, 0).await.unwrap().text().await.unwrap(), "api-8");
+}
+
+#[test]
+fn parses_set_ids_from_links() {
+    assert_eq!(parse_set_id("https://osu.ppy.sh/beatmapsets/1234#mania/5678"), Some(1234));
+    assert_eq!(parse_set_id("https://catboy.best/d/1234"), Some(1234));
+    assert_eq!(parse_set_id("https://osef.me/api/mapset/1234/download"), Some(1234));
+    assert_eq!(parse_set_id("https://example.com/1234.osz?noVideo=1"), Some(1234));
+    assert_eq!(parse_set_id("https://example.com/beatmap"), None);
+}
+
+#[test]
+fn settings_order_and_skip_missing_credentials() {
+    let mut settings = MirrorSettings::default();
+    settings.move_mirror(2, -1);
+    settings.move_mirror(0, -1);
+    let kinds: Vec<MirrorKind> = settings.mirrors.iter().map(|mirror| mirror.kind).collect();
+    assert_eq!(kinds[..3], [MirrorKind::Osef, MirrorKind::Nerinyan, MirrorKind::Catboy]);
+
+    settings.mirrors.iter_mut().for_each(|mirror| mirror.enabled = true);
+    let names = MirrorList::from_settings(Client::new(), &settings).names();
+    assert_eq!(names.len(), MirrorKind::ALL.len() - 1);
+
+    settings.osu_client_id = "1234".to_string();
+    settings.osu_client_secret = "secret".to_string();
+    let names = MirrorList::from_settings(Client::new(), &settings).names();
+    assert_eq!(names.last().map(String::as_str), Some("osu! API v2"));
+}
@@ -1,5 +1,12 @@
 pub mod api;
+pub mod data_dir;
+pub mod difficulty;
+pub mod mirror;
+pub mod modes;
+pub mod mods;
+pub mod msd;
 pub mod nps;
 
 pub mod pattern_detector;
-pub mod rate;
\ No newline at end of file
+pub mod rate;
+pub mod scoring;
\ No newline at end of file
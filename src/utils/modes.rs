@@ -0,0 +1,5 @@
+/// Ruleset ids, as stored in memory, osu!.db, scores.db and replays
+pub const OSU_MODE: u8 = 0;
+pub const TAIKO_MODE: u8 = 1;
+pub const CATCH_MODE: u8 = 2;
+pub const MANIA_MODE: u8 = 3;
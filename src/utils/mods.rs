@@ -0,0 +1,91 @@
+pub const NO_FAIL: u32 = 1 << 0;
+pub const EASY: u32 = 1 << 1;
+pub const HIDDEN: u32 = 1 << 3;
+pub const HARD_ROCK: u32 = 1 << 4;
+pub const SUDDEN_DEATH: u32 = 1 << 5;
+pub const DOUBLE_TIME: u32 = 1 << 6;
+pub const HALF_TIME: u32 = 1 << 8;
+pub const NIGHTCORE: u32 = 1 << 9;
+pub const FLASHLIGHT: u32 = 1 << 10;
+pub const AUTOPLAY: u32 = 1 << 11;
+pub const PERFECT: u32 = 1 << 14;
+pub const KEY_4: u32 = 1 << 15;
+pub const KEY_5: u32 = 1 << 16;
+pub const KEY_6: u32 = 1 << 17;
+pub const KEY_7: u32 = 1 << 18;
+pub const KEY_8: u32 = 1 << 19;
+pub const FADE_IN: u32 = 1 << 20;
+pub const RANDOM: u32 = 1 << 21;
+pub const KEY_9: u32 = 1 << 24;
+pub const KEY_COOP: u32 = 1 << 25;
+pub const KEY_1: u32 = 1 << 26;
+pub const KEY_3: u32 = 1 << 27;
+pub const KEY_2: u32 = 1 << 28;
+pub const SCORE_V2: u32 = 1 << 29;
+pub const MIRROR: u32 = 1 << 30;
+
+/// Mods changing the clock rate of the map
+pub const SPEED_MODS: u32 = DOUBLE_TIME | HALF_TIME | NIGHTCORE;
+
+const ACRONYMS: [(u32, &str); 24] = [
+    (NO_FAIL, "NF"),
+    (EASY, "EZ"),
+    (HIDDEN, "HD"),
+    (HARD_ROCK, "HR"),
+    (SUDDEN_DEATH, "SD"),
+    (NIGHTCORE, "NC"),
+    (DOUBLE_TIME, "DT"),
+    (HALF_TIME, "HT"),
+    (FLASHLIGHT, "FL"),
+    (AUTOPLAY, "AT"),
+    (PERFECT, "PF"),
+    (FADE_IN, "FI"),
+    (RANDOM, "RD"),
+    (MIRROR, "MR"),
+    (KEY_COOP, "COOP"),
+    (KEY_1, "1K"),
+    (KEY_2, "2K"),
+    (KEY_3, "3K"),
+    (KEY_4, "4K"),
+    (KEY_5, "5K"),
+    (KEY_6, "6K"),
+    (KEY_7, "7K"),
+    (KEY_8, "8K"),
+    (KEY_9, "9K"),
+];
+
+/// Returns the clock rate implied by the mods (1.5 for DT/NC, 0.75 for HT)
+pub fn clock_rate(mods: u32) -> f64 {
+    if mods & (DOUBLE_TIME | NIGHTCORE) != 0 {
+        1.5
+    } else if mods & HALF_TIME != 0 {
+        0.75
+    } else {
+        1.0
+    }
+}
+
+/// Removes the mods changing the clock rate, used when the rate is given explicitly
+pub fn without_speed_mods(mods: u32) -> u32 {
+    mods & !SPEED_MODS
+}
+
+/// Formats a mods bitmask as acronyms (e.g. "HDDT"), "NM" when no mod is active
+///
+/// NC implies DT and PF implies SD in osu!, only the former is displayed.
+pub fn mods_to_string(mods: u32) -> String {
+    let mut result = String::new();
+    for (bit, acronym) in ACRONYMS {
+        if mods & bit == 0 {
+            continue;
+        }
+        if (bit == DOUBLE_TIME && mods & NIGHTCORE != 0) || (bit == SUDDEN_DEATH && mods & PERFECT != 0) {
+            continue;
+        }
+        result.push_str(acronym);
+    }
+    if result.is_empty() {
+        result.push_str("NM");
+    }
+    result
+}
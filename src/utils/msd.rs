@@ -0,0 +1,75 @@
+use minacalc_rs::{Calc, Note};
+use rosu_map::Beatmap;
+use rosu_map::section::general::GameMode;
+use rosu_map::section::hit_objects::HitObjectKind;
+use std::collections::BTreeMap;
+
+/// Rates computed by MinaCalc, from 0.7x to 2.0x by steps of 0.1
+pub const MSD_RATES: [f64; 14] = [0.7, 0.8, 0.9, 1.0, 1.1, 1.2, 1.3, 1.4, 1.5, 1.6, 1.7, 1.8, 1.9, 2.0];
+
+/// Converts the hit objects of a 4K mania map into MinaCalc rows
+///
+/// # Arguments
+/// * `map` - Parsed beatmap
+fn to_minacalc_notes(map: &Beatmap) -> Vec<Note> {
+    let keys = map.circle_size as usize;
+    let mut rows: BTreeMap<i32, u32> = BTreeMap::new();
+
+    for hit_object in &map.hit_objects {
+        let pos_x = match &hit_object.kind {
+            HitObjectKind::Circle(circle) => circle.pos.x,
+            HitObjectKind::Slider(slider) => slider.pos.x,
+            HitObjectKind::Hold(hold) => hold.pos_x,
+            _ => continue,
+        };
+        let column = ((pos_x * keys as f32 / 512.0).floor() as usize).min(keys - 1);
+        *rows.entry(hit_object.start_time as i32).or_default() |= 1 << column;
+    }
+
+    rows.into_iter()
+        .map(|(time, notes)| Note {
+            notes,
+            row_time: time as f32 / 1000.0,
+        })
+        .collect()
+}
+
+/// Computes the overall MSD of a map for every rate of `MSD_RATES`
+///
+/// Only 4K mania maps are supported by MinaCalc.
+///
+/// # Arguments
+/// * `map` - Parsed beatmap
+pub fn calc_msd(map: &Beatmap) -> eyre::Result<[f64; 14]> {
+    if map.mode != GameMode::Mania || map.circle_size as i32 != 4 {
+        return Err(eyre::eyre!("MSD non supporté : seul le 4K est pris en charge"));
+    }
+
+    let notes = to_minacalc_notes(map);
+    if notes.is_empty() {
+        return Err(eyre::eyre!("Impossible de calculer le MSD d'une map vide"));
+    }
+
+    let calc = Calc::new().map_err(|e| eyre::eyre!("Erreur lors de l'initialisation de MinaCalc: {:?}", e))?;
+    let all_rates = calc.calc_msd(&notes)
+        .map_err(|e| eyre::eyre!("Erreur lors du calcul du MSD: {:?}", e))?;
+
+    let mut result = [0.0; 14];
+    for (i, ssr) in all_rates.msds.iter().enumerate().take(14) {
+        result[i] = ssr.overall as f64;
+    }
+    Ok(result)
+}
+
+/// Returns the MSD for an arbitrary rate, interpolating between MinaCalc rates
+///
+/// # Arguments
+/// * `msds` - MSD values as returned by `calc_msd`
+/// * `rate` - Wanted rate, clamped to 0.7x - 2.0x
+pub fn msd_for_rate(msds: &[f64; 14], rate: f64) -> f64 {
+    let position = ((rate.clamp(0.7, 2.0) - 0.7) * 10.0).max(0.0);
+    let lower = (position.floor() as usize).min(13);
+    let upper = (lower + 1).min(13);
+    let t = position - lower as f64;
+    msds[lower] + (msds[upper] - msds[lower]) * t
+}
@@ -10,8 +10,10 @@ pub struct KeyValue {
     pub value: f64,
 }
 
-pub fn load_file(file_path: &str) -> String {
-    fs::read_to_string(file_path).unwrap()
+/// Reads a .osu file, invalid UTF-8 (old maps in a local encoding) being replaced
+pub fn load_file(file_path: &str) -> eyre::Result<String> {
+    let bytes = fs::read(file_path).wrap_err_with(|| format!("Impossible de lire {}", file_path))?;
+    Ok(String::from_utf8_lossy(&bytes).into_owned())
 }
 
 pub fn read_note(line: &str) -> eyre::Result<i32> {
@@ -85,7 +87,7 @@ pub fn calculate_average_notes_per_second(timings: &[i32], frequency: f64) -> ey
 }
 
 pub fn get_nps(url: &str, frequency: f64) -> eyre::Result<Vec<KeyValue>> {
-    let curl_content = load_file(url);
+    let curl_content = load_file(url)?;
 
     let parsed_hit_objects = parse_hit_objects(&curl_content)
         .wrap_err_with(|| "Erreur lors de l'analyse du fichier .osu")?;
@@ -97,3 +99,23 @@ pub fn get_nps(url: &str, frequency: f64) -> eyre::Result<Vec<KeyValue>> {
     Ok(nps_result)
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_maps_that_are_not_utf8() {
+        let path = std::env::temp_dir().join(format!("cobra_nps_{}.osu", std::process::id()));
+        let mut content = b"[Metadata]\nTitle:Caf\xe9\n\n[HitObjects]\n".to_vec();
+        for time in [0, 250, 500, 1000] {
+            content.extend_from_slice(format!("64,192,{},1,0,0:0:0:0:\n", time).as_bytes());
+        }
+        std::fs::write(&path, content).unwrap();
+
+        let nps = get_nps(path.to_str().unwrap(), 1.0).unwrap();
+        assert_eq!(nps.len(), 100);
+        assert_eq!(nps.first().map(|kv| kv.key), Some(0));
+        std::fs::remove_file(&path).unwrap();
+        assert!(load_file(path.to_str().unwrap()).is_err());
+    }
+}
@@ -51,7 +51,10 @@ pub(crate) fn transform_hit_object_to_mania_notes(
 pub(crate) fn group_notes_by_measures(
     notes: Vec<Notes>,
     timing_points: Vec<TimingPoint>,
-) -> BTreeMap<i32, ManiaMeasure> {
+) -> eyre::Result<BTreeMap<i32, ManiaMeasure>> {
+    let first_timing_point = timing_points
+        .first()
+        .ok_or_else(|| eyre::eyre!("Aucun timing point dans la map"))?;
     let mut measures = BTreeMap::new();
 
     for note in notes {
@@ -59,7 +62,7 @@ pub(crate) fn group_notes_by_measures(
             .iter()
             .rev()
             .find(|tp| note.timestamp >= tp.time as i32)
-            .unwrap_or_else(|| timing_points.first().unwrap());
+            .unwrap_or(first_timing_point);
 
         let beat_len = timing_point.beat_len as f32;
         let start_time = timing_point.time as i32;
@@ -82,5 +85,5 @@ pub(crate) fn group_notes_by_measures(
         measure_entry.measure.npm += note.notes.iter().filter(|&&n| n).count() as i32;
     }
 
-    measures
+    Ok(measures)
 }
\ No newline at end of file
@@ -17,12 +17,63 @@ pub fn download_file(url: &str) -> Result<String, Box<dyn Error>> {
 }
 
 pub(crate) fn get_patterns(path: &str) -> Result<Value, eyre::Report> {
-    let map = rosu_map::from_path::<Beatmap>(&path).unwrap();
+    let map = rosu_map::from_path::<Beatmap>(&path)
+        .map_err(|e| eyre::eyre!("Impossible de lire la map {}: {}", path, e))?;
     if (map.mode == rosu_map::section::general::GameMode::Mania) {
-        let result_json = mania::transformers(map);
-        Ok(result_json)
+        mania::transformers(map)
     }
     else{
         Err(eyre::eyre!("Mode de jeu non supporté : seul Mania est pris en charge"))
     }
 }
+
+/// Pattern families offered in the filters, with the detector patterns each one covers
+pub(crate) const PATTERN_FAMILIES: [(&str, &[&str]); 7] = [
+    ("Jack", &["Chordjack", "Dense Chordjack", "Speedjack"]),
+    ("Chordjack", &["Chordjack", "Dense Chordjack"]),
+    ("Speedjack", &["Speedjack"]),
+    ("Jumpstream", &["JS", "Light JS", "Anchor JS", "JT"]),
+    ("Handstream", &["HS", "Light HS", "Anchor HS", "Dense HS"]),
+    ("Chordstream", &["ChordStream"]),
+    ("Singlestream", &["Singlestream"]),
+];
+
+/// Whether a pattern found by the detector belongs to a family of `PATTERN_FAMILIES`
+///
+/// A name that is not a family only matches the same detector pattern.
+///
+/// # Arguments
+/// * `pattern` - Detector pattern, as in `dominant_patterns` (ex: "Light JS")
+/// * `family` - Family or detector pattern searched, case insensitive
+pub(crate) fn pattern_in_family(pattern: &str, family: &str) -> bool {
+    match PATTERN_FAMILIES.iter().find(|(name, _)| name.eq_ignore_ascii_case(family)) {
+        Some((_, members)) => members.iter().any(|member| member.eq_ignore_ascii_case(pattern)),
+        None => pattern.eq_ignore_ascii_case(family),
+    }
+}
+
+/// Strongest patterns of a `get_patterns` result, within 80% of the strongest one
+///
+/// # Arguments
+/// * `json_value` - Result of `get_patterns`
+///
+/// # Returns
+/// Names of the patterns, strongest first
+pub(crate) fn dominant_patterns(json_value: &Value) -> Vec<String> {
+    let Some(tertiary) = json_value.get("TertiaryPattern").and_then(|v| v.as_object()) else {
+        return Vec::new();
+    };
+    let mut values: Vec<(String, f64)> = tertiary
+        .iter()
+        .filter_map(|(key, val)| val.as_f64().map(|v| (key.clone(), v)))
+        .filter(|(_k, v)| *v > 0.0)
+        .collect();
+    values.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal));
+
+    let max_value = values.first().map(|(_k, v)| *v).unwrap_or_default();
+    values
+        .into_iter()
+        .filter(|(_k, v)| *v >= max_value * 0.8)
+        .map(|(k, _v)| k)
+        .collect()
+}
@@ -181,6 +181,22 @@ pub fn convert_wav_to_ogg(input_wav: &str, output_ogg: &str) -> eyre::Result<()>
     Ok(())
 }
 
+/// Reads the rate of a difficulty created by `change_osu_speed` from its name
+///
+/// # Arguments
+/// * `version` - Difficulty name, e.g. "Insane 1.20x"
+///
+/// # Returns
+/// The rate, or None when the difficulty is not a rate change
+pub fn rate_from_version(version: &str) -> Option<f64> {
+    version
+        .rsplit(' ')
+        .next()?
+        .strip_suffix('x')?
+        .parse::<f64>()
+        .ok()
+}
+
 pub fn change_osu_speed(input_path: &str, rate: f32, audio_path: &str) {
     let mut map: Beatmap = rosu_map::from_path(input_path).unwrap();
 
@@ -0,0 +1,245 @@
+use crate::reader::structs::Hit;
+use crate::utils::modes::{CATCH_MODE, MANIA_MODE, OSU_MODE, TAIKO_MODE};
+
+/// Timing scale of Etterna judge 4, the default judge of Wife3
+pub const WIFE3_J4: f64 = 1.0;
+
+/// Points of a perfectly timed note in Wife3
+const WIFE3_MAX_POINTS: f64 = 2.0;
+/// Points of a miss in Wife3
+const WIFE3_MISS_WEIGHT: f64 = -5.5;
+
+/// Computes the classic osu! (ScoreV1) accuracy of a play, between 0.0 and 1.0
+///
+/// # Arguments
+/// * `mode` - Ruleset id (0 osu!, 1 taiko, 2 catch, 3 mania)
+/// * `hit` - Judgements of the play
+///
+/// # Returns
+/// The accuracy, 1.0 when nothing was judged yet, or an error for unknown modes
+pub fn accuracy_v1(mode: u8, hit: &Hit) -> eyre::Result<f64> {
+    let geki = hit._geki as f64;
+    let n300 = hit._300 as f64;
+    let katu = hit._katu as f64;
+    let n100 = hit._100 as f64;
+    let n50 = hit._50 as f64;
+    let miss = hit._miss as f64;
+
+    let (numerator, denominator) = match mode {
+        OSU_MODE => (
+            n300 * 6.0 + n100 * 2.0 + n50,
+            (n300 + n100 + n50 + miss) * 6.0,
+        ),
+        TAIKO_MODE => (
+            n300 * 2.0 + n100,
+            (n300 + n100 + n50 + miss) * 2.0,
+        ),
+        CATCH_MODE => (
+            n300 + n100 + n50,
+            n300 + n100 + n50 + katu + miss,
+        ),
+        MANIA_MODE => (
+            (geki + n300) * 6.0 + katu * 4.0 + n100 * 2.0 + n50,
+            (geki + n300 + katu + n100 + n50 + miss) * 6.0,
+        ),
+        _ => return Err(eyre::eyre!("Mode de jeu non supporté : {}", mode)),
+    };
+
+    if denominator == 0.0 {
+        return Ok(1.0);
+    }
+    Ok(numerator / denominator)
+}
+
+/// Computes the ScoreV2 accuracy of a mania play, where MAX judgements are worth more than 300s
+///
+/// # Arguments
+/// * `hit` - Judgements of the play
+///
+/// # Returns
+/// The accuracy between 0.0 and 1.0, 1.0 when nothing was judged yet
+pub fn mania_accuracy_v2(hit: &Hit) -> f64 {
+    let geki = hit._geki as f64;
+    let n300 = hit._300 as f64;
+    let katu = hit._katu as f64;
+    let n100 = hit._100 as f64;
+    let n50 = hit._50 as f64;
+    let miss = hit._miss as f64;
+
+    let total = geki + n300 + katu + n100 + n50 + miss;
+    if total == 0.0 {
+        return 1.0;
+    }
+    (geki * 305.0 + n300 * 300.0 + katu * 200.0 + n100 * 100.0 + n50 * 50.0) / (total * 305.0)
+}
+
+/// Computes the osu! grade of a play ("SS", "S", "A", "B", "C" or "D")
+///
+/// # Arguments
+/// * `mode` - Ruleset id (0 osu!, 1 taiko, 2 catch, 3 mania)
+/// * `hit` - Judgements of the play
+pub fn grade(mode: u8, hit: &Hit) -> eyre::Result<&'static str> {
+    let accuracy = accuracy_v1(mode, hit)?;
+    if accuracy >= 1.0 {
+        return Ok("SS");
+    }
+
+    let grade = match mode {
+        OSU_MODE | TAIKO_MODE => {
+            let total = (hit._300 as f64 + hit._100 as f64 + hit._50 as f64 + hit._miss as f64).max(1.0);
+            let ratio_300 = hit._300 as f64 / total;
+            let ratio_50 = hit._50 as f64 / total;
+            let no_miss = hit._miss == 0;
+            if ratio_300 > 0.9 && ratio_50 <= 0.01 && no_miss {
+                "S"
+            } else if (ratio_300 > 0.8 && no_miss) || ratio_300 > 0.9 {
+                "A"
+            } else if (ratio_300 > 0.7 && no_miss) || ratio_300 > 0.8 {
+                "B"
+            } else if ratio_300 > 0.6 {
+                "C"
+            } else {
+                "D"
+            }
+        }
+        CATCH_MODE => match accuracy {
+            a if a > 0.98 => "S",
+            a if a > 0.94 => "A",
+            a if a > 0.90 => "B",
+            a if a > 0.85 => "C",
+            _ => "D",
+        },
+        _ => match accuracy {
+            a if a > 0.95 => "S",
+            a if a > 0.90 => "A",
+            a if a > 0.80 => "B",
+            a if a > 0.70 => "C",
+            _ => "D",
+        },
+    };
+    Ok(grade)
+}
+
+/// Points given by Wife3 to a note hit `offset` ms away from its time
+///
+/// # Arguments
+/// * `offset` - Hit error in ms, in real time
+/// * `judge` - Timing scale of the judge (`WIFE3_J4` for judge 4)
+pub fn wife3_points(offset: f64, judge: f64) -> f64 {
+    let offset = offset.abs();
+    let ridiculous = 5.0 * judge;
+    let max_boo_weight = 180.0 * judge;
+    let zero = 65.0 * judge.powf(0.75);
+    let deviation = 22.7 * judge.powf(0.75);
+
+    if offset <= ridiculous {
+        WIFE3_MAX_POINTS
+    } else if offset <= zero {
+        WIFE3_MAX_POINTS * erf((zero - offset) / deviation)
+    } else if offset <= max_boo_weight {
+        (offset - zero) * WIFE3_MISS_WEIGHT / (max_boo_weight - zero)
+    } else {
+        WIFE3_MISS_WEIGHT
+    }
+}
+
+/// Estimates the Etterna Wife3 accuracy of a play from its hit errors
+///
+/// osu! only records hit errors of the notes that were hit, misses are added
+/// from the judgements. Hold releases are judged like taps, so the result is
+/// an estimate on maps with long notes.
+///
+/// # Arguments
+/// * `hit_errors` - Hit errors of the play in ms, as recorded by osu!
+/// * `misses` - Number of misses
+/// * `rate` - Clock rate of the play, hit errors are converted to real time
+/// * `judge` - Timing scale of the judge (`WIFE3_J4` for judge 4)
+///
+/// # Returns
+/// The accuracy (can be negative with many misses), None when no hit error is available
+pub fn wife3_accuracy(hit_errors: &[i32], misses: u32, rate: f64, judge: f64) -> Option<f64> {
+    if hit_errors.is_empty() {
+        return None;
+    }
+    let rate = if rate > 0.0 { rate } else { 1.0 };
+    let points: f64 = hit_errors
+        .iter()
+        .map(|&error| wife3_points(error as f64 / rate, judge))
+        .sum::<f64>()
+        + misses as f64 * WIFE3_MISS_WEIGHT;
+    let notes = (hit_errors.len() as u32 + misses) as f64;
+    Some(points / (notes * WIFE3_MAX_POINTS))
+}
+
+/// Error function, Abramowitz and Stegun approximation 7.1.26 (error below 1.5e-7)
+fn erf(x: f64) -> f64 {
+    let sign = if x < 0.0 { -1.0 } else { 1.0 };
+    let x = x.abs();
+    let t = 1.0 / (1.0 + 0.3275911 * x);
+    let y = 1.0
+        - (((((1.061405429 * t - 1.453152027) * t) + 1.421413741) * t - 0.284496736) * t + 0.254829592)
+            * t
+            * (-x * x).exp();
+    sign * y
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn mania_hits() -> Hit {
+        Hit { _geki: 100, _300: 50, _katu: 10, _100: 4, _50: 1, _miss: 2 }
+    }
+
+    #[test]
+    fn unknown_mode_is_an_error() {
+        assert!(accuracy_v1(4, &mania_hits()).is_err());
+    }
+
+    #[test]
+    fn empty_play_is_perfect() {
+        assert_eq!(accuracy_v1(3, &Hit::default()).unwrap(), 1.0);
+        assert_eq!(mania_accuracy_v2(&Hit::default()), 1.0);
+    }
+
+    #[test]
+    fn mania_v2_rewards_max_judgements() {
+        let hits = mania_hits();
+        let v1 = accuracy_v1(3, &hits).unwrap();
+        let v2 = mania_accuracy_v2(&hits);
+        assert!((v1 - 949.0 / 1002.0).abs() < 1e-9);
+        assert!((v2 - 47950.0 / 50935.0).abs() < 1e-9);
+        assert_eq!(mania_accuracy_v2(&Hit { _geki: 10, ..Default::default() }), 1.0);
+    }
+
+    #[test]
+    fn large_judgement_counts_do_not_overflow() {
+        let hits = Hit { _geki: i16::MAX, _300: i16::MAX, ..Default::default() };
+        assert_eq!(accuracy_v1(3, &hits).unwrap(), 1.0);
+    }
+
+    #[test]
+    fn mania_grades() {
+        assert_eq!(grade(3, &Hit { _geki: 10, ..Default::default() }).unwrap(), "SS");
+        assert_eq!(grade(3, &mania_hits()).unwrap(), "A");
+        assert!(grade(7, &mania_hits()).is_err());
+    }
+
+    #[test]
+    fn wife3_curve() {
+        assert_eq!(wife3_points(3.0, WIFE3_J4), 2.0);
+        assert_eq!(wife3_points(-200.0, WIFE3_J4), WIFE3_MISS_WEIGHT);
+        assert!(wife3_points(30.0, WIFE3_J4) > 0.0 && wife3_points(30.0, WIFE3_J4) < 2.0);
+        assert!(wife3_points(100.0, WIFE3_J4) < 0.0);
+    }
+
+    #[test]
+    fn wife3_accuracy_from_hit_errors() {
+        assert_eq!(wife3_accuracy(&[], 3, 1.0, WIFE3_J4), None);
+        assert_eq!(wife3_accuracy(&[0, 2, -4], 0, 1.0, WIFE3_J4), Some(1.0));
+        let with_miss = wife3_accuracy(&[0, 0, 0], 1, 1.0, WIFE3_J4).unwrap();
+        assert!((with_miss - (6.0 - 5.5) / 8.0).abs() < 1e-9);
+        // 9ms in game time at 1.5x is 6ms in real time
+        assert!(wife3_accuracy(&[9], 0, 1.5, WIFE3_J4).unwrap() > wife3_accuracy(&[9], 0, 1.0, WIFE3_J4).unwrap());
+    }
+}